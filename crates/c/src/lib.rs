@@ -1,4 +1,7 @@
-mod component_type_object;
+pub mod component_type_object;
+mod split;
+
+pub use split::{is_export_copy, split_resources};
 
 use anyhow::Result;
 use heck::*;
@@ -7,7 +10,8 @@ use std::fmt::Write;
 use std::mem;
 use wit_bindgen_core::abi::{self, AbiVariant, Bindgen, Bitcast, Instruction, LiftLower, WasmType};
 use wit_bindgen_core::{
-    uwrite, uwriteln, wit_parser::*, Files, InterfaceGenerator as _, Ns, WorldGenerator,
+    uwrite, uwriteln, wit_parser::*, CoreSymbols, Files, InterfaceGenerator as _, Ns, WitItem,
+    WorldGenerator,
 };
 use wit_component::StringEncoding;
 
@@ -68,6 +72,13 @@ struct C {
     // implementation of functions. These types go in the implementation file,
     // not the header file.
     private_anonymous_types: BTreeSet<TypeId>,
+
+    // A copy of the `Resolve` being generated where interfaces which are both
+    // imported and exported and define resources have been split in two, if
+    // there are any, along with the copy of each such interface that its
+    // export is generated from. See the `split` module for more details.
+    split: Option<Resolve>,
+    export_copies: HashMap<InterfaceId, InterfaceId>,
 }
 
 #[derive(Default, Debug, Clone)]
@@ -115,10 +126,22 @@ enum Scalar {
 }
 
 impl WorldGenerator for C {
-    fn preprocess(&mut self, resolve: &Resolve, world: WorldId) {
+    fn preprocess(&mut self, resolve: &Resolve, world: WorldId) -> Result<()> {
         let name = &resolve.worlds[world].name;
         self.world = name.to_string();
-        self.sizes.fill(resolve);
+
+        if let Some((split, copies)) = split::split_resources(resolve, world) {
+            self.split = Some(split);
+            self.export_copies = copies;
+        }
+        self.sizes.fill(self.split.as_ref().unwrap_or(resolve));
+        Ok(())
+    }
+
+    fn core_symbols(&self, resolve: &Resolve, world: WorldId) -> CoreSymbols {
+        let mut symbols = CoreSymbols::new(resolve, world);
+        symbols.export_realloc(resolve, world);
+        symbols
     }
 
     fn import_interface(
@@ -174,21 +197,20 @@ impl WorldGenerator for C {
         id: InterfaceId,
         _files: &mut Files,
     ) -> Result<()> {
-        self.interface_names.insert(id, name.clone());
+        // If this interface is also imported and defines resources then the
+        // export is generated from a separate copy of it, which is keyed by its
+        // own id so its types get their own namespace.
+        let split = self.split.take();
+        let (resolve, id, key) = match self.export_copies.get(&id) {
+            Some(copy) => (split.as_ref().unwrap(), *copy, WorldKey::Interface(*copy)),
+            None => (resolve, id, name.clone()),
+        };
+
+        self.interface_names.insert(id, key);
         let mut gen = self.interface(resolve, false);
         gen.interface = Some(id);
         if gen.gen.interfaces_with_types_printed.insert(id) {
             gen.types(id);
-        } else {
-            let iface = &resolve.interfaces[id];
-            for id in iface.types.values() {
-                if let TypeDefKind::Resource = &resolve.types[*id].kind {
-                    // This will require a substantial refactor so we can
-                    // generate two sets of types and helper functions, one for
-                    // the imported types and one for the exported types.
-                    todo!("importing and exporting the same interface containing a resource not yet supported");
-                }
-            }
         }
 
         for (i, (_name, func)) in resolve.interfaces[id].functions.iter().enumerate() {
@@ -200,6 +222,7 @@ impl WorldGenerator for C {
         }
 
         gen.gen.src.append(&gen.src);
+        self.split = split;
         Ok(())
     }
 
@@ -239,6 +262,13 @@ impl WorldGenerator for C {
     }
 
     fn finish(&mut self, resolve: &Resolve, id: WorldId, files: &mut Files) {
+        // The component type describes the world as written, but everything
+        // else needs to see the copies of any split interfaces.
+        let split = self.split.take();
+        let component_type =
+            component_type_object::object(resolve, id, self.opts.string_encoding).unwrap();
+        let resolve = split.as_ref().unwrap_or(resolve);
+
         self.finish_types(resolve);
 
         let world = &resolve.worlds[id];
@@ -260,48 +290,30 @@ impl WorldGenerator for C {
         if self.needs_string {
             self.include("<string.h>");
             let (strlen, size) = match self.opts.string_encoding {
-                StringEncoding::UTF8 => (format!("strlen(s)"), 1),
+                StringEncoding::UTF8 | StringEncoding::CompactUTF16 => (format!("strlen(s)"), 1),
                 StringEncoding::UTF16 => {
-                    self.include("<uchar.h>");
-                    uwrite!(
-                        self.src.h_helpers,
-                        "
-                            size_t {snake}_string_len(const char16_t* s);
-                        ",
-                    );
-                    uwrite!(
-                        self.src.c_helpers,
-                        "
-                            size_t {snake}_string_len(const char16_t* s) {{
-                                char16_t* c = (char16_t*)s;
-                                for (; *c; ++c);
-                                return c-s;
-                            }}
-                        ",
-                    );
+                    self.print_string_len_utf16();
                     (format!("{snake}_string_len(s)"), 2)
                 }
-                StringEncoding::CompactUTF16 => unimplemented!(),
             };
             let ty = self.char_type();
             let c_string_ty = match self.opts.string_encoding {
-                StringEncoding::UTF8 => "char",
+                StringEncoding::UTF8 | StringEncoding::CompactUTF16 => "char",
                 StringEncoding::UTF16 => "char16_t",
-                StringEncoding::CompactUTF16 => panic!("Compact UTF16 unsupported"),
+            };
+            let encoding = match self.opts.string_encoding {
+                StringEncoding::CompactUTF16 => "Latin-1 ",
+                _ => "",
             };
             uwrite!(
                 self.src.h_helpers,
                 "
-                   // Transfers ownership of `s` into the string `ret`
+                   // Transfers ownership of the {encoding}string `s` into the string `ret`
                    void {snake}_string_set({snake}_string_t *ret, {c_string_ty} *s);
 
-                   // Creates a copy of the input nul-terminate string `s` and
+                   // Creates a copy of the input nul-terminate {encoding}string `s` and
                    // stores it into the component model string `ret`.
                    void {snake}_string_dup({snake}_string_t *ret, const {c_string_ty} *s);
-
-                   // Deallocates the string pointed to by `ret`, deallocating
-                   // the memory behind the string.
-                   void {snake}_string_free({snake}_string_t *ret);\
                ",
             );
             uwrite!(
@@ -317,7 +329,52 @@ impl WorldGenerator for C {
                        ret->ptr = cabi_realloc(NULL, 0, {size}, ret->len * {size});
                        memcpy(ret->ptr, s, ret->len * {size});
                    }}
+               ",
+            );
+            if self.opts.string_encoding == StringEncoding::CompactUTF16 {
+                self.print_string_len_utf16();
+                let tag = format!("{}_STRING_UTF16_TAG", snake.to_shouty_snake_case());
+                uwrite!(
+                    self.src.h_helpers,
+                    "
+
+                       // Transfers ownership of the UTF-16 string `s` into the string `ret`
+                       void {snake}_string_set_utf16({snake}_string_t *ret, char16_t *s);
+
+                       // Creates a copy of the input nul-terminated UTF-16 string `s` and
+                       // stores it into the component model string `ret`.
+                       void {snake}_string_dup_utf16({snake}_string_t *ret, const char16_t *s);
+                   ",
+                );
+                uwrite!(
+                    self.src.c_helpers,
+                    "
+                       void {snake}_string_set_utf16({snake}_string_t *ret, char16_t *s) {{
+                           ret->ptr = (uint8_t*) s;
+                           ret->len = {snake}_string_len(s) | {tag};
+                       }}
 
+                       void {snake}_string_dup_utf16({snake}_string_t *ret, const char16_t *s) {{
+                           size_t len = {snake}_string_len(s);
+                           ret->len = len | {tag};
+                           ret->ptr = cabi_realloc(NULL, 0, 2, len * 2);
+                           memcpy(ret->ptr, s, len * 2);
+                       }}
+                   ",
+                );
+            }
+            uwrite!(
+                self.src.h_helpers,
+                "
+
+                   // Deallocates the string pointed to by `ret`, deallocating
+                   // the memory behind the string.
+                   void {snake}_string_free({snake}_string_t *ret);\
+               ",
+            );
+            uwrite!(
+                self.src.c_helpers,
+                "
                    void {snake}_string_free({snake}_string_t *ret) {{
                        if (ret->len > 0) {{
                            free(ret->ptr);
@@ -363,6 +420,16 @@ impl WorldGenerator for C {
         c_str.push_str(&self.src.c_fns);
 
         if self.needs_string {
+            if self.opts.string_encoding == StringEncoding::CompactUTF16 {
+                uwriteln!(
+                    h_str,
+                    "
+                    // Strings are either Latin-1 or UTF-16. UTF-16 strings have this bit set
+                    // in `len`, and the rest of `len` is the number of code units in `ptr`.
+                    #define {}_STRING_UTF16_TAG ((size_t) 1 << 31)",
+                    snake.to_shouty_snake_case(),
+                );
+            }
             uwriteln!(
                 h_str,
                 "
@@ -424,10 +491,15 @@ impl WorldGenerator for C {
         files.push(&format!("{snake}.h"), h_str.as_bytes());
         files.push(
             &format!("{snake}_component_type.o",),
-            component_type_object::object(resolve, id, self.opts.string_encoding)
-                .unwrap()
-                .as_slice(),
+            component_type.as_slice(),
         );
+        for name in [
+            format!("{snake}.c"),
+            format!("{snake}.h"),
+            format!("{snake}_component_type.o"),
+        ] {
+            files.set_source(&name, WitItem::world(resolve, id));
+        }
     }
 }
 
@@ -450,11 +522,32 @@ impl C {
         self.includes.push(s.to_string());
     }
 
+    fn print_string_len_utf16(&mut self) {
+        let snake = self.world.to_snake_case();
+        self.include("<uchar.h>");
+        uwrite!(
+            self.src.h_helpers,
+            "
+                size_t {snake}_string_len(const char16_t* s);
+            ",
+        );
+        uwrite!(
+            self.src.c_helpers,
+            "
+                size_t {snake}_string_len(const char16_t* s) {{
+                    char16_t* c = (char16_t*)s;
+                    for (; *c; ++c);
+                    return c-s;
+                }}
+            ",
+        );
+    }
+
     fn char_type(&self) -> &'static str {
         match self.opts.string_encoding {
             StringEncoding::UTF8 => "uint8_t",
             StringEncoding::UTF16 => "uint16_t",
-            StringEncoding::CompactUTF16 => panic!("Compact UTF16 unsupported"),
+            StringEncoding::CompactUTF16 => "uint8_t",
         }
     }
 
@@ -529,6 +622,13 @@ impl C {
             let name = resolve.types[*id].name.as_deref().unwrap();
             let snake = name.to_snake_case();
             let module = self.owner_wasm_namespace(resolve, *id);
+            // Intrinsics for exported resources are imported from a separate
+            // module so they're distinct from those of an imported resource of
+            // the same interface.
+            let intrinsics_module = match info.direction {
+                Direction::Import => module.clone(),
+                Direction::Export => format!("[export]{module}"),
+            };
 
             for s in [&mut *h_str, &mut *c_str] {
                 uwriteln!(
@@ -566,7 +666,7 @@ impl C {
 
                 uwriteln!(
                     c_str,
-                    r#"__attribute__((__import_module__("{module}"), __import_name__("[resource-drop]{name}")))
+                    r#"__attribute__((__import_module__("{intrinsics_module}"), __import_name__("[resource-drop]{name}")))
                        void __wasm_import_{namespace}_{snake}_drop_own(int32_t);
 
                        void {namespace}_{snake}_drop_own({own_name}{space}arg) {{
@@ -606,7 +706,7 @@ impl C {
 
                 uwriteln!(
                     c_str,
-                    r#"__attribute__((__import_module__("{module}"), __import_name__("[resource-new]{name}")))
+                    r#"__attribute__((__import_module__("{intrinsics_module}"), __import_name__("[resource-new]{name}")))
                        int32_t __wasm_import_{namespace}_{snake}_new(int32_t);
 
                        {own_name} {namespace}_{snake}_new({namespace}_{snake}_t* arg) {{
@@ -621,7 +721,7 @@ impl C {
 
                 uwriteln!(
                     c_str,
-                    r#"__attribute__((__import_module__("{module}"), __import_name__("[resource-rep]{snake}")))
+                    r#"__attribute__((__import_module__("{intrinsics_module}"), __import_name__("[resource-rep]{name}")))
                        int32_t __wasm_import_{namespace}_{snake}_rep(int32_t);
 
                        {namespace}_{snake}_t* {namespace}_{snake}_rep({own_name}{space}arg) {{
@@ -636,7 +736,7 @@ impl C {
 
                 uwriteln!(
                     c_str,
-                    r#"__attribute__((__export_name__("{module}#[dtor]{snake}")))
+                    r#"__attribute__((__export_name__("{module}#[dtor]{name}")))
                        void __wasm_export_{namespace}_{snake}_dtor({namespace}_{snake}_t* arg) {{
                            {namespace}_{snake}_destructor(arg);
                        }}"#
@@ -1282,16 +1382,49 @@ pub fn owner_namespace(
             false,
         )),
         TypeOwner::World(owner) => Some(resolve.worlds[owner].name.to_snake_case()),
-        TypeOwner::None => None,
+        TypeOwner::None => match ty.kind {
+            // Handles to resources of an interface that's split in two are
+            // namespaced under their interface so the handles of the import
+            // and export sides don't share a name.
+            TypeDefKind::Handle(Handle::Own(resource) | Handle::Borrow(resource)) => {
+                match resolve.types[dealias(resolve, resource)].owner {
+                    TypeOwner::Interface(owner) if split::is_export_copy(resolve, owner) => Some(
+                        interface_identifier(&WorldKey::Interface(owner), resolve, false),
+                    ),
+                    _ => None,
+                }
+            }
+            _ => None,
+        },
+    }
+}
+
+/// Returns the C name of `func`, which the generated `__wasm_import_*`,
+/// `__wasm_export_*` and `cabi_post_*` symbols are derived from.
+pub fn func_name(
+    resolve: &Resolve,
+    world: &str,
+    interface_id: Option<&WorldKey>,
+    func: &Function,
+    in_export: bool,
+) -> String {
+    let mut name = String::new();
+    match interface_id {
+        Some(id) => name.push_str(&interface_identifier(id, resolve, in_export)),
+        None => name.push_str(&world.to_snake_case()),
     }
+    name.push_str("_");
+    name.push_str(&func.name.to_snake_case().replace('.', "_"));
+    name
 }
 
-fn interface_identifier(interface_id: &WorldKey, resolve: &Resolve, in_export: bool) -> String {
+/// Returns the C identifier prefixing the items of the interface `interface_id`.
+pub fn interface_identifier(interface_id: &WorldKey, resolve: &Resolve, in_export: bool) -> String {
     match interface_id {
         WorldKey::Name(name) => name.to_snake_case(),
         WorldKey::Interface(id) => {
             let mut ns = String::new();
-            if in_export {
+            if in_export || split::is_export_copy(resolve, *id) {
                 ns.push_str("exports_");
             }
             let iface = &resolve.interfaces[*id];
@@ -1646,6 +1779,16 @@ impl<'a> wit_bindgen_core::InterfaceGenerator<'a> for InterfaceGenerator<'a> {
         self.finish_ty(id, prev);
     }
 
+    fn type_future(&mut self, id: TypeId, name: &str, ty: &Option<Type>, docs: &Docs) {
+        let _ = (id, name, ty, docs);
+        unreachable!("`future` types are rejected by `supports_async_types`")
+    }
+
+    fn type_stream(&mut self, id: TypeId, name: &str, stream: &Stream, docs: &Docs) {
+        let _ = (id, name, stream, docs);
+        unreachable!("`stream` types are rejected by `supports_async_types`")
+    }
+
     fn type_builtin(&mut self, id: TypeId, name: &str, ty: &Type, docs: &Docs) {
         let _ = (id, name, ty, docs);
     }
@@ -1653,14 +1796,13 @@ impl<'a> wit_bindgen_core::InterfaceGenerator<'a> for InterfaceGenerator<'a> {
 
 impl InterfaceGenerator<'_> {
     fn c_func_name(&self, interface_id: Option<&WorldKey>, func: &Function) -> String {
-        let mut name = String::new();
-        match interface_id {
-            Some(id) => name.push_str(&interface_identifier(id, &self.resolve, !self.in_import)),
-            None => name.push_str(&self.gen.world.to_snake_case()),
-        }
-        name.push_str("_");
-        name.push_str(&func.name.to_snake_case().replace('.', "_"));
-        name
+        func_name(
+            self.resolve,
+            &self.gen.world,
+            interface_id,
+            func,
+            !self.in_import,
+        )
     }
 
     fn import(&mut self, interface_name: Option<&WorldKey>, func: &Function) {
@@ -0,0 +1,236 @@
+//! Support for interfaces which are both imported and exported by a world and
+//! define resources.
+//!
+//! The C bindings otherwise share one set of types between the import and the
+//! export of an interface, but an imported resource is represented with
+//! handles while an exported resource is a user-defined struct. To keep the
+//! two apart the export is instead generated from a copy of the interface, and
+//! the copy's types and functions are named with an `exports_` prefix.
+
+use std::collections::HashMap;
+use wit_bindgen_core::wit_parser::*;
+
+/// Returns a copy of `resolve` where each interface that `world` both imports
+/// and exports, and which defines a resource, has been duplicated, along with
+/// a map from each such interface to its duplicate.
+///
+/// The duplicate owns copies of all of the original interface's types, and of
+/// any anonymous types which refer to them, so none of them are shared with
+/// the import. Other exported interfaces which use types from a duplicated
+/// interface are duplicated as well so that they refer to the exported
+/// versions of those types. Returns `None` if the world has no such
+/// interfaces.
+pub fn split_resources(
+    resolve: &Resolve,
+    world: WorldId,
+) -> Option<(Resolve, HashMap<InterfaceId, InterfaceId>)> {
+    let world = &resolve.worlds[world];
+    let imported = |id: InterfaceId| {
+        world
+            .imports
+            .values()
+            .any(|item| matches!(item, WorldItem::Interface(i) if *i == id))
+    };
+    let has_resource = |id: InterfaceId| {
+        resolve.interfaces[id]
+            .types
+            .values()
+            .any(|ty| matches!(resolve.types[*ty].kind, TypeDefKind::Resource))
+    };
+    let uses = |id: InterfaceId, split: &[InterfaceId]| {
+        resolve.interfaces[id]
+            .types
+            .values()
+            .any(|ty| match resolve.types[*ty].kind {
+                TypeDefKind::Type(Type::Id(used)) => matches!(
+                    resolve.types[used].owner,
+                    TypeOwner::Interface(owner) if split.contains(&owner)
+                ),
+                _ => false,
+            })
+    };
+    let exports = world
+        .exports
+        .values()
+        .filter_map(|item| match item {
+            WorldItem::Interface(id) => Some(*id),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let mut split = exports
+        .iter()
+        .copied()
+        .filter(|id| imported(*id) && has_resource(*id))
+        .collect::<Vec<_>>();
+    if split.is_empty() {
+        return None;
+    }
+    loop {
+        let used = exports
+            .iter()
+            .copied()
+            .filter(|id| !split.contains(id) && uses(*id, &split))
+            .collect::<Vec<_>>();
+        if used.is_empty() {
+            break;
+        }
+        split.extend(used);
+    }
+
+    // Interfaces are allocated after the interfaces they use, so copying them
+    // in that order means used types have always been copied already.
+    split.sort_by_key(|id| id.index());
+
+    let mut resolve = resolve.clone();
+    let mut copier = Copier {
+        resolve: &mut resolve,
+        types: HashMap::new(),
+    };
+    let copies = split
+        .into_iter()
+        .map(|id| (id, copier.interface(id)))
+        .collect();
+    Some((resolve, copies))
+}
+
+/// Returns whether `id` is a duplicate made by `split_resources`.
+///
+/// Duplicates keep the name and package of the original interface so they're
+/// imported from and exported under the same names, but the package only
+/// lists the original.
+pub fn is_export_copy(resolve: &Resolve, id: InterfaceId) -> bool {
+    let iface = &resolve.interfaces[id];
+    match (&iface.name, iface.package) {
+        (Some(name), Some(pkg)) => resolve.packages[pkg].interfaces.get(name) != Some(&id),
+        _ => false,
+    }
+}
+
+struct Copier<'a> {
+    resolve: &'a mut Resolve,
+    types: HashMap<TypeId, TypeId>,
+}
+
+impl Copier<'_> {
+    fn interface(&mut self, id: InterfaceId) -> InterfaceId {
+        let mut iface = self.resolve.interfaces[id].clone();
+        let copy = self.resolve.interfaces.alloc(iface.clone());
+
+        // Types are copied in the order they were defined in, which keeps
+        // every type after the types it refers to as `SizeAlign` requires.
+        let mut types = iface.types.values().copied().collect::<Vec<_>>();
+        types.sort_by_key(|ty| ty.index());
+        for ty in types {
+            let mut def = self.resolve.types[ty].clone();
+            def.owner = TypeOwner::Interface(copy);
+            def.kind = self.kind(&def.kind);
+            let new = self.resolve.types.alloc(def);
+            self.types.insert(ty, new);
+        }
+        for ty in iface.types.values_mut() {
+            *ty = self.types[ty];
+        }
+
+        for func in iface.functions.values_mut() {
+            func.kind = match func.kind {
+                FunctionKind::Freestanding => FunctionKind::Freestanding,
+                FunctionKind::Method(ty) => FunctionKind::Method(self.id(ty)),
+                FunctionKind::Static(ty) => FunctionKind::Static(self.id(ty)),
+                FunctionKind::Constructor(ty) => FunctionKind::Constructor(self.id(ty)),
+            };
+            for (_, ty) in func.params.iter_mut() {
+                *ty = self.ty(ty);
+            }
+            match &mut func.results {
+                Results::Named(params) => {
+                    for (_, ty) in params.iter_mut() {
+                        *ty = self.ty(ty);
+                    }
+                }
+                Results::Anon(ty) => *ty = self.ty(ty),
+            }
+        }
+
+        self.resolve.interfaces[copy] = iface;
+        copy
+    }
+
+    fn ty(&mut self, ty: &Type) -> Type {
+        match ty {
+            Type::Id(id) => Type::Id(self.id(*id)),
+            ty => *ty,
+        }
+    }
+
+    fn id(&mut self, id: TypeId) -> TypeId {
+        if let Some(new) = self.types.get(&id) {
+            return *new;
+        }
+
+        // Named types from other interfaces are shared with the import, and
+        // so are anonymous types which don't refer to anything copied.
+        let def = &self.resolve.types[id];
+        let new = if def.name.is_some() {
+            id
+        } else {
+            let def = def.clone();
+            let kind = self.kind(&def.kind);
+            if kind == def.kind {
+                id
+            } else {
+                self.resolve.types.alloc(TypeDef { kind, ..def })
+            }
+        };
+        self.types.insert(id, new);
+        new
+    }
+
+    fn kind(&mut self, kind: &TypeDefKind) -> TypeDefKind {
+        match kind {
+            TypeDefKind::Record(r) => TypeDefKind::Record(Record {
+                fields: r
+                    .fields
+                    .iter()
+                    .map(|field| Field {
+                        ty: self.ty(&field.ty),
+                        ..field.clone()
+                    })
+                    .collect(),
+            }),
+            TypeDefKind::Tuple(t) => TypeDefKind::Tuple(Tuple {
+                types: t.types.iter().map(|ty| self.ty(ty)).collect(),
+            }),
+            TypeDefKind::Variant(v) => TypeDefKind::Variant(Variant {
+                cases: v
+                    .cases
+                    .iter()
+                    .map(|case| Case {
+                        ty: case.ty.as_ref().map(|ty| self.ty(ty)),
+                        ..case.clone()
+                    })
+                    .collect(),
+            }),
+            TypeDefKind::Option(ty) => TypeDefKind::Option(self.ty(ty)),
+            TypeDefKind::Result(r) => TypeDefKind::Result(Result_ {
+                ok: r.ok.as_ref().map(|ty| self.ty(ty)),
+                err: r.err.as_ref().map(|ty| self.ty(ty)),
+            }),
+            TypeDefKind::List(ty) => TypeDefKind::List(self.ty(ty)),
+            TypeDefKind::Future(ty) => TypeDefKind::Future(ty.as_ref().map(|ty| self.ty(ty))),
+            TypeDefKind::Stream(s) => TypeDefKind::Stream(Stream {
+                element: s.element.as_ref().map(|ty| self.ty(ty)),
+                end: s.end.as_ref().map(|ty| self.ty(ty)),
+            }),
+            TypeDefKind::Handle(Handle::Own(ty)) => TypeDefKind::Handle(Handle::Own(self.id(*ty))),
+            TypeDefKind::Handle(Handle::Borrow(ty)) => {
+                TypeDefKind::Handle(Handle::Borrow(self.id(*ty)))
+            }
+            TypeDefKind::Type(ty) => TypeDefKind::Type(self.ty(ty)),
+            TypeDefKind::Resource
+            | TypeDefKind::Flags(_)
+            | TypeDefKind::Enum(_)
+            | TypeDefKind::Unknown => kind.clone(),
+        }
+    }
+}
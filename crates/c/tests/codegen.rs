@@ -4,14 +4,6 @@ use std::path::{Path, PathBuf};
 use std::process::Command;
 
 macro_rules! codegen_test {
-    // TODO: support importing and exporting the same interface containing one
-    // or more resources, then remove the following lines:
-    (import_and_export_resource $name:tt $test:tt) => {};
-    (import_and_export_resource_alias $name:tt $test:tt) => {};
-    (resource_alias $name:tt $test:tt) => {};
-    (resource_local_alias $name:tt $test:tt) => {};
-    (resources_with_lists $name:tt $test:tt) => {};
-    (resources_in_aggregates $name:tt $test:tt) => {};
     ($id:ident $name:tt $test:tt) => {
         #[test]
         fn $id() {
@@ -59,3 +51,39 @@ fn verify(dir: &Path, name: &str) {
 
     test_helpers::run_command(&mut cmd);
 }
+
+#[test]
+fn rejects_futures_and_streams() {
+    let mut resolve = wit_bindgen_core::wit_parser::Resolve::default();
+    let pkg = resolve
+        .push(
+            wit_bindgen_core::wit_parser::UnresolvedPackage::parse(
+                "async.wit".as_ref(),
+                "
+                    package my:async;
+
+                    interface channels {
+                        type bytes = stream<u8>;
+
+                        open: func() -> bytes;
+                    }
+
+                    world component {
+                        import channels;
+                    }
+                ",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+    let world = resolve.select_world(pkg, None).unwrap();
+    let err = wit_bindgen_c::Opts::default()
+        .build()
+        .generate(&resolve, world, &mut Default::default())
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "`stream` types are not supported by this generator\n  \
+         --> in type `bytes` of interface `my:async/channels`"
+    );
+}
@@ -1,10 +1,22 @@
 use wit_parser::{
     Enum, Flags, FlagsRepr, Function, Handle, Int, Record, Resolve, Result_, Results, SizeAlign,
-    Tuple, Type, TypeDefKind, TypeId, Variant,
+    Stream, Tuple, Type, TypeDefKind, TypeId, Variant,
 };
 
 pub use wit_parser::abi::{AbiVariant, WasmSignature, WasmType};
 
+/// Maximum number of flat core wasm parameters a function can have before its
+/// parameters are passed in linear memory instead.
+///
+/// This is the limit used by [`Resolve::wasm_signature`].
+pub const MAX_FLAT_PARAMS: usize = 16;
+
+/// Maximum number of flat core wasm results a function can have before its
+/// results are passed in linear memory instead.
+///
+/// This is the limit used by [`Resolve::wasm_signature`].
+pub const MAX_FLAT_RESULTS: usize = 1;
+
 // Helper macro for defining instructions without having to have tons of
 // exhaustive `match` statements to update
 macro_rules! def_instruction {
@@ -341,6 +353,38 @@ def_instruction! {
             ty: TypeId,
         } : [1] => [1],
 
+        /// Create an `i32` from a future.
+        ///
+        /// Futures are represented in the canonical ABI as handles, so this
+        /// transfers ownership of the readable end of the future to the
+        /// callee.
+        FutureLower {
+            payload: &'a Option<Type>,
+            ty: TypeId,
+        } : [1] => [1],
+
+        /// Create a future from an `i32`.
+        FutureLift {
+            payload: &'a Option<Type>,
+            ty: TypeId,
+        } : [1] => [1],
+
+        /// Create an `i32` from a stream.
+        ///
+        /// Like futures, streams are represented in the canonical ABI as
+        /// handles and ownership of the readable end is transferred to the
+        /// callee.
+        StreamLower {
+            stream: &'a Stream,
+            ty: TypeId,
+        } : [1] => [1],
+
+        /// Create a stream from an `i32`.
+        StreamLift {
+            stream: &'a Stream,
+            ty: TypeId,
+        } : [1] => [1],
+
         /// Pops a tuple value off the stack, decomposes the tuple to all of
         /// its fields, and then pushes the fields onto the stack.
         TupleLower {
@@ -620,6 +664,31 @@ pub trait Bindgen {
     /// "canonical" form for lists. This dictates whether the `ListCanonLower`
     /// and `ListCanonLift` instructions are used or not.
     fn is_list_canonical(&self, resolve: &Resolve, element: &Type) -> bool;
+
+    /// Called with the `operand` holding a value of the type `ty` before it's
+    /// lowered, including through aliases of `ty`.
+    ///
+    /// This allows a value in a custom representation to be converted to the
+    /// one lowering instructions expect. `realloc` is as for
+    /// [`Instruction::ListLower`], being `None` if the lowered value is only
+    /// borrowed for the duration of a call.
+    fn before_lower(
+        &mut self,
+        resolve: &Resolve,
+        ty: TypeId,
+        realloc: Option<&str>,
+        operand: &mut Self::Operand,
+    ) {
+        let _ = (resolve, ty, realloc, operand);
+    }
+
+    /// Called with the `operand` holding a value of the type `ty` after it's
+    /// lifted, including through aliases of `ty`.
+    ///
+    /// This is the dual of [`Bindgen::before_lower`].
+    fn after_lift(&mut self, resolve: &Resolve, ty: TypeId, operand: &mut Self::Operand) {
+        let _ = (resolve, ty, operand);
+    }
 }
 
 /// Generates an abstract sequence of instructions which represents this
@@ -662,6 +731,52 @@ pub fn post_return(resolve: &Resolve, func: &Function, bindgen: &mut impl Bindge
     )
     .post_return(func);
 }
+
+/// Generates instructions to lift a value of type `ty` out of linear memory.
+///
+/// The value is read starting at `address` using the canonical ABI layout of
+/// `ty`, and the resulting language-level value is returned. This is useful
+/// for generators that need to move values through memory outside of a
+/// function call, for example when reading the payload of a `future` or a
+/// `stream`.
+pub fn lift_from_memory<B: Bindgen>(
+    resolve: &Resolve,
+    bindgen: &mut B,
+    address: B::Operand,
+    ty: &Type,
+) -> B::Operand {
+    let mut generator = Generator::new(
+        resolve,
+        AbiVariant::GuestImport,
+        LiftLower::LowerArgsLiftResults,
+        bindgen,
+    );
+    generator.read_from_memory(ty, address, 0);
+    generator.stack.pop().unwrap()
+}
+
+/// Generates instructions to lower `value` of type `ty` into linear memory.
+///
+/// This is the dual of [`lift_from_memory`]. Ownership of any allocations
+/// made while lowering (such as lists and strings) is transferred to the
+/// reader of `address`, so `realloc` is always used.
+pub fn lower_to_memory<B: Bindgen>(
+    resolve: &Resolve,
+    bindgen: &mut B,
+    address: B::Operand,
+    value: B::Operand,
+    ty: &Type,
+) {
+    let mut generator = Generator::new(
+        resolve,
+        AbiVariant::GuestExport,
+        LiftLower::LiftArgsLowerResults,
+        bindgen,
+    );
+    generator.stack.push(value);
+    generator.write_to_memory(ty, address, 0);
+}
+
 /// Returns whether the `Function` specified needs a post-return function to
 /// be generated in guest code.
 ///
@@ -694,7 +809,7 @@ fn needs_post_return(resolve: &Resolve, ty: &Type) -> bool {
                 .filter_map(|t| t.as_ref())
                 .any(|t| needs_post_return(resolve, t)),
             TypeDefKind::Flags(_) | TypeDefKind::Enum(_) => false,
-            TypeDefKind::Future(_) | TypeDefKind::Stream(_) => unimplemented!(),
+            TypeDefKind::Future(_) | TypeDefKind::Stream(_) => false,
             TypeDefKind::Unknown => unreachable!(),
         },
 
@@ -1016,6 +1131,9 @@ impl<'a, B: Bindgen> Generator<'a, B> {
     fn lower(&mut self, ty: &Type) {
         use Instruction::*;
 
+        if let Type::Id(id) = *ty {
+            self.before_lower(id);
+        }
         match *ty {
             Type::Bool => self.emit(&I32FromBool),
             Type::S8 => self.emit(&I32FromS8),
@@ -1128,8 +1246,12 @@ impl<'a, B: Bindgen> Generator<'a, B> {
                         results: &results,
                     });
                 }
-                TypeDefKind::Future(_) => todo!("lower future"),
-                TypeDefKind::Stream(_) => todo!("lower stream"),
+                TypeDefKind::Future(payload) => {
+                    self.emit(&FutureLower { payload, ty: id });
+                }
+                TypeDefKind::Stream(stream) => {
+                    self.emit(&StreamLower { stream, ty: id });
+                }
                 TypeDefKind::Unknown => unreachable!(),
             },
         }
@@ -1203,6 +1325,13 @@ impl<'a, B: Bindgen> Generator<'a, B> {
     /// Note that in general everything in this function is the opposite of the
     /// `lower` function above. This is intentional and should be kept this way!
     fn lift(&mut self, ty: &Type) {
+        self.lift_value(ty);
+        if let Type::Id(id) = *ty {
+            self.after_lift(id);
+        }
+    }
+
+    fn lift_value(&mut self, ty: &Type) {
         use Instruction::*;
 
         match *ty {
@@ -1313,8 +1442,13 @@ impl<'a, B: Bindgen> Generator<'a, B> {
                     self.emit(&ResultLift { result: r, ty: id });
                 }
 
-                TypeDefKind::Future(_) => todo!("lift future"),
-                TypeDefKind::Stream(_) => todo!("lift stream"),
+                TypeDefKind::Future(payload) => {
+                    self.emit(&FutureLift { payload, ty: id });
+                }
+
+                TypeDefKind::Stream(stream) => {
+                    self.emit(&StreamLift { stream, ty: id });
+                }
                 TypeDefKind::Unknown => unreachable!(),
             },
         }
@@ -1363,6 +1497,11 @@ impl<'a, B: Bindgen> Generator<'a, B> {
     fn write_to_memory(&mut self, ty: &Type, addr: B::Operand, offset: i32) {
         use Instruction::*;
 
+        if let Type::Id(id) = *ty {
+            if !self.stored_through_lower(id) {
+                self.before_lower(id);
+            }
+        }
         match *ty {
             // Builtin types need different flavors of storage instructions
             // depending on the size of the value written.
@@ -1472,8 +1611,9 @@ impl<'a, B: Bindgen> Generator<'a, B> {
                     self.store_intrepr(offset, e.tag());
                 }
 
-                TypeDefKind::Future(_) => todo!("write future to memory"),
-                TypeDefKind::Stream(_) => todo!("write stream to memory"),
+                TypeDefKind::Future(_) | TypeDefKind::Stream(_) => {
+                    self.lower_and_emit(ty, addr, &I32Store { offset })
+                }
                 TypeDefKind::Unknown => unreachable!(),
             },
         }
@@ -1545,6 +1685,33 @@ impl<'a, B: Bindgen> Generator<'a, B> {
         }
     }
 
+    /// Returns whether values of the type `id` are written to and read from
+    /// memory through `lower` and `lift`, which invoke the hooks of the
+    /// `Bindgen` for them.
+    fn stored_through_lower(&self, id: TypeId) -> bool {
+        matches!(
+            self.resolve.types[id].kind,
+            TypeDefKind::List(_)
+                | TypeDefKind::Handle(_)
+                | TypeDefKind::Flags(_)
+                | TypeDefKind::Enum(_)
+                | TypeDefKind::Future(_)
+                | TypeDefKind::Stream(_)
+        )
+    }
+
+    fn before_lower(&mut self, id: TypeId) {
+        let realloc = self.list_realloc();
+        let operand = self.stack.last_mut().unwrap();
+        self.bindgen
+            .before_lower(self.resolve, id, realloc, operand);
+    }
+
+    fn after_lift(&mut self, id: TypeId) {
+        let operand = self.stack.last_mut().unwrap();
+        self.bindgen.after_lift(self.resolve, id, operand);
+    }
+
     fn lower_and_emit(&mut self, ty: &Type, addr: B::Operand, instr: &Instruction) {
         self.lower(ty);
         self.stack.push(addr);
@@ -1552,6 +1719,15 @@ impl<'a, B: Bindgen> Generator<'a, B> {
     }
 
     fn read_from_memory(&mut self, ty: &Type, addr: B::Operand, offset: i32) {
+        self.read_value_from_memory(ty, addr, offset);
+        if let Type::Id(id) = *ty {
+            if !self.stored_through_lower(id) {
+                self.after_lift(id);
+            }
+        }
+    }
+
+    fn read_value_from_memory(&mut self, ty: &Type, addr: B::Operand, offset: i32) {
         use Instruction::*;
 
         match *ty {
@@ -1655,8 +1831,9 @@ impl<'a, B: Bindgen> Generator<'a, B> {
                     self.lift(ty);
                 }
 
-                TypeDefKind::Future(_) => todo!("read future from memory"),
-                TypeDefKind::Stream(_) => todo!("read stream from memory"),
+                TypeDefKind::Future(_) | TypeDefKind::Stream(_) => {
+                    self.emit_and_lift(ty, addr, &I32Load { offset })
+                }
                 TypeDefKind::Unknown => unreachable!(),
             },
         }
@@ -1825,8 +2002,9 @@ impl<'a, B: Bindgen> Generator<'a, B> {
 
                 TypeDefKind::Enum(_) => {}
 
-                TypeDefKind::Future(_) => todo!("read future from memory"),
-                TypeDefKind::Stream(_) => todo!("read stream from memory"),
+                // Ownership of future and stream handles is transferred to
+                // the caller, so there's nothing to deallocate here.
+                TypeDefKind::Future(_) | TypeDefKind::Stream(_) => {}
                 TypeDefKind::Unknown => unreachable!(),
             },
         }
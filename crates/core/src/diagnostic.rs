@@ -0,0 +1,121 @@
+use std::fmt;
+use wit_parser::{Resolve, TypeId, TypeOwner, WorldId, WorldKey};
+
+/// An error raised by a generator while producing bindings.
+///
+/// In addition to a message this records the WIT item which caused the error,
+/// if known, along with an optional generator-specific hint on how to fix it.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    message: String,
+    item: Option<WitItem>,
+    help: Option<String>,
+}
+
+/// A reference to an item in a WIT world, used to point at the source of a
+/// [`Diagnostic`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WitItem {
+    /// A world, by name.
+    World(String),
+    /// An interface, named as with [`Resolve::name_world_key`].
+    Interface(String),
+    /// A function, optionally within an interface.
+    Function {
+        interface: Option<String>,
+        name: String,
+    },
+    /// A type, optionally within an interface.
+    Type {
+        interface: Option<String>,
+        name: String,
+    },
+}
+
+impl Diagnostic {
+    pub fn new(message: impl Into<String>) -> Diagnostic {
+        Diagnostic {
+            message: message.into(),
+            item: None,
+            help: None,
+        }
+    }
+
+    /// Records `item` as the source of this diagnostic.
+    pub fn with_item(mut self, item: WitItem) -> Diagnostic {
+        self.item = Some(item);
+        self
+    }
+
+    /// Attaches a hint describing how to fix this diagnostic.
+    pub fn with_help(mut self, help: impl Into<String>) -> Diagnostic {
+        self.help = Some(help.into());
+        self
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn item(&self) -> Option<&WitItem> {
+        self.item.as_ref()
+    }
+
+    pub fn help(&self) -> Option<&str> {
+        self.help.as_deref()
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)?;
+        if let Some(item) = &self.item {
+            write!(f, "\n  --> in {item}")?;
+        }
+        if let Some(help) = &self.help {
+            write!(f, "\n  = help: {help}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+impl WitItem {
+    pub fn world(resolve: &Resolve, world: WorldId) -> WitItem {
+        WitItem::World(resolve.worlds[world].name.clone())
+    }
+
+    pub fn interface(resolve: &Resolve, key: &WorldKey) -> WitItem {
+        WitItem::Interface(resolve.name_world_key(key))
+    }
+
+    /// Returns the item for the type `id`, or `None` if it's anonymous.
+    pub fn ty(resolve: &Resolve, id: TypeId) -> Option<WitItem> {
+        let ty = &resolve.types[id];
+        let interface = match ty.owner {
+            TypeOwner::Interface(iface) => resolve.id_of(iface),
+            _ => None,
+        };
+        Some(WitItem::Type {
+            interface,
+            name: ty.name.clone()?,
+        })
+    }
+}
+
+impl fmt::Display for WitItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (kind, interface, name) = match self {
+            WitItem::World(name) => return write!(f, "world `{name}`"),
+            WitItem::Interface(name) => return write!(f, "interface `{name}`"),
+            WitItem::Function { interface, name } => ("function", interface, name),
+            WitItem::Type { interface, name } => ("type", interface, name),
+        };
+        write!(f, "{kind} `{name}`")?;
+        if let Some(interface) = interface {
+            write!(f, " of interface `{interface}`")?;
+        }
+        Ok(())
+    }
+}
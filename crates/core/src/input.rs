@@ -0,0 +1,262 @@
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use wit_component::DecodedWasm;
+use wit_parser::{PackageId, Resolve, UnresolvedPackage, WorldId};
+
+/// The result of adding an input to a [`Resolve`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parsed {
+    /// A WIT package from which a world still needs to be selected.
+    Package(PackageId),
+    /// The world of a binary component.
+    World(WorldId),
+}
+
+impl Parsed {
+    /// Selects the world to generate bindings for, optionally by `name`.
+    ///
+    /// Packages defer to [`Resolve::select_world`], while a component only
+    /// contains a single world which `name` must match if it's specified.
+    pub fn select_world(self, resolve: &Resolve, name: Option<&str>) -> Result<WorldId> {
+        match self {
+            Parsed::Package(pkg) => resolve.select_world(pkg, name),
+            Parsed::World(world) => {
+                if let Some(name) = name {
+                    if resolve.worlds[world].name != name {
+                        bail!(
+                            "world `{name}` not found, a component only contains the world `{}`",
+                            resolve.worlds[world].name
+                        );
+                    }
+                }
+                Ok(world)
+            }
+        }
+    }
+}
+
+/// Adds each of `deps` to `resolve` in order, returning the files they were
+/// read from.
+///
+/// Each dependency is added with [`push_path`] and must come after any
+/// packages it depends on.
+pub fn push_deps<P: AsRef<Path>>(resolve: &mut Resolve, deps: &[P]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for dep in deps {
+        let dep = dep.as_ref();
+        let (_, sources) = push_path(resolve, dep)
+            .with_context(|| format!("failed to push dependency {}", dep.display()))?;
+        files.extend(sources);
+    }
+    Ok(files)
+}
+
+/// Adds the WIT directory, WIT file, or wasm binary at `path` to `resolve`,
+/// returning what was added along with the files it was read from.
+pub fn push_path(resolve: &mut Resolve, path: &Path) -> Result<(Parsed, Vec<PathBuf>)> {
+    if path.is_dir() {
+        // Packages with a `deps` directory resolve their dependencies from
+        // there, otherwise they're expected to have already been pushed.
+        if path.join("deps").exists() {
+            let (pkg, sources) = resolve.push_dir(path)?;
+            return Ok((Parsed::Package(pkg), sources));
+        }
+        let pkg = UnresolvedPackage::parse_dir(path)?;
+        let sources = pkg.source_files().map(|s| s.to_owned()).collect();
+        return Ok((Parsed::Package(resolve.push(pkg)?), sources));
+    }
+    let contents =
+        std::fs::read(path).with_context(|| format!("failed to read file {}", path.display()))?;
+    let sources = vec![path.to_owned()];
+    if contents.starts_with(b"\0asm") {
+        let parsed = push_wasm(resolve, &contents)
+            .with_context(|| format!("failed to decode wasm file {}", path.display()))?;
+        return Ok((parsed, sources));
+    }
+    let contents = std::str::from_utf8(&contents)
+        .with_context(|| format!("input file {} is not valid utf-8", path.display()))?;
+    let pkg = UnresolvedPackage::parse(path, contents)?;
+    Ok((Parsed::Package(resolve.push(pkg)?), sources))
+}
+
+/// Adds the binary WIT package or component in `wasm` to `resolve`.
+///
+/// A WIT package yields the package itself while a component yields the world
+/// embedded within it.
+pub fn push_wasm(resolve: &mut Resolve, wasm: &[u8]) -> Result<Parsed> {
+    Ok(match wit_component::decode(wasm)? {
+        DecodedWasm::WitPackage(r, pkg) => {
+            let remap = resolve.merge(r)?;
+            Parsed::Package(remap.packages[pkg.index()])
+        }
+        DecodedWasm::Component(r, world) => {
+            let remap = resolve.merge(r)?;
+            Parsed::World(remap.worlds[world.index()])
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wit_component::{ComponentEncoder, StringEncoding};
+    use wit_parser::UnresolvedPackage;
+
+    const DEP: &str = "
+        package my:dep
+
+        interface types {
+            type id = u32
+        }
+    ";
+
+    const ROOT: &str = "
+        package my:root
+
+        interface api {
+            use my:dep/types.{id}
+            lookup: func(x: id) -> u64
+        }
+
+        world host {
+            import api
+        }
+
+        world guest {
+            export api
+        }
+    ";
+
+    /// Creates an empty scratch directory unique to the calling test.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join("wit-bindgen-core-tests")
+            .join(format!("{name}-{}", std::process::id()));
+        drop(std::fs::remove_dir_all(&dir));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn deps_are_pushed_before_the_package() {
+        let dir = scratch_dir("deps");
+        std::fs::write(dir.join("dep.wit"), DEP).unwrap();
+        std::fs::create_dir(dir.join("root")).unwrap();
+        std::fs::write(dir.join("root/root.wit"), ROOT).unwrap();
+
+        let mut resolve = Resolve::default();
+        let files = push_deps(&mut resolve, &[dir.join("dep.wit")]).unwrap();
+        assert_eq!(files, [dir.join("dep.wit")]);
+
+        let (parsed, files) = push_path(&mut resolve, &dir.join("root")).unwrap();
+        assert_eq!(files, [dir.join("root/root.wit")]);
+        let world = parsed.select_world(&resolve, Some("guest")).unwrap();
+        assert_eq!(resolve.worlds[world].name, "guest");
+        assert!(parsed.select_world(&resolve, None).is_err());
+    }
+
+    #[test]
+    fn missing_deps_are_reported() {
+        let dir = scratch_dir("missing-deps");
+        std::fs::write(dir.join("root.wit"), ROOT).unwrap();
+
+        let mut resolve = Resolve::default();
+        assert!(push_path(&mut resolve, &dir.join("root.wit")).is_err());
+
+        let err = push_deps(&mut resolve, &[dir.join("missing.wit")]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "failed to push dependency {}",
+                dir.join("missing.wit").display()
+            )
+        );
+    }
+
+    #[test]
+    fn binary_wit_package() {
+        let mut resolve = Resolve::default();
+        resolve
+            .push(UnresolvedPackage::parse("dep.wit".as_ref(), DEP).unwrap())
+            .unwrap();
+        let pkg = resolve
+            .push(UnresolvedPackage::parse("root.wit".as_ref(), ROOT).unwrap())
+            .unwrap();
+        let wasm = wit_component::encode(Some(true), &resolve, pkg).unwrap();
+
+        // The binary package carries its own dependencies.
+        let mut resolve = Resolve::default();
+        let Parsed::Package(pkg) = push_wasm(&mut resolve, &wasm).unwrap() else {
+            panic!("expected a package");
+        };
+        assert_eq!(resolve.packages[pkg].name.to_string(), "my:root");
+        let world = resolve.select_world(pkg, Some("host")).unwrap();
+        assert_eq!(resolve.worlds[world].name, "host");
+    }
+
+    #[test]
+    fn binary_component() {
+        let mut resolve = Resolve::default();
+        resolve
+            .push(UnresolvedPackage::parse("dep.wit".as_ref(), DEP).unwrap())
+            .unwrap();
+        let pkg = resolve
+            .push(UnresolvedPackage::parse("root.wit".as_ref(), ROOT).unwrap())
+            .unwrap();
+        let world = resolve.select_world(pkg, Some("host")).unwrap();
+
+        // A core module importing `lookup` with its flattened signature.
+        let mut module = wasm_encoder::Module::new();
+        let mut types = wasm_encoder::TypeSection::new();
+        types.function([wasm_encoder::ValType::I32], [wasm_encoder::ValType::I64]);
+        module.section(&types);
+        let mut imports = wasm_encoder::ImportSection::new();
+        imports.import(
+            "my:root/api",
+            "lookup",
+            wasm_encoder::EntityType::Function(0),
+        );
+        module.section(&imports);
+        let mut module = module.finish();
+        wit_component::embed_component_metadata(&mut module, &resolve, world, StringEncoding::UTF8)
+            .unwrap();
+        let component = ComponentEncoder::default()
+            .module(&module)
+            .unwrap()
+            .validate(true)
+            .encode()
+            .unwrap();
+
+        let mut resolve = Resolve::default();
+        let parsed = push_wasm(&mut resolve, &component).unwrap();
+        let Parsed::World(world) = parsed else {
+            panic!("expected a world, got {parsed:?}");
+        };
+        assert_eq!(resolve.worlds[world].name, "root");
+        assert_eq!(parsed.select_world(&resolve, None).unwrap(), world);
+        assert_eq!(parsed.select_world(&resolve, Some("root")).unwrap(), world);
+        assert_eq!(
+            parsed
+                .select_world(&resolve, Some("guest"))
+                .unwrap_err()
+                .to_string(),
+            "world `guest` not found, a component only contains the world `root`"
+        );
+        // `api` is imported along with the `types` interface it uses.
+        assert_eq!(resolve.worlds[world].imports.len(), 2);
+    }
+
+    #[test]
+    fn invalid_wasm_is_reported() {
+        let dir = scratch_dir("invalid-wasm");
+        std::fs::write(dir.join("bad.wasm"), b"\0asm\x01\0\0\0\xff").unwrap();
+        let err = push_path(&mut Resolve::default(), &dir.join("bad.wasm")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "failed to decode wasm file {}",
+                dir.join("bad.wasm").display()
+            )
+        );
+    }
+}
@@ -6,8 +6,14 @@ use wit_parser::*;
 
 pub use wit_parser;
 pub mod abi;
+mod diagnostic;
+pub use diagnostic::{Diagnostic, WitItem};
+mod input;
+pub use input::{push_deps, push_path, push_wasm, Parsed};
 mod ns;
 pub use ns::Ns;
+mod symbols;
+pub use symbols::{CoreExport, CoreImport, CoreSymbols};
 
 #[derive(Default)]
 pub struct Types {
@@ -45,6 +51,9 @@ pub struct TypeInfo {
 
     /// Whether or not this type (transitively) has an own handle.
     pub has_own_handle: bool,
+
+    /// Whether or not this type (transitively) has a float.
+    pub has_float: bool,
 }
 
 impl std::ops::BitOrAssign for TypeInfo {
@@ -56,6 +65,7 @@ impl std::ops::BitOrAssign for TypeInfo {
         self.has_resource |= rhs.has_resource;
         self.has_borrow_handle |= rhs.has_borrow_handle;
         self.has_own_handle |= rhs.has_own_handle;
+        self.has_float |= rhs.has_float;
     }
 }
 
@@ -66,6 +76,17 @@ impl TypeInfo {
     pub fn is_copy(&self) -> bool {
         !self.has_list && !self.has_resource
     }
+    /// Whether values of this type can be compared, and partially ordered.
+    ///
+    /// Resource handles, futures and streams are not comparable.
+    pub fn is_partial_eq(&self) -> bool {
+        !self.has_resource
+    }
+    /// Whether values of this type have a total equality, order and hash,
+    /// which floats don't.
+    pub fn is_eq(&self) -> bool {
+        self.is_partial_eq() && !self.has_float
+    }
 }
 
 impl Types {
@@ -200,12 +221,22 @@ impl Types {
                 info = self.optional_type_info(resolve, r.ok.as_ref());
                 info |= self.optional_type_info(resolve, r.err.as_ref());
             }
+            // Futures and streams are passed as owned handles in the canonical
+            // ABI, so their payloads never live inline within this type. The
+            // payload's info is still computed as its values are read and
+            // written separately, but isn't merged in here: a
+            // `future<list<u8>>` is a handle which neither borrows a list nor
+            // contains floats.
             TypeDefKind::Future(ty) => {
-                info = self.optional_type_info(resolve, ty.as_ref());
+                self.optional_type_info(resolve, ty.as_ref());
+                info.has_own_handle = true;
+                info.has_resource = true;
             }
             TypeDefKind::Stream(stream) => {
-                info = self.optional_type_info(resolve, stream.element.as_ref());
-                info |= self.optional_type_info(resolve, stream.end.as_ref());
+                self.optional_type_info(resolve, stream.element.as_ref());
+                self.optional_type_info(resolve, stream.end.as_ref());
+                info.has_own_handle = true;
+                info.has_resource = true;
             }
             TypeDefKind::Unknown => unreachable!(),
         }
@@ -218,6 +249,7 @@ impl Types {
         let mut info = TypeInfo::default();
         match ty {
             Type::String => info.has_list = true,
+            Type::Float32 | Type::Float64 => info.has_float = true,
             Type::Id(id) => return self.type_id_info(resolve, *id),
             _ => {}
         }
@@ -235,6 +267,7 @@ impl Types {
 #[derive(Default)]
 pub struct Files {
     files: BTreeMap<String, Vec<u8>>,
+    sources: BTreeMap<String, WitItem>,
 }
 
 impl Files {
@@ -263,6 +296,18 @@ impl Files {
     pub fn iter(&self) -> impl Iterator<Item = (&'_ str, &'_ [u8])> {
         self.files.iter().map(|p| (p.0.as_str(), p.1.as_slice()))
     }
+
+    /// Records that the file `name` was generated for `item`.
+    ///
+    /// Files without a recorded source are generated for the world as a
+    /// whole.
+    pub fn set_source(&mut self, name: &str, item: WitItem) {
+        self.sources.insert(name.to_owned(), item);
+    }
+
+    pub fn source(&self, name: &str) -> Option<&WitItem> {
+        self.sources.get(name)
+    }
 }
 
 #[derive(Default)]
@@ -367,13 +412,38 @@ macro_rules! uwriteln {
     };
 }
 
+/// Returns the first `future` or `stream` type used by `world`, if any.
+pub fn find_async_type(resolve: &Resolve, world: WorldId) -> Option<TypeId> {
+    let mut live = LiveTypes::default();
+    live.add_world(resolve, world);
+    let ty = live.iter().find(|id| {
+        matches!(
+            resolve.types[*id].kind,
+            TypeDefKind::Future(_) | TypeDefKind::Stream(_)
+        )
+    });
+    ty
+}
+
+fn unsupported_async_type(resolve: &Resolve, world: WorldId, id: TypeId) -> Diagnostic {
+    let kind = match resolve.types[id].kind {
+        TypeDefKind::Future(_) => "future",
+        _ => "stream",
+    };
+    Diagnostic::new(format!(
+        "`{kind}` types are not supported by this generator"
+    ))
+    .with_item(WitItem::ty(resolve, id).unwrap_or_else(|| WitItem::world(resolve, world)))
+}
+
 pub fn generated_preamble(src: &mut Source, version: &str) {
     uwriteln!(src, "// Generated by `wit-bindgen` {version}. DO NOT EDIT!")
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Source;
+    use super::{abi, CoreSymbols, Diagnostic, Source, Types, WitItem};
+    use wit_parser::{Resolve, UnresolvedPackage, WorldItem, WorldKey};
 
     #[test]
     fn simple_append() {
@@ -420,12 +490,167 @@ mod tests {
         );
         assert_eq!(s.s, "function() {\n  x\n}");
     }
+
+    #[test]
+    fn diagnostic_display() {
+        let diagnostic = Diagnostic::new("something went wrong");
+        assert_eq!(diagnostic.to_string(), "something went wrong");
+
+        let diagnostic = diagnostic
+            .with_item(WitItem::Function {
+                interface: Some("a:b/c".to_string()),
+                name: "f".to_string(),
+            })
+            .with_help("try something else");
+        assert_eq!(
+            diagnostic.to_string(),
+            "something went wrong\n  --> in function `f` of interface `a:b/c`\n  = help: try something else"
+        );
+    }
+
+    #[test]
+    fn type_info() {
+        let mut resolve = Resolve::default();
+        let pkg = resolve
+            .push(
+                UnresolvedPackage::parse(
+                    "test.wit".as_ref(),
+                    "
+                        package a:b;
+                        interface i {
+                            resource r;
+                            record plain { a: u32, b: list<string> }
+                            record float { a: option<float64> }
+                            variant handle { a(float), b(own<r>) }
+                        }
+                    ",
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        let mut types = Types::default();
+        types.analyze(&resolve);
+        let iface = &resolve.interfaces[resolve.packages[pkg].interfaces["i"]];
+        let info = |name: &str| types.get(iface.types[name]);
+
+        assert!(info("plain").is_eq());
+        assert!(info("float").has_float);
+        assert!(info("float").is_partial_eq() && !info("float").is_eq());
+        assert!(info("handle").has_float && info("handle").has_resource);
+        assert!(!info("handle").is_partial_eq());
+    }
+
+    #[test]
+    fn flat_limits() {
+        let mut resolve = Resolve::default();
+        let params = |n: usize| {
+            (0..n)
+                .map(|i| format!("p{i}: u32"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let wit = format!(
+            "
+                package a:b;
+                world w {{
+                    import max: func({}) -> u32;
+                    import over: func({}) -> tuple<u32, u32>;
+                }}
+            ",
+            params(abi::MAX_FLAT_PARAMS),
+            params(abi::MAX_FLAT_PARAMS + 1),
+        );
+        let pkg = resolve
+            .push(UnresolvedPackage::parse("test.wit".as_ref(), &wit).unwrap())
+            .unwrap();
+        let world = &resolve.worlds[resolve.select_world(pkg, None).unwrap()];
+        let sig = |name: &str| {
+            let WorldItem::Function(func) = &world.imports[&WorldKey::Name(name.to_string())]
+            else {
+                unreachable!()
+            };
+            resolve.wasm_signature(abi::AbiVariant::GuestImport, func)
+        };
+
+        let max = sig("max");
+        assert!(!max.indirect_params && !max.retptr);
+        assert_eq!(max.params.len(), abi::MAX_FLAT_PARAMS);
+        assert_eq!(max.results.len(), abi::MAX_FLAT_RESULTS);
+
+        let over = sig("over");
+        assert!(over.indirect_params && over.retptr);
+    }
+
+    #[test]
+    fn core_symbols() {
+        let mut resolve = Resolve::default();
+        let pkg = resolve
+            .push(
+                UnresolvedPackage::parse(
+                    "test.wit".as_ref(),
+                    "
+                        package a:b;
+                        interface i {
+                            resource r;
+                            f: func() -> string;
+                        }
+                        world w {
+                            import i;
+                            export i;
+                            export g: func();
+                        }
+                    ",
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        let world = resolve.select_world(pkg, None).unwrap();
+        let symbols = CoreSymbols::new(&resolve, world);
+
+        let imports = symbols
+            .imports
+            .iter()
+            .map(|i| format!("{}::{}", i.module, i.name))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            imports,
+            [
+                "a:b/i::[resource-drop]r",
+                "a:b/i::f",
+                "[export]a:b/i::[resource-new]r",
+                "[export]a:b/i::[resource-rep]r",
+                "[export]a:b/i::[resource-drop]r",
+            ]
+        );
+
+        let exports = symbols
+            .exports
+            .iter()
+            .map(|e| e.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            exports,
+            ["g", "a:b/i#[dtor]r", "a:b/i#f", "cabi_post_a:b/i#f"]
+        );
+        assert_eq!(
+            symbols.exports[2].item,
+            WitItem::Function {
+                interface: Some("a:b/i".to_string()),
+                name: "f".to_string(),
+            }
+        );
+    }
 }
 
 pub trait WorldGenerator {
     fn generate(&mut self, resolve: &Resolve, id: WorldId, files: &mut Files) -> Result<()> {
         let world = &resolve.worlds[id];
-        self.preprocess(resolve, id);
+        if !self.supports_async_types() {
+            if let Some(ty) = find_async_type(resolve, id) {
+                return Err(unsupported_async_type(resolve, id, ty).into());
+            }
+        }
+        self.preprocess(resolve, id)?;
 
         fn unwrap_name(key: &WorldKey) -> &str {
             match key {
@@ -481,8 +706,26 @@ pub trait WorldGenerator {
         let _ = (resolve, world, files);
     }
 
-    fn preprocess(&mut self, resolve: &Resolve, world: WorldId) {
+    /// Whether this generator supports `future` and `stream` types.
+    ///
+    /// Generating bindings for a world which uses them is an error otherwise.
+    fn supports_async_types(&self) -> bool {
+        false
+    }
+
+    fn preprocess(&mut self, resolve: &Resolve, world: WorldId) -> Result<()> {
         let _ = (resolve, world);
+        Ok(())
+    }
+
+    /// Returns the core wasm symbols used by the bindings generated for
+    /// `world`.
+    ///
+    /// This is expected to be called after [`WorldGenerator::generate`], and
+    /// generators whose options or runtime support add to these symbols
+    /// adjust the ones dictated by the component model.
+    fn core_symbols(&self, resolve: &Resolve, world: WorldId) -> CoreSymbols {
+        CoreSymbols::new(resolve, world)
     }
 
     fn import_interface(
@@ -544,6 +787,8 @@ pub trait InterfaceGenerator<'a> {
     fn type_enum(&mut self, id: TypeId, name: &str, enum_: &Enum, docs: &Docs);
     fn type_alias(&mut self, id: TypeId, name: &str, ty: &Type, docs: &Docs);
     fn type_list(&mut self, id: TypeId, name: &str, ty: &Type, docs: &Docs);
+    fn type_future(&mut self, id: TypeId, name: &str, ty: &Option<Type>, docs: &Docs);
+    fn type_stream(&mut self, id: TypeId, name: &str, stream: &Stream, docs: &Docs);
     fn type_builtin(&mut self, id: TypeId, name: &str, ty: &Type, docs: &Docs);
 
     fn types(&mut self, iface: InterfaceId) {
@@ -566,8 +811,8 @@ pub trait InterfaceGenerator<'a> {
             TypeDefKind::Result(r) => self.type_result(id, name, r, &ty.docs),
             TypeDefKind::List(t) => self.type_list(id, name, t, &ty.docs),
             TypeDefKind::Type(t) => self.type_alias(id, name, t, &ty.docs),
-            TypeDefKind::Future(_) => todo!("generate for future"),
-            TypeDefKind::Stream(_) => todo!("generate for stream"),
+            TypeDefKind::Future(t) => self.type_future(id, name, t, &ty.docs),
+            TypeDefKind::Stream(s) => self.type_stream(id, name, s, &ty.docs),
             TypeDefKind::Handle(_) => todo!("generate for handle"),
             TypeDefKind::Unknown => unreachable!(),
         }
@@ -0,0 +1,162 @@
+use crate::abi;
+use crate::WitItem;
+use wit_parser::*;
+
+/// The core wasm imports and exports which bindings for a world use to
+/// implement the canonical ABI.
+///
+/// [`CoreSymbols::new`] returns the names dictated by the component model,
+/// which generators extend with their own runtime support through
+/// [`WorldGenerator::core_symbols`](crate::WorldGenerator::core_symbols).
+#[derive(Debug, Default)]
+pub struct CoreSymbols {
+    pub imports: Vec<CoreImport>,
+    pub exports: Vec<CoreExport>,
+}
+
+/// A function imported by the core wasm module.
+#[derive(Debug, Clone)]
+pub struct CoreImport {
+    pub module: String,
+    pub name: String,
+    /// The WIT item this import was generated for.
+    pub item: WitItem,
+}
+
+/// A function exported by the core wasm module.
+#[derive(Debug, Clone)]
+pub struct CoreExport {
+    pub name: String,
+    /// The WIT item this export was generated for.
+    pub item: WitItem,
+}
+
+impl CoreSymbols {
+    pub fn new(resolve: &Resolve, world: WorldId) -> CoreSymbols {
+        let mut symbols = CoreSymbols::default();
+        let world = &resolve.worlds[world];
+        for (key, item) in world.imports.iter() {
+            match item {
+                WorldItem::Function(func) => symbols.import_func(None, "$root", func),
+                WorldItem::Interface(id) => {
+                    let name = resolve.name_world_key(key);
+                    let iface = &resolve.interfaces[*id];
+                    for (_, ty) in iface.types.iter() {
+                        symbols.import_resource(resolve, &name, Some(&name), *ty, false);
+                    }
+                    for (_, func) in iface.functions.iter() {
+                        symbols.import_func(Some(&name), &name, func);
+                    }
+                }
+                WorldItem::Type(ty) => symbols.import_resource(resolve, "$root", None, *ty, false),
+            }
+        }
+        for (key, item) in world.exports.iter() {
+            match item {
+                WorldItem::Function(func) => {
+                    symbols.export_func(resolve, None, func.name.clone(), func)
+                }
+                WorldItem::Interface(id) => {
+                    let name = resolve.name_world_key(key);
+                    let iface = &resolve.interfaces[*id];
+                    let module = format!("[export]{name}");
+                    for (_, ty) in iface.types.iter() {
+                        symbols.import_resource(resolve, &module, Some(&name), *ty, true);
+                        if is_resource(resolve, *ty) {
+                            let resource = resolve.types[*ty].name.clone().unwrap();
+                            symbols.exports.push(CoreExport {
+                                name: format!("{name}#[dtor]{resource}"),
+                                item: WitItem::Type {
+                                    interface: Some(name.clone()),
+                                    name: resource,
+                                },
+                            });
+                        }
+                    }
+                    for (_, func) in iface.functions.iter() {
+                        let export_name = format!("{name}#{}", func.name);
+                        symbols.export_func(resolve, Some(&name), export_name, func);
+                    }
+                }
+                WorldItem::Type(_) => unreachable!(),
+            }
+        }
+        symbols
+    }
+
+    /// Records the `cabi_realloc` export used by the host to allocate memory
+    /// in the module.
+    pub fn export_realloc(&mut self, resolve: &Resolve, world: WorldId) {
+        self.exports.push(CoreExport {
+            name: "cabi_realloc".to_string(),
+            item: WitItem::world(resolve, world),
+        });
+    }
+
+    fn import_func(&mut self, interface: Option<&str>, module: &str, func: &Function) {
+        self.imports.push(CoreImport {
+            module: module.to_string(),
+            name: func.name.clone(),
+            item: WitItem::Function {
+                interface: interface.map(|s| s.to_string()),
+                name: func.name.clone(),
+            },
+        });
+    }
+
+    /// Records the intrinsics used for the resource `ty`, if it is one.
+    fn import_resource(
+        &mut self,
+        resolve: &Resolve,
+        module: &str,
+        interface: Option<&str>,
+        ty: TypeId,
+        export: bool,
+    ) {
+        if !is_resource(resolve, ty) {
+            return;
+        }
+        let resource = resolve.types[ty].name.as_deref().unwrap();
+        let intrinsics: &[&str] = if export {
+            &["resource-new", "resource-rep", "resource-drop"]
+        } else {
+            &["resource-drop"]
+        };
+        for intrinsic in intrinsics {
+            self.imports.push(CoreImport {
+                module: module.to_string(),
+                name: format!("[{intrinsic}]{resource}"),
+                item: WitItem::Type {
+                    interface: interface.map(|s| s.to_string()),
+                    name: resource.to_string(),
+                },
+            });
+        }
+    }
+
+    fn export_func(
+        &mut self,
+        resolve: &Resolve,
+        interface: Option<&str>,
+        export_name: String,
+        func: &Function,
+    ) {
+        let item = WitItem::Function {
+            interface: interface.map(|s| s.to_string()),
+            name: func.name.clone(),
+        };
+        let post_return = abi::guest_export_needs_post_return(resolve, func)
+            .then(|| format!("cabi_post_{export_name}"));
+        self.exports.push(CoreExport {
+            name: export_name,
+            item: item.clone(),
+        });
+        if let Some(name) = post_return {
+            self.exports.push(CoreExport { name, item });
+        }
+    }
+}
+
+fn is_resource(resolve: &Resolve, ty: TypeId) -> bool {
+    matches!(resolve.types[ty].kind, TypeDefKind::Resource)
+}
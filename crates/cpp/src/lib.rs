@@ -0,0 +1,2071 @@
+use anyhow::Result;
+use heck::*;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::mem;
+use wit_bindgen_c::{component_type_object, int_repr, interface_identifier, to_c_ident};
+use wit_bindgen_core::abi::{
+    self, AbiVariant, Bindgen, Bitcast, Instruction, LiftLower, WasmSignature, WasmType,
+};
+use wit_bindgen_core::{
+    uwrite, uwriteln, wit_parser::*, CoreSymbols, Files, InterfaceGenerator as _, Ns, Source,
+    Types, WitItem, WorldGenerator,
+};
+use wit_component::StringEncoding;
+
+/// Names of the members generated for every resource class, which methods of
+/// the resource are renamed to avoid.
+const RESOURCE_MEMBERS: &[&str] = &[
+    "handle",
+    "into_handle",
+    "from_handle",
+    "create",
+    "wasm_lower_own",
+    "wasm_lift_own",
+    "wasm_dtor",
+];
+
+#[derive(Default, Debug, Clone)]
+#[cfg_attr(feature = "clap", derive(clap::Args))]
+pub struct Opts {}
+
+impl Opts {
+    pub fn build(&self) -> Box<dyn WorldGenerator> {
+        Box::new(Cpp {
+            _opts: self.clone(),
+            ..Cpp::default()
+        })
+    }
+}
+
+#[derive(Default)]
+struct Cpp {
+    _opts: Opts,
+    world: String,
+    sizes: SizeAlign,
+    types: Types,
+    // The header is split into forward declarations, type definitions and
+    // function declarations so that every item can refer to the types of
+    // any namespace.
+    h_fwd: Source,
+    h_types: Source,
+    h_funcs: Source,
+    c_src: Source,
+    interface_names: HashMap<InterfaceId, WorldKey>,
+
+    // Interfaces exported by the world.
+    //
+    // Items of these interfaces live in the `exports` namespace when they're
+    // referred to from an export, even if the interface is also imported.
+    exported_interfaces: HashSet<InterfaceId>,
+
+    return_pointer_area_size: usize,
+    return_pointer_area_align: usize,
+    needs_bitcast: bool,
+}
+
+impl Cpp {
+    fn interface<'a>(
+        &'a mut self,
+        resolve: &'a Resolve,
+        interface: Option<InterfaceId>,
+        key: Option<&'a WorldKey>,
+        in_export: bool,
+    ) -> InterfaceGenerator<'a> {
+        InterfaceGenerator {
+            gen: self,
+            resolve,
+            interface,
+            key,
+            in_export,
+            h_fwd: Source::default(),
+            h_types: Vec::new(),
+            h_funcs: Source::default(),
+            c_src: Source::default(),
+        }
+    }
+
+    /// Returns the C++ namespace, without a leading `::`, that the items of
+    /// `interface` (or of the world, if `None`) are generated in.
+    fn namespace(
+        &self,
+        resolve: &Resolve,
+        interface: Option<InterfaceId>,
+        in_export: bool,
+    ) -> String {
+        let mut ns = String::new();
+        if in_export {
+            ns.push_str("exports::");
+        }
+        match interface {
+            None => ns.push_str(&to_c_ident(&self.world.to_snake_case())),
+            Some(id) => match &self.interface_names[&id] {
+                WorldKey::Name(name) => ns.push_str(&to_c_ident(&name.to_snake_case())),
+                WorldKey::Interface(_) => {
+                    let iface = &resolve.interfaces[id];
+                    let pkg = &resolve.packages[iface.package.unwrap()];
+                    ns.push_str(&to_c_ident(&pkg.name.namespace.to_snake_case()));
+                    ns.push_str("::");
+                    ns.push_str(&to_c_ident(&pkg.name.name.to_snake_case()));
+                    ns.push_str("::");
+                    if let Some(version) = &pkg.name.version {
+                        let version = version.to_string().replace(['.', '-', '+'], "_");
+                        uwrite!(ns, "v{version}::");
+                    }
+                    ns.push_str(&to_c_ident(&iface.name.as_ref().unwrap().to_snake_case()));
+                }
+            },
+        }
+        ns
+    }
+
+    fn print_intrinsics(&mut self, c_str: &mut Source) {
+        // Note that this is declared as `weak` so it can be overridden from
+        // some other symbol, such as the one generated for C bindings.
+        c_str.push_str(
+            r#"
+                extern "C" __attribute__((__weak__, __export_name__("cabi_realloc")))
+                void *cabi_realloc(void *ptr, size_t old_size, size_t align, size_t new_size) {
+                    (void) old_size;
+                    if (new_size == 0) return (void*) align;
+                    void *ret = realloc(ptr, new_size);
+                    if (!ret) abort();
+                    return ret;
+                }
+            "#,
+        );
+
+        if self.needs_bitcast {
+            c_str.push_str(
+                "
+                    namespace {
+                    template <typename To, typename From> To bitcast(From value) {
+                        static_assert(sizeof(To) == sizeof(From), \"bitcast between types of different sizes\");
+                        To ret;
+                        memcpy(&ret, &value, sizeof(To));
+                        return ret;
+                    }
+                    }
+                ",
+            );
+        }
+    }
+}
+
+impl WorldGenerator for Cpp {
+    fn preprocess(&mut self, resolve: &Resolve, world: WorldId) -> Result<()> {
+        let world = &resolve.worlds[world];
+        self.world = world.name.to_string();
+        self.sizes.fill(resolve);
+        self.types.analyze(resolve);
+
+        for (key, item) in world.imports.iter() {
+            if let WorldItem::Interface(id) = item {
+                self.interface_names.insert(*id, key.clone());
+            }
+        }
+        for (key, item) in world.exports.iter() {
+            if let WorldItem::Interface(id) = item {
+                self.interface_names.insert(*id, key.clone());
+                self.exported_interfaces.insert(*id);
+            }
+        }
+        Ok(())
+    }
+
+    fn core_symbols(&self, resolve: &Resolve, world: WorldId) -> CoreSymbols {
+        let mut symbols = CoreSymbols::new(resolve, world);
+        symbols.export_realloc(resolve, world);
+        symbols
+    }
+
+    fn import_interface(
+        &mut self,
+        resolve: &Resolve,
+        name: &WorldKey,
+        id: InterfaceId,
+        _files: &mut Files,
+    ) {
+        let mut gen = self.interface(resolve, Some(id), Some(name), false);
+        gen.types(id);
+
+        for (i, (_name, func)) in resolve.interfaces[id].functions.iter().enumerate() {
+            if i == 0 {
+                let name = resolve.name_world_key(name);
+                uwriteln!(gen.c_src, "\n// Imported Functions from `{name}`");
+            }
+            gen.import(func);
+        }
+
+        gen.finish();
+    }
+
+    fn import_funcs(
+        &mut self,
+        resolve: &Resolve,
+        world: WorldId,
+        funcs: &[(&str, &Function)],
+        _files: &mut Files,
+    ) {
+        let name = &resolve.worlds[world].name;
+        let mut gen = self.interface(resolve, None, None, false);
+
+        for (i, (_name, func)) in funcs.iter().enumerate() {
+            if i == 0 {
+                uwriteln!(gen.c_src, "\n// Imported Functions from `{name}`");
+            }
+            gen.import(func);
+        }
+
+        gen.finish();
+    }
+
+    fn export_interface(
+        &mut self,
+        resolve: &Resolve,
+        name: &WorldKey,
+        id: InterfaceId,
+        _files: &mut Files,
+    ) -> Result<()> {
+        let mut gen = self.interface(resolve, Some(id), Some(name), true);
+        gen.types(id);
+
+        for (i, (_name, func)) in resolve.interfaces[id].functions.iter().enumerate() {
+            if i == 0 {
+                let name = resolve.name_world_key(name);
+                uwriteln!(gen.c_src, "\n// Exported Functions from `{name}`");
+            }
+            gen.export(func);
+        }
+
+        gen.finish();
+        Ok(())
+    }
+
+    fn export_funcs(
+        &mut self,
+        resolve: &Resolve,
+        world: WorldId,
+        funcs: &[(&str, &Function)],
+        _files: &mut Files,
+    ) -> Result<()> {
+        let name = &resolve.worlds[world].name;
+        let mut gen = self.interface(resolve, None, None, true);
+
+        for (i, (_name, func)) in funcs.iter().enumerate() {
+            if i == 0 {
+                uwriteln!(gen.c_src, "\n// Exported Functions from `{name}`");
+            }
+            gen.export(func);
+        }
+
+        gen.finish();
+        Ok(())
+    }
+
+    fn import_types(
+        &mut self,
+        resolve: &Resolve,
+        _world: WorldId,
+        types: &[(&str, TypeId)],
+        _files: &mut Files,
+    ) {
+        let mut gen = self.interface(resolve, None, None, false);
+        for (name, id) in types {
+            gen.define_type(name, *id);
+        }
+        gen.finish();
+    }
+
+    fn finish(&mut self, resolve: &Resolve, id: WorldId, files: &mut Files) {
+        let world = &resolve.worlds[id];
+        let snake = world.name.to_snake_case();
+        let version = env!("CARGO_PKG_VERSION");
+
+        let mut h_str = Source::default();
+        wit_bindgen_core::generated_preamble(&mut h_str, version);
+        uwriteln!(
+            h_str,
+            "#ifndef __BINDINGS_{0}_HPP
+            #define __BINDINGS_{0}_HPP",
+            world.name.to_shouty_snake_case(),
+        );
+        for include in [
+            "<cstdint>",
+            "<functional>",
+            "<memory>",
+            "<optional>",
+            "<string>",
+            "<string_view>",
+            "<tuple>",
+            "<utility>",
+            "<variant>",
+            "<vector>",
+        ] {
+            uwriteln!(h_str, "#include {include}");
+        }
+        h_str.push_str(EXPECTED);
+        h_str.push_str(&self.h_fwd);
+        h_str.push_str(&self.h_types);
+        h_str.push_str(&self.h_funcs);
+        uwriteln!(h_str, "\n#endif");
+
+        let mut c_str = Source::default();
+        wit_bindgen_core::generated_preamble(&mut c_str, version);
+        uwriteln!(c_str, "#include \"{snake}.hpp\"");
+        for include in ["<cstdlib>", "<cstring>", "<deque>"] {
+            uwriteln!(c_str, "#include {include}");
+        }
+
+        let linking_symbol = component_type_object::linking_symbol(&world.name);
+        let linking = format!(
+            "
+                extern \"C\" void {linking_symbol}(void);
+                extern \"C\" void {linking_symbol}_public_use_in_this_compilation_unit(void) {{
+                    {linking_symbol}();
+                }}
+            "
+        );
+        c_str.push_str(&linking);
+
+        self.print_intrinsics(&mut c_str);
+
+        // Declare a statically-allocated return area, if needed. This is only
+        // used by exports, because imports allocate their return area on the
+        // stack.
+        if self.return_pointer_area_size > 0 {
+            let ret_area = format!(
+                "
+                    namespace {{
+                    alignas({}) uint8_t RET_AREA[{}];
+                    }}
+                ",
+                self.return_pointer_area_align, self.return_pointer_area_size,
+            );
+            c_str.push_str(&ret_area);
+        }
+        c_str.push_str(&self.c_src);
+
+        files.push(&format!("{snake}.hpp"), h_str.as_bytes());
+        files.push(&format!("{snake}.cpp"), c_str.as_bytes());
+        files.push(
+            &format!("{snake}_component_type.o",),
+            component_type_object::object(resolve, id, StringEncoding::UTF8)
+                .unwrap()
+                .as_slice(),
+        );
+        for name in [
+            format!("{snake}.hpp"),
+            format!("{snake}.cpp"),
+            format!("{snake}_component_type.o"),
+        ] {
+            files.set_source(&name, WitItem::world(resolve, id));
+        }
+    }
+}
+
+/// A minimal version of C++23's `std::expected` which WIT `result`s are
+/// mapped to.
+const EXPECTED: &str = "
+    #ifndef WIT_BINDGEN_CPP_EXPECTED
+    #define WIT_BINDGEN_CPP_EXPECTED
+    namespace wit {
+    // The error of a failed `expected`, mirroring C++23's `std::unexpected`.
+    template <typename E> struct unexpected {
+        E value;
+        explicit unexpected(E value) : value(std::move(value)) {}
+    };
+
+    // Either a value or an error, mirroring C++23's `std::expected`.
+    template <typename T, typename E> class expected {
+    public:
+        expected() = default;
+        expected(T value) : storage_(std::in_place_index<0>, std::move(value)) {}
+        template <typename G>
+        expected(unexpected<G> error) : storage_(std::in_place_index<1>, std::move(error.value)) {}
+
+        bool has_value() const { return storage_.index() == 0; }
+        explicit operator bool() const { return has_value(); }
+
+        T& value() & { return std::get<0>(storage_); }
+        T const& value() const& { return std::get<0>(storage_); }
+        T&& value() && { return std::get<0>(std::move(storage_)); }
+        E& error() & { return std::get<1>(storage_); }
+        E const& error() const& { return std::get<1>(storage_); }
+        E&& error() && { return std::get<1>(std::move(storage_)); }
+
+        T& operator*() & { return value(); }
+        T const& operator*() const& { return value(); }
+        T* operator->() { return &value(); }
+        T const* operator->() const { return &value(); }
+
+    private:
+        std::variant<T, E> storage_;
+    };
+    }
+    #endif
+";
+
+struct InterfaceGenerator<'a> {
+    gen: &'a mut Cpp,
+    resolve: &'a Resolve,
+    interface: Option<InterfaceId>,
+    key: Option<&'a WorldKey>,
+    in_export: bool,
+
+    // Forward declarations of the classes, structs and enums of this
+    // namespace, which lets type definitions and function declarations refer to
+    // types defined after them.
+    h_fwd: Source,
+    // Type definitions, printed in the order of the type arena so that types
+    // are always defined before they're used by value.
+    h_types: Vec<(TypeId, String)>,
+    h_funcs: Source,
+    c_src: Source,
+}
+
+impl InterfaceGenerator<'_> {
+    fn finish(mut self) {
+        let ns = self.namespace();
+        if !self.h_fwd.is_empty() {
+            uwriteln!(self.gen.h_fwd, "\nnamespace {ns} {{");
+            self.gen.h_fwd.push_str(&self.h_fwd);
+            uwriteln!(self.gen.h_fwd, "}}");
+        }
+        if !self.h_types.is_empty() {
+            uwriteln!(self.gen.h_types, "\nnamespace {ns} {{");
+            self.h_types.sort_by_key(|(id, _)| id.index());
+            for (i, (_, src)) in self.h_types.iter().enumerate() {
+                if i > 0 {
+                    self.gen.h_types.push_str("\n");
+                }
+                self.gen.h_types.push_str(src);
+            }
+            uwriteln!(self.gen.h_types, "}}");
+        }
+        if !self.h_funcs.is_empty() {
+            uwriteln!(self.gen.h_funcs, "\nnamespace {ns} {{");
+            self.gen.h_funcs.push_str(&self.h_funcs);
+            uwriteln!(self.gen.h_funcs, "}}");
+        }
+        self.gen.c_src.push_str(&self.c_src);
+    }
+
+    fn namespace(&self) -> String {
+        self.gen
+            .namespace(self.resolve, self.interface, self.in_export)
+    }
+
+    /// Returns the name of the core wasm module this interface's functions
+    /// are imported from, or exported under.
+    fn wasm_module(&self) -> String {
+        match self.key {
+            Some(key) => self.resolve.name_world_key(key),
+            None => "$root".to_string(),
+        }
+    }
+
+    /// Returns the prefix of the C symbols of this interface's items.
+    fn c_prefix(&self) -> String {
+        match self.key {
+            Some(key) => interface_identifier(key, self.resolve, self.in_export),
+            None => self.gen.world.to_snake_case(),
+        }
+    }
+
+    /// Returns the fully-qualified name of the named type `id`.
+    fn type_path(&self, id: TypeId) -> String {
+        let ty = &self.resolve.types[id];
+        let ns = match ty.owner {
+            TypeOwner::Interface(owner) => self.gen.namespace(
+                self.resolve,
+                Some(owner),
+                self.in_export && self.gen.exported_interfaces.contains(&owner),
+            ),
+            TypeOwner::World(_) => self.gen.namespace(self.resolve, None, false),
+            TypeOwner::None => unreachable!("type path of an anonymous type"),
+        };
+        format!("::{ns}::{}", type_ident(ty.name.as_deref().unwrap()))
+    }
+
+    /// Whether `resource` is implemented by this module, as opposed to being
+    /// imported from the host.
+    fn is_exported_resource(&self, resource: TypeId) -> bool {
+        match self.resolve.types[dealias(self.resolve, resource)].owner {
+            TypeOwner::Interface(owner) => {
+                self.in_export && self.gen.exported_interfaces.contains(&owner)
+            }
+            _ => false,
+        }
+    }
+
+    fn type_name(&self, ty: &Type) -> String {
+        match ty {
+            Type::Bool => "bool".to_string(),
+            Type::Char => "char32_t".to_string(),
+            Type::U8 => "uint8_t".to_string(),
+            Type::S8 => "int8_t".to_string(),
+            Type::U16 => "uint16_t".to_string(),
+            Type::S16 => "int16_t".to_string(),
+            Type::U32 => "uint32_t".to_string(),
+            Type::S32 => "int32_t".to_string(),
+            Type::U64 => "uint64_t".to_string(),
+            Type::S64 => "int64_t".to_string(),
+            Type::Float32 => "float".to_string(),
+            Type::Float64 => "double".to_string(),
+            Type::String => "std::string".to_string(),
+            Type::Id(id) => match &self.resolve.types[*id].kind {
+                TypeDefKind::Type(t) => self.type_name(t),
+                TypeDefKind::Record(_)
+                | TypeDefKind::Resource
+                | TypeDefKind::Flags(_)
+                | TypeDefKind::Enum(_)
+                | TypeDefKind::Variant(_) => self.type_path(*id),
+                TypeDefKind::Handle(Handle::Own(resource)) => {
+                    let resource = dealias(self.resolve, *resource);
+                    let path = self.type_path(resource);
+                    if self.is_exported_resource(resource) {
+                        format!("std::unique_ptr<{path}>")
+                    } else {
+                        path
+                    }
+                }
+                TypeDefKind::Handle(Handle::Borrow(resource)) => {
+                    let resource = dealias(self.resolve, *resource);
+                    let path = self.type_path(resource);
+                    if self.is_exported_resource(resource) {
+                        format!("std::reference_wrapper<{path}>")
+                    } else {
+                        format!("std::reference_wrapper<{path} const>")
+                    }
+                }
+                TypeDefKind::Tuple(t) => {
+                    let types = t
+                        .types
+                        .iter()
+                        .map(|ty| self.type_name(ty))
+                        .collect::<Vec<_>>();
+                    format!("std::tuple<{}>", types.join(", "))
+                }
+                TypeDefKind::Option(t) => format!("std::optional<{}>", self.type_name(t)),
+                TypeDefKind::Result(r) => format!(
+                    "::wit::expected<{}, {}>",
+                    self.optional_type_name(r.ok.as_ref()),
+                    self.optional_type_name(r.err.as_ref()),
+                ),
+                TypeDefKind::List(t) => format!("std::vector<{}>", self.type_name(t)),
+                TypeDefKind::Future(_) | TypeDefKind::Stream(_) => {
+                    unreachable!(
+                        "`future` and `stream` types are rejected by `supports_async_types`"
+                    )
+                }
+                TypeDefKind::Unknown => unreachable!(),
+            },
+        }
+    }
+
+    fn optional_type_name(&self, ty: Option<&Type>) -> String {
+        match ty {
+            Some(ty) => self.type_name(ty),
+            None => "std::monostate".to_string(),
+        }
+    }
+
+    /// Returns the type a parameter of type `ty` is passed as.
+    ///
+    /// Imports borrow their arguments where possible, while the functions
+    /// implementing exports take ownership of theirs.
+    fn param_type(&self, ty: &Type) -> String {
+        let ty = resolve_type(self.resolve, ty);
+        let id = match ty {
+            Type::String if !self.in_export => return "std::string_view".to_string(),
+            Type::Id(id) => id,
+            _ => return self.type_name(&ty),
+        };
+        match &self.resolve.types[id].kind {
+            TypeDefKind::Handle(Handle::Borrow(resource)) => {
+                let resource = dealias(self.resolve, *resource);
+                let path = self.type_path(resource);
+                if self.is_exported_resource(resource) {
+                    format!("{path}&")
+                } else {
+                    format!("{path} const&")
+                }
+            }
+            TypeDefKind::Handle(Handle::Own(_)) | TypeDefKind::Enum(_) | TypeDefKind::Flags(_) => {
+                self.type_name(&ty)
+            }
+            _ if self.in_export || self.gen.types.get(id).has_own_handle => self.type_name(&ty),
+            _ => format!("{} const&", self.type_name(&ty)),
+        }
+    }
+
+    fn results_type(&self, func: &Function) -> String {
+        match func.results.len() {
+            0 => "void".to_string(),
+            1 => self.type_name(func.results.iter_types().next().unwrap()),
+            _ => {
+                let types = func
+                    .results
+                    .iter_types()
+                    .map(|ty| self.type_name(ty))
+                    .collect::<Vec<_>>();
+                format!("std::tuple<{}>", types.join(", "))
+            }
+        }
+    }
+
+    /// Returns the names of the parameters of `func`, excluding `self`.
+    fn param_names(&self, func: &Function) -> Vec<String> {
+        let skip = match func.kind {
+            FunctionKind::Method(_) => 1,
+            _ => 0,
+        };
+        func.params
+            .iter()
+            .skip(skip)
+            .map(|(name, _)| to_c_ident(&name.to_snake_case()))
+            .collect()
+    }
+
+    fn params(&self, func: &Function) -> String {
+        let skip = match func.kind {
+            FunctionKind::Method(_) => 1,
+            _ => 0,
+        };
+        let names = self.param_names(func);
+        func.params
+            .iter()
+            .skip(skip)
+            .zip(names)
+            .map(|((_, ty), name)| format!("{} {name}", self.param_type(ty)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn func_ident(&self, func: &Function) -> String {
+        let name = to_c_ident(&func.item_name().to_snake_case());
+        match func.kind {
+            FunctionKind::Freestanding => name,
+            _ if RESOURCE_MEMBERS.contains(&name.as_str()) => format!("{name}_"),
+            _ => name,
+        }
+    }
+
+    /// Returns the declaration of `func` as it appears in the header, where
+    /// the members of resources are declared within their class.
+    fn declaration(&self, func: &Function) -> String {
+        let name = self.func_ident(func);
+        let params = self.params(func);
+        match &func.kind {
+            FunctionKind::Freestanding => {
+                format!("{} {name}({params});", self.results_type(func))
+            }
+            FunctionKind::Method(_) if self.in_export => {
+                format!("virtual {} {name}({params}) = 0;", self.results_type(func))
+            }
+            FunctionKind::Method(_) => {
+                format!("{} {name}({params}) const;", self.results_type(func))
+            }
+            FunctionKind::Static(_) => {
+                format!("static {} {name}({params});", self.results_type(func))
+            }
+            FunctionKind::Constructor(resource) => {
+                let ident = type_ident(self.resolve.types[*resource].name.as_deref().unwrap());
+                if self.in_export {
+                    format!("static std::unique_ptr<{ident}> create({params});")
+                } else if func.params.len() == 1 {
+                    format!("explicit {ident}({params});")
+                } else {
+                    format!("{ident}({params});")
+                }
+            }
+        }
+    }
+
+    /// Returns the functions which are members of `resource`.
+    fn resource_functions(&self, resource: TypeId) -> Vec<&Function> {
+        let is_member = |func: &&Function| match func.kind {
+            FunctionKind::Method(id) | FunctionKind::Static(id) | FunctionKind::Constructor(id) => {
+                id == resource
+            }
+            FunctionKind::Freestanding => false,
+        };
+        match self.resolve.types[resource].owner {
+            TypeOwner::Interface(owner) => self.resolve.interfaces[owner]
+                .functions
+                .values()
+                .filter(is_member)
+                .collect(),
+            TypeOwner::World(owner) => self.resolve.worlds[owner]
+                .imports
+                .values()
+                .filter_map(|item| match item {
+                    WorldItem::Function(func) => Some(func),
+                    _ => None,
+                })
+                .filter(is_member)
+                .collect(),
+            TypeOwner::None => Vec::new(),
+        }
+    }
+
+    fn import(&mut self, func: &Function) {
+        let sig = self.resolve.wasm_signature(AbiVariant::GuestImport, func);
+
+        // Print the declaration of the actual wasm import that we'll be
+        // calling, which has the raw wasm signature.
+        let c_name = wit_bindgen_c::func_name(self.resolve, &self.gen.world, self.key, func, false);
+        let import_name = format!("__wasm_import_{c_name}");
+        uwriteln!(
+            self.c_src,
+            "\nextern \"C\" __attribute__((__import_module__(\"{}\"), __import_name__(\"{}\")))",
+            self.wasm_module(),
+            func.name,
+        );
+        uwriteln!(
+            self.c_src,
+            "{} {import_name}({});",
+            wasm_results(&sig),
+            wasm_params(&sig),
+        );
+
+        if let FunctionKind::Freestanding = func.kind {
+            let decl = self.declaration(func);
+            docs(&mut self.h_funcs, &func.docs);
+            uwriteln!(self.h_funcs, "{decl}");
+        }
+
+        // Print the definition of the C++ function, which lowers its arguments
+        // to call the import and lifts the results.
+        let ns = self.namespace();
+        let name = self.func_ident(func);
+        let params = self.params(func);
+        match &func.kind {
+            FunctionKind::Freestanding => {
+                let ret = self.results_type(func);
+                uwriteln!(self.c_src, "\n{ret} {ns}::{name}({params}) {{");
+            }
+            FunctionKind::Method(resource) => {
+                let ret = self.results_type(func);
+                let class = type_ident(self.resolve.types[*resource].name.as_deref().unwrap());
+                uwriteln!(
+                    self.c_src,
+                    "\n{ret} {ns}::{class}::{name}({params}) const {{"
+                );
+            }
+            FunctionKind::Static(resource) => {
+                let ret = self.results_type(func);
+                let class = type_ident(self.resolve.types[*resource].name.as_deref().unwrap());
+                uwriteln!(self.c_src, "\n{ret} {ns}::{class}::{name}({params}) {{");
+            }
+            FunctionKind::Constructor(resource) => {
+                let class = type_ident(self.resolve.types[*resource].name.as_deref().unwrap());
+                uwriteln!(self.c_src, "\n{ns}::{class}::{class}({params}) {{");
+            }
+        }
+
+        let mut f = FunctionBindgen::new(self, import_name);
+        if let FunctionKind::Method(_) = func.kind {
+            f.params.push("(*this)".to_string());
+        }
+        for name in f.gen.param_names(func) {
+            f.locals.insert(&name).unwrap();
+            f.params.push(name);
+        }
+        abi::call(
+            f.gen.resolve,
+            AbiVariant::GuestImport,
+            LiftLower::LowerArgsLiftResults,
+            func,
+            &mut f,
+        );
+
+        let FunctionBindgen {
+            src,
+            ret_area,
+            ret_area_size,
+            ret_area_align,
+            cleanup,
+            ..
+        } = f;
+        if let Some(ret_area) = ret_area {
+            uwriteln!(
+                self.c_src,
+                "alignas({ret_area_align}) uint8_t {ret_area}[{ret_area_size}];"
+            );
+        }
+        if let Some(cleanup) = cleanup {
+            uwriteln!(self.c_src, "std::vector<void*> {cleanup};");
+        }
+        self.c_src.push_str(&src);
+        self.c_src.push_str("}\n");
+    }
+
+    fn export(&mut self, func: &Function) {
+        let sig = self.resolve.wasm_signature(AbiVariant::GuestExport, func);
+        let core_module_name = self.key.map(|key| self.resolve.name_world_key(key));
+        let export_name = func.core_export_name(core_module_name.as_deref());
+
+        // Declare the function the user implements in the header.
+        if let FunctionKind::Freestanding = func.kind {
+            let decl = self.declaration(func);
+            docs(&mut self.h_funcs, &func.docs);
+            uwriteln!(self.h_funcs, "{decl}");
+        }
+
+        // Generate the function with the raw wasm signature that's exported,
+        // which lifts its arguments to call the user's implementation and
+        // lowers the results.
+        let c_name = wit_bindgen_c::func_name(self.resolve, &self.gen.world, self.key, func, true);
+        let export_fn = format!("__wasm_export_{c_name}");
+        uwriteln!(
+            self.c_src,
+            "\nextern \"C\" __attribute__((__export_name__(\"{export_name}\")))"
+        );
+
+        let mut f = FunctionBindgen::new(self, String::new());
+        let mut params = Vec::new();
+        for param in sig.params.iter() {
+            let name = f.locals.tmp("arg");
+            params.push(format!("{} {name}", wasm_type(*param)));
+            f.params.push(name);
+        }
+        abi::call(
+            f.gen.resolve,
+            AbiVariant::GuestExport,
+            LiftLower::LiftArgsLowerResults,
+            func,
+            &mut f,
+        );
+        let FunctionBindgen { src, borrows, .. } = f;
+
+        uwriteln!(
+            self.c_src,
+            "{} {export_fn}({}) {{",
+            wasm_results(&sig),
+            params.join(", "),
+        );
+        for (resource, name) in borrows {
+            let path = self.type_path(resource);
+            uwriteln!(self.c_src, "std::deque<{path}> {name};");
+        }
+        self.c_src.push_str(&src);
+        self.c_src.push_str("}\n");
+
+        if abi::guest_export_needs_post_return(self.resolve, func) {
+            uwriteln!(
+                self.c_src,
+                "\nextern \"C\" __attribute__((__weak__, __export_name__(\"cabi_post_{export_name}\")))"
+            );
+
+            let mut f = FunctionBindgen::new(self, String::new());
+            let mut params = Vec::new();
+            for result in sig.results.iter() {
+                let name = f.locals.tmp("arg");
+                params.push(format!("{} {name}", wasm_type(*result)));
+                f.params.push(name);
+            }
+            abi::post_return(f.gen.resolve, func, &mut f);
+            let FunctionBindgen { src, .. } = f;
+
+            uwriteln!(
+                self.c_src,
+                "void {export_fn}_post_return({}) {{",
+                params.join(", "),
+            );
+            self.c_src.push_str(&src);
+            self.c_src.push_str("}\n");
+        }
+    }
+
+    fn push_type(&mut self, id: TypeId, src: Source) {
+        self.h_types.push((id, src.into()));
+    }
+
+    fn print_import_resource(&mut self, id: TypeId, name: &str, src: &mut Source) {
+        let ident = type_ident(name);
+        let funcs = self.resource_functions(id);
+        uwriteln!(src, "class {ident} {{");
+        uwriteln!(src, "public:");
+        for func in funcs {
+            let decl = self.declaration(func);
+            docs(src, &func.docs);
+            uwriteln!(src, "{decl}");
+        }
+        let members = format!(
+            "
+                // Takes ownership of the resource `handle`.
+                static {ident} from_handle(int32_t handle) {{ return {ident}(std::in_place, handle); }}
+                int32_t handle() const {{ return handle_; }}
+                // Releases ownership of the handle, which the caller then has to drop.
+                int32_t into_handle() {{ return std::exchange(handle_, -1); }}
+
+                {ident}({ident}&& other) noexcept : handle_(std::exchange(other.handle_, -1)) {{}}
+                {ident}& operator=({ident}&& other) noexcept {{
+                    std::swap(handle_, other.handle_);
+                    return *this;
+                }}
+                {ident}({ident} const&) = delete;
+                {ident}& operator=({ident} const&) = delete;
+                ~{ident}();
+
+            private:
+                {ident}(std::in_place_t, int32_t handle) : handle_(handle) {{}}
+                int32_t handle_ = -1;
+            }};
+            "
+        );
+        src.push_str(&members);
+
+        let ns = self.namespace();
+        let module = self.wasm_module();
+        let drop = format!(
+            "__wasm_import_{}_{}_drop",
+            self.c_prefix(),
+            name.to_snake_case()
+        );
+        let dtor = format!(
+            r#"
+                extern "C" __attribute__((__import_module__("{module}"), __import_name__("[resource-drop]{name}")))
+                void {drop}(int32_t);
+
+                {ns}::{ident}::~{ident}() {{
+                    if (handle_ != -1) {{
+                        {drop}(handle_);
+                    }}
+                }}
+            "#
+        );
+        self.c_src.push_str(&dtor);
+    }
+
+    fn print_export_resource(&mut self, id: TypeId, name: &str, src: &mut Source) {
+        let ident = type_ident(name);
+        let funcs = self.resource_functions(id);
+        uwriteln!(src, "class {ident} {{");
+        uwriteln!(src, "public:");
+        uwriteln!(src, "virtual ~{ident}() = default;");
+        for func in funcs {
+            let decl = self.declaration(func);
+            docs(src, &func.docs);
+            uwriteln!(src, "{decl}");
+        }
+        let members = format!(
+            "
+                // Transfers ownership of `rep` to a new handle.
+                static int32_t wasm_lower_own(std::unique_ptr<{ident}> rep);
+                // Takes ownership of the representation of the handle `handle`.
+                static std::unique_ptr<{ident}> wasm_lift_own(int32_t handle);
+                // Destroys the representation of a handle dropped by its owner.
+                static void wasm_dtor(int32_t rep);
+
+            private:
+                int32_t wasm_handle = -1;
+            }};
+            "
+        );
+        src.push_str(&members);
+
+        let ns = self.namespace();
+        let module = self.wasm_module();
+        let snake = name.to_snake_case();
+        let prefix = format!("{}_{snake}", self.c_prefix());
+        let intrinsics = format!(
+            r#"
+                extern "C" __attribute__((__import_module__("[export]{module}"), __import_name__("[resource-new]{name}")))
+                int32_t __wasm_import_{prefix}_new(int32_t);
+                extern "C" __attribute__((__import_module__("[export]{module}"), __import_name__("[resource-rep]{name}")))
+                int32_t __wasm_import_{prefix}_rep(int32_t);
+                extern "C" __attribute__((__import_module__("[export]{module}"), __import_name__("[resource-drop]{name}")))
+                void __wasm_import_{prefix}_drop(int32_t);
+
+                extern "C" __attribute__((__export_name__("{module}#[dtor]{name}")))
+                void __wasm_export_{prefix}_dtor(int32_t rep) {{
+                    {ns}::{ident}::wasm_dtor(rep);
+                }}
+
+                int32_t {ns}::{ident}::wasm_lower_own(std::unique_ptr<{ident}> rep) {{
+                    {ident}* ptr = rep.release();
+                    ptr->wasm_handle = __wasm_import_{prefix}_new((int32_t) (intptr_t) ptr);
+                    return ptr->wasm_handle;
+                }}
+
+                std::unique_ptr<::{ns}::{ident}> {ns}::{ident}::wasm_lift_own(int32_t handle) {{
+                    auto* ptr = ({ident}*) (intptr_t) __wasm_import_{prefix}_rep(handle);
+                    // Dropping the handle runs the destructor hook, which
+                    // leaves representations without a handle alone.
+                    ptr->wasm_handle = -1;
+                    __wasm_import_{prefix}_drop(handle);
+                    return std::unique_ptr<{ident}>(ptr);
+                }}
+
+                void {ns}::{ident}::wasm_dtor(int32_t rep) {{
+                    auto* ptr = ({ident}*) (intptr_t) rep;
+                    if (ptr->wasm_handle != -1) {{
+                        delete ptr;
+                    }}
+                }}
+            "#
+        );
+        self.c_src.push_str(&intrinsics);
+    }
+
+    fn print_alias(&mut self, id: TypeId, name: &str, ty: String, docs_: &Docs) {
+        let mut src = Source::default();
+        docs(&mut src, docs_);
+        uwriteln!(src, "using {} = {ty};", type_ident(name));
+        self.push_type(id, src);
+    }
+}
+
+impl<'a> wit_bindgen_core::InterfaceGenerator<'a> for InterfaceGenerator<'a> {
+    fn resolve(&self) -> &'a Resolve {
+        self.resolve
+    }
+
+    fn type_record(&mut self, id: TypeId, name: &str, record: &Record, docs_: &Docs) {
+        let ident = type_ident(name);
+        uwriteln!(self.h_fwd, "struct {ident};");
+
+        let mut src = Source::default();
+        docs(&mut src, docs_);
+        uwriteln!(src, "struct {ident} {{");
+        for field in record.fields.iter() {
+            docs(&mut src, &field.docs);
+            let ty = self.type_name(&field.ty);
+            uwriteln!(src, "{ty} {};", to_c_ident(&field.name.to_snake_case()));
+        }
+        uwriteln!(src, "}};");
+        self.push_type(id, src);
+    }
+
+    fn type_resource(&mut self, id: TypeId, name: &str, docs_: &Docs) {
+        uwriteln!(self.h_fwd, "class {};", type_ident(name));
+
+        let mut src = Source::default();
+        docs(&mut src, docs_);
+        if self.in_export {
+            self.print_export_resource(id, name, &mut src);
+        } else {
+            self.print_import_resource(id, name, &mut src);
+        }
+        self.push_type(id, src);
+    }
+
+    fn type_flags(&mut self, id: TypeId, name: &str, flags: &Flags, docs_: &Docs) {
+        let ident = type_ident(name);
+        let repr = int_repr(flags_repr(flags));
+        uwriteln!(self.h_fwd, "enum class {ident} : {repr};");
+
+        let mut src = Source::default();
+        docs(&mut src, docs_);
+        uwriteln!(src, "enum class {ident} : {repr} {{");
+        for (i, flag) in flags.flags.iter().enumerate() {
+            docs(&mut src, &flag.docs);
+            uwriteln!(src, "{} = 1ULL << {i},", flag.name.to_upper_camel_case());
+        }
+        uwriteln!(src, "}};");
+
+        let mask = match flags.flags.len() {
+            64 => u64::MAX,
+            n => (1u64 << n) - 1,
+        };
+        for op in ["|", "&", "^"] {
+            uwriteln!(
+                src,
+                "inline constexpr {ident} operator{op}({ident} a, {ident} b) {{ return {ident}(static_cast<{repr}>(a) {op} static_cast<{repr}>(b)); }}"
+            );
+        }
+        uwriteln!(
+            src,
+            "inline constexpr {ident} operator~({ident} a) {{ return {ident}(~static_cast<{repr}>(a) & {mask:#x}ULL); }}"
+        );
+        self.push_type(id, src);
+    }
+
+    fn type_tuple(&mut self, id: TypeId, name: &str, _tuple: &Tuple, docs: &Docs) {
+        let ty = self.type_name(&Type::Id(id));
+        self.print_alias(id, name, ty, docs);
+    }
+
+    fn type_variant(&mut self, id: TypeId, name: &str, variant: &Variant, docs_: &Docs) {
+        let ident = type_ident(name);
+        uwriteln!(self.h_fwd, "struct {ident};");
+
+        let mut src = Source::default();
+        docs(&mut src, docs_);
+        uwriteln!(src, "struct {ident} {{");
+        let mut cases = Vec::new();
+        for case in variant.cases.iter() {
+            let case_ident = case_ident(&ident, &case.name);
+            docs(&mut src, &case.docs);
+            match &case.ty {
+                Some(ty) => {
+                    let ty = self.type_name(ty);
+                    uwriteln!(src, "struct {case_ident} {{ {ty} value; }};");
+                }
+                None => uwriteln!(src, "struct {case_ident} {{}};"),
+            }
+            cases.push(case_ident);
+        }
+        uwriteln!(src, "std::variant<{}> variants;", cases.join(", "));
+        uwriteln!(src, "}};");
+        self.push_type(id, src);
+    }
+
+    fn type_option(&mut self, id: TypeId, name: &str, _payload: &Type, docs: &Docs) {
+        let ty = self.type_name(&Type::Id(id));
+        self.print_alias(id, name, ty, docs);
+    }
+
+    fn type_result(&mut self, id: TypeId, name: &str, _result: &Result_, docs: &Docs) {
+        let ty = self.type_name(&Type::Id(id));
+        self.print_alias(id, name, ty, docs);
+    }
+
+    fn type_enum(&mut self, id: TypeId, name: &str, enum_: &Enum, docs_: &Docs) {
+        let ident = type_ident(name);
+        let repr = int_repr(enum_.tag());
+        uwriteln!(self.h_fwd, "enum class {ident} : {repr};");
+
+        let mut src = Source::default();
+        docs(&mut src, docs_);
+        uwriteln!(src, "enum class {ident} : {repr} {{");
+        for case in enum_.cases.iter() {
+            docs(&mut src, &case.docs);
+            uwriteln!(src, "{},", case.name.to_upper_camel_case());
+        }
+        uwriteln!(src, "}};");
+        self.push_type(id, src);
+    }
+
+    fn type_alias(&mut self, id: TypeId, name: &str, _ty: &Type, docs: &Docs) {
+        let ty = self.type_name(&Type::Id(id));
+        self.print_alias(id, name, ty, docs);
+    }
+
+    fn type_list(&mut self, id: TypeId, name: &str, _ty: &Type, docs: &Docs) {
+        let ty = self.type_name(&Type::Id(id));
+        self.print_alias(id, name, ty, docs);
+    }
+
+    fn type_future(&mut self, _id: TypeId, _name: &str, _ty: &Option<Type>, _docs: &Docs) {
+        unreachable!("`future` types are rejected by `supports_async_types`")
+    }
+
+    fn type_stream(&mut self, _id: TypeId, _name: &str, _stream: &Stream, _docs: &Docs) {
+        unreachable!("`stream` types are rejected by `supports_async_types`")
+    }
+
+    fn type_builtin(&mut self, id: TypeId, name: &str, ty: &Type, docs: &Docs) {
+        let _ = (id, name, ty, docs);
+    }
+}
+
+struct FunctionBindgen<'a, 'b> {
+    gen: &'a mut InterfaceGenerator<'b>,
+    locals: Ns,
+    src: Source,
+    block_storage: Vec<Source>,
+    blocks: Vec<(String, Vec<String>)>,
+    payloads: Vec<String>,
+    params: Vec<String>,
+    wasm_import: String,
+
+    // The stack-allocated return area of an import, if it needs one.
+    ret_area: Option<String>,
+    ret_area_size: usize,
+    ret_area_align: usize,
+
+    // The list of temporary allocations made to lower the arguments of an
+    // import, which are freed once it returns.
+    cleanup: Option<String>,
+
+    // The imported resources borrowed by an export, which are kept alive, and
+    // dropped, for the duration of the call.
+    borrows: Vec<(TypeId, String)>,
+}
+
+impl<'a, 'b> FunctionBindgen<'a, 'b> {
+    fn new(gen: &'a mut InterfaceGenerator<'b>, wasm_import: String) -> FunctionBindgen<'a, 'b> {
+        FunctionBindgen {
+            gen,
+            locals: Default::default(),
+            src: Default::default(),
+            block_storage: Vec::new(),
+            blocks: Vec::new(),
+            payloads: Vec::new(),
+            params: Vec::new(),
+            wasm_import,
+            ret_area: None,
+            ret_area_size: 0,
+            ret_area_align: 0,
+            cleanup: None,
+            borrows: Vec::new(),
+        }
+    }
+
+    fn load(&mut self, ty: &str, offset: i32, operands: &[String], results: &mut Vec<String>) {
+        results.push(format!(
+            "*(({ty}*) (intptr_t) (({}) + {offset}))",
+            operands[0]
+        ));
+    }
+
+    fn load_ext(&mut self, ty: &str, offset: i32, operands: &[String], results: &mut Vec<String>) {
+        self.load(ty, offset, operands, results);
+        let result = results.pop().unwrap();
+        results.push(format!("(int32_t) ({result})"));
+    }
+
+    fn store(&mut self, ty: &str, offset: i32, operands: &[String]) {
+        uwriteln!(
+            self.src,
+            "*(({ty}*) (intptr_t) (({}) + {offset})) = {};",
+            operands[1],
+            operands[0]
+        );
+    }
+
+    fn bitcast(&mut self, ty: &str, op: &str) -> String {
+        self.gen.gen.needs_bitcast = true;
+        format!("bitcast<{ty}>({op})")
+    }
+
+    /// Declares a local holding the result of a lift, and returns the
+    /// expression moving out of it.
+    fn lifted(&mut self, name: &str, ty: &str, value: &str) -> String {
+        let local = self.locals.tmp(name);
+        uwriteln!(self.src, "{ty} {local} = {value};");
+        format!("std::move({local})")
+    }
+
+    fn lower_list(
+        &mut self,
+        size: usize,
+        op: &str,
+        realloc: Option<&str>,
+        results: &mut Vec<String>,
+    ) {
+        match realloc {
+            // Imports borrow the argument for the duration of the call.
+            None => {
+                results.push(format!("(int32_t) (intptr_t) ({op}).data()"));
+                results.push(format!("(int32_t) ({op}).size()"));
+            }
+            // Exports transfer ownership of a copy, freed in the post-return.
+            Some(_) => {
+                let vec = self.locals.tmp("vec");
+                let ptr = self.locals.tmp("ptr");
+                uwriteln!(self.src, "auto&& {vec} = {op};");
+                uwriteln!(
+                    self.src,
+                    "int32_t {ptr} = (int32_t) (intptr_t) malloc({vec}.size() * {size});"
+                );
+                uwriteln!(
+                    self.src,
+                    "memcpy((void*) (intptr_t) {ptr}, {vec}.data(), {vec}.size() * {size});"
+                );
+                results.push(ptr);
+                results.push(format!("(int32_t) {vec}.size()"));
+            }
+        }
+    }
+
+    /// Declares the locals that the lowered representation of a variant is
+    /// assigned to in each case.
+    fn variant_results(&mut self, name: &str, tys: &[WasmType], results: &mut Vec<String>) {
+        for ty in tys {
+            let local = self.locals.tmp(name);
+            uwriteln!(self.src, "{} {local} = 0;", wasm_type(*ty));
+            results.push(local);
+        }
+    }
+
+    fn assign_variant_results(&mut self, results: &[String], operands: &[String]) {
+        for (result, op) in results.iter().zip(operands) {
+            uwriteln!(self.src, "{result} = {op};");
+        }
+    }
+}
+
+impl Bindgen for FunctionBindgen<'_, '_> {
+    type Operand = String;
+
+    fn sizes(&self) -> &SizeAlign {
+        &self.gen.gen.sizes
+    }
+
+    fn push_block(&mut self) {
+        let prev = mem::take(&mut self.src);
+        self.block_storage.push(prev);
+    }
+
+    fn finish_block(&mut self, operands: &mut Vec<String>) {
+        let to_restore = self.block_storage.pop().unwrap();
+        let src = mem::replace(&mut self.src, to_restore);
+        self.blocks.push((src.into(), mem::take(operands)));
+    }
+
+    fn return_pointer(&mut self, size: usize, align: usize) -> String {
+        let ptr = self.locals.tmp("ptr");
+
+        // Use a stack-based return area for imports, because exports need
+        // their return area to be live until the post-return call.
+        if self.gen.in_export {
+            self.gen.gen.return_pointer_area_size = self.gen.gen.return_pointer_area_size.max(size);
+            self.gen.gen.return_pointer_area_align =
+                self.gen.gen.return_pointer_area_align.max(align);
+            uwriteln!(self.src, "int32_t {ptr} = (int32_t) (intptr_t) RET_AREA;");
+        } else {
+            self.ret_area_size = self.ret_area_size.max(size);
+            self.ret_area_align = self.ret_area_align.max(align);
+            if self.ret_area.is_none() {
+                self.ret_area = Some(self.locals.tmp("ret_area"));
+            }
+            let ret_area = self.ret_area.as_deref().unwrap();
+            uwriteln!(self.src, "int32_t {ptr} = (int32_t) (intptr_t) {ret_area};");
+        }
+
+        ptr
+    }
+
+    fn is_list_canonical(&self, resolve: &Resolve, ty: &Type) -> bool {
+        matches!(
+            resolve_type(resolve, ty),
+            Type::U8
+                | Type::S8
+                | Type::U16
+                | Type::S16
+                | Type::U32
+                | Type::S32
+                | Type::U64
+                | Type::S64
+                | Type::Float32
+                | Type::Float64
+        )
+    }
+
+    fn emit(
+        &mut self,
+        resolve: &Resolve,
+        inst: &Instruction<'_>,
+        operands: &mut Vec<String>,
+        results: &mut Vec<String>,
+    ) {
+        match inst {
+            Instruction::GetArg { nth } => results.push(self.params[*nth].clone()),
+            Instruction::I32Const { val } => results.push(val.to_string()),
+            Instruction::ConstZero { tys } => {
+                for _ in tys.iter() {
+                    results.push("0".to_string());
+                }
+            }
+
+            // TODO: checked?
+            Instruction::U8FromI32 => results.push(format!("(uint8_t) ({})", operands[0])),
+            Instruction::S8FromI32 => results.push(format!("(int8_t) ({})", operands[0])),
+            Instruction::U16FromI32 => results.push(format!("(uint16_t) ({})", operands[0])),
+            Instruction::S16FromI32 => results.push(format!("(int16_t) ({})", operands[0])),
+            Instruction::U32FromI32 => results.push(format!("(uint32_t) ({})", operands[0])),
+            Instruction::S32FromI32 | Instruction::S64FromI64 => results.push(operands[0].clone()),
+            Instruction::U64FromI64 => results.push(format!("(uint64_t) ({})", operands[0])),
+
+            Instruction::I32FromU8
+            | Instruction::I32FromS8
+            | Instruction::I32FromU16
+            | Instruction::I32FromS16
+            | Instruction::I32FromU32 => {
+                results.push(format!("(int32_t) ({})", operands[0]));
+            }
+            Instruction::I32FromS32 | Instruction::I64FromS64 => results.push(operands[0].clone()),
+            Instruction::I64FromU64 => {
+                results.push(format!("(int64_t) ({})", operands[0]));
+            }
+
+            // f32/f64 have the same representation for the import type and
+            // internal type, so these are no-ops.
+            Instruction::Float32FromF32
+            | Instruction::Float64FromF64
+            | Instruction::F32FromFloat32
+            | Instruction::F64FromFloat64 => {
+                results.push(operands[0].clone());
+            }
+
+            Instruction::CharFromI32 => {
+                results.push(format!("(char32_t) ({})", operands[0]));
+            }
+            Instruction::I32FromChar => {
+                results.push(format!("(int32_t) ({})", operands[0]));
+            }
+
+            Instruction::Bitcasts { casts } => {
+                for (cast, op) in casts.iter().zip(operands) {
+                    let op = op.to_string();
+                    let result = match cast {
+                        Bitcast::I32ToF32 => self.bitcast("float", &op),
+                        Bitcast::I64ToF32 => self.bitcast("float", &format!("(int32_t) ({op})")),
+                        Bitcast::F32ToI32 => self.bitcast("int32_t", &op),
+                        Bitcast::F32ToI64 => {
+                            format!("(int64_t) {}", self.bitcast("int32_t", &op))
+                        }
+                        Bitcast::I64ToF64 => self.bitcast("double", &op),
+                        Bitcast::F64ToI64 => self.bitcast("int64_t", &op),
+                        Bitcast::I32ToI64 => format!("(int64_t) ({op})"),
+                        Bitcast::I64ToI32 => format!("(int32_t) ({op})"),
+                        Bitcast::None => op,
+                    };
+                    results.push(result);
+                }
+            }
+
+            Instruction::BoolFromI32 => {
+                results.push(format!("(bool) ({})", operands[0]));
+            }
+            Instruction::I32FromBool => {
+                results.push(format!("(int32_t) ({})", operands[0]));
+            }
+
+            Instruction::RecordLower { record, .. } => {
+                let op = &operands[0];
+                for field in record.fields.iter() {
+                    results.push(format!(
+                        "({op}).{}",
+                        to_c_ident(&field.name.to_snake_case())
+                    ));
+                }
+            }
+            Instruction::RecordLift { ty, .. } => {
+                let path = self.gen.type_path(*ty);
+                results.push(format!("{path}{{{}}}", operands.join(", ")));
+            }
+
+            Instruction::TupleLower { tuple, .. } => {
+                let op = &operands[0];
+                for i in 0..tuple.types.len() {
+                    results.push(format!("std::get<{i}>({op})"));
+                }
+            }
+            Instruction::TupleLift { ty, .. } => {
+                let ty = self.gen.type_name(&Type::Id(*ty));
+                results.push(format!("{ty}({})", operands.join(", ")));
+            }
+
+            Instruction::HandleLower { handle, .. } => {
+                let op = &operands[0];
+                let (Handle::Own(resource) | Handle::Borrow(resource)) = handle;
+                let resource = dealias(resolve, *resource);
+                let path = self.gen.type_path(resource);
+                let exported = self.gen.is_exported_resource(resource);
+                results.push(match (handle, exported) {
+                    (Handle::Own(_), true) => format!("{path}::wasm_lower_own(std::move({op}))"),
+                    (Handle::Own(_), false) => format!("({op}).into_handle()"),
+                    (Handle::Borrow(_), true) => format!("(int32_t) (intptr_t) &({op})"),
+                    (Handle::Borrow(_), false) => {
+                        format!("static_cast<{path} const&>({op}).handle()")
+                    }
+                });
+            }
+            Instruction::HandleLift { handle, .. } => {
+                let op = &operands[0];
+                let (Handle::Own(resource) | Handle::Borrow(resource)) = handle;
+                let resource = dealias(resolve, *resource);
+                let path = self.gen.type_path(resource);
+                let exported = self.gen.is_exported_resource(resource);
+                results.push(match (handle, exported) {
+                    (Handle::Own(_), true) => format!("{path}::wasm_lift_own({op})"),
+                    (Handle::Own(_), false) => format!("{path}::from_handle({op})"),
+                    (Handle::Borrow(_), true) => format!("(*({path}*) (intptr_t) ({op}))"),
+                    (Handle::Borrow(_), false) => {
+                        // Borrowed handles are owned by the callee for the
+                        // duration of the call, so they're dropped once the
+                        // call finishes.
+                        let borrows = match self.borrows.iter().find(|(id, _)| *id == resource) {
+                            Some((_, name)) => name.clone(),
+                            None => {
+                                let name = self.locals.tmp("borrows");
+                                self.borrows.push((resource, name.clone()));
+                                name
+                            }
+                        };
+                        format!("{borrows}.emplace_back({path}::from_handle({op}))")
+                    }
+                });
+            }
+
+            Instruction::FlagsLower { flags, .. } => match flags_repr(flags) {
+                Int::U64 => {
+                    let op = &operands[0];
+                    results.push(format!("(int32_t) (uint64_t) ({op})"));
+                    results.push(format!("(int32_t) ((uint64_t) ({op}) >> 32)"));
+                }
+                _ => results.push(format!("(int32_t) ({})", operands[0])),
+            },
+            Instruction::FlagsLift { flags, ty, .. } => {
+                let path = self.gen.type_path(*ty);
+                match flags_repr(flags) {
+                    Int::U64 if flags.flags.is_empty() => results.push(format!("{path}{{}}")),
+                    Int::U64 => results.push(format!(
+                        "({path}) ((uint64_t) (uint32_t) ({}) | ((uint64_t) (uint32_t) ({}) << 32))",
+                        operands[0], operands[1]
+                    )),
+                    repr => results.push(format!("({path}) ({}) ({})", int_repr(repr), operands[0])),
+                }
+            }
+
+            Instruction::VariantPayloadName => {
+                let name = self.locals.tmp("payload");
+                results.push(name.clone());
+                self.payloads.push(name);
+            }
+
+            Instruction::VariantLower {
+                variant,
+                results: result_types,
+                ..
+            } => {
+                let blocks = self
+                    .blocks
+                    .drain(self.blocks.len() - variant.cases.len()..)
+                    .collect::<Vec<_>>();
+                let payloads = self
+                    .payloads
+                    .drain(self.payloads.len() - variant.cases.len()..)
+                    .collect::<Vec<_>>();
+
+                self.variant_results("variant", result_types, results);
+                let op = &operands[0];
+                uwriteln!(self.src, "switch (({op}).variants.index()) {{");
+                for (i, ((case, (block, block_results)), payload)) in
+                    variant.cases.iter().zip(blocks).zip(payloads).enumerate()
+                {
+                    uwriteln!(self.src, "case {i}: {{");
+                    if case.ty.is_some() {
+                        uwriteln!(
+                            self.src,
+                            "[[maybe_unused]] auto&& {payload} = std::get<{i}>(({op}).variants).value;"
+                        );
+                    }
+                    self.src.push_str(&block);
+                    self.assign_variant_results(results, &block_results);
+                    uwriteln!(self.src, "break;");
+                    uwriteln!(self.src, "}}");
+                }
+                uwriteln!(self.src, "}}");
+            }
+
+            Instruction::VariantLift { variant, ty, .. } => {
+                let blocks = self
+                    .blocks
+                    .drain(self.blocks.len() - variant.cases.len()..)
+                    .collect::<Vec<_>>();
+
+                let path = self.gen.type_path(*ty);
+                let ident = type_ident(resolve.types[*ty].name.as_deref().unwrap());
+                let result = self.locals.tmp("variant");
+                uwriteln!(self.src, "std::optional<{path}> {result};");
+                uwriteln!(self.src, "switch ({}) {{", operands[0]);
+                for (i, (case, (block, block_results))) in
+                    variant.cases.iter().zip(blocks).enumerate()
+                {
+                    let case_ident = case_ident(&ident, &case.name);
+                    uwriteln!(self.src, "case {i}: {{");
+                    self.src.push_str(&block);
+                    let value = block_results.first().map(|s| s.as_str()).unwrap_or("");
+                    uwriteln!(
+                        self.src,
+                        "{result}.emplace({path}{{{path}::{case_ident}{{{value}}}}});"
+                    );
+                    uwriteln!(self.src, "break;");
+                    uwriteln!(self.src, "}}");
+                }
+                uwriteln!(self.src, "}}");
+                results.push(format!("std::move(*{result})"));
+            }
+
+            Instruction::EnumLower { .. } => {
+                results.push(format!("(int32_t) ({})", operands[0]));
+            }
+            Instruction::EnumLift { ty, .. } => {
+                let path = self.gen.type_path(*ty);
+                results.push(format!("({path}) ({})", operands[0]));
+            }
+
+            Instruction::OptionLower {
+                results: result_types,
+                ..
+            } => {
+                let (mut some, some_results) = self.blocks.pop().unwrap();
+                let (mut none, none_results) = self.blocks.pop().unwrap();
+                let some_payload = self.payloads.pop().unwrap();
+                let _none_payload = self.payloads.pop().unwrap();
+
+                self.variant_results("option", result_types, results);
+                for (result, op) in results.iter().zip(&some_results) {
+                    uwriteln!(some, "{result} = {op};");
+                }
+                for (result, op) in results.iter().zip(&none_results) {
+                    uwriteln!(none, "{result} = {op};");
+                }
+
+                let op = &operands[0];
+                uwriteln!(self.src, "if (({op}).has_value()) {{");
+                uwriteln!(
+                    self.src,
+                    "[[maybe_unused]] auto&& {some_payload} = *({op});"
+                );
+                self.src.push_str(&some);
+                uwriteln!(self.src, "}} else {{");
+                self.src.push_str(&none);
+                uwriteln!(self.src, "}}");
+            }
+
+            Instruction::OptionLift { ty, .. } => {
+                let (some, some_results) = self.blocks.pop().unwrap();
+                let (none, none_results) = self.blocks.pop().unwrap();
+                assert!(none_results.is_empty());
+                assert!(some_results.len() == 1);
+
+                let ty = self.gen.type_name(&Type::Id(*ty));
+                let result = self.locals.tmp("option");
+                uwriteln!(self.src, "{ty} {result};");
+                uwriteln!(self.src, "if ({}) {{", operands[0]);
+                self.src.push_str(&some);
+                uwriteln!(self.src, "{result}.emplace({});", some_results[0]);
+                if !none.trim().is_empty() {
+                    uwriteln!(self.src, "}} else {{");
+                    self.src.push_str(&none);
+                }
+                uwriteln!(self.src, "}}");
+                results.push(format!("std::move({result})"));
+            }
+
+            Instruction::ResultLower {
+                result,
+                results: result_types,
+                ..
+            } => {
+                let (mut err, err_results) = self.blocks.pop().unwrap();
+                let (mut ok, ok_results) = self.blocks.pop().unwrap();
+                let err_payload = self.payloads.pop().unwrap();
+                let ok_payload = self.payloads.pop().unwrap();
+
+                self.variant_results("result", result_types, results);
+                for (result, op) in results.iter().zip(&ok_results) {
+                    uwriteln!(ok, "{result} = {op};");
+                }
+                for (result, op) in results.iter().zip(&err_results) {
+                    uwriteln!(err, "{result} = {op};");
+                }
+
+                let op = &operands[0];
+                uwriteln!(self.src, "if (({op}).has_value()) {{");
+                if result.ok.is_some() {
+                    uwriteln!(
+                        self.src,
+                        "[[maybe_unused]] auto&& {ok_payload} = ({op}).value();"
+                    );
+                }
+                self.src.push_str(&ok);
+                uwriteln!(self.src, "}} else {{");
+                if result.err.is_some() {
+                    uwriteln!(
+                        self.src,
+                        "[[maybe_unused]] auto&& {err_payload} = ({op}).error();"
+                    );
+                }
+                self.src.push_str(&err);
+                uwriteln!(self.src, "}}");
+            }
+
+            Instruction::ResultLift { result, ty, .. } => {
+                let (err, err_results) = self.blocks.pop().unwrap();
+                let (ok, ok_results) = self.blocks.pop().unwrap();
+
+                let ty = self.gen.type_name(&Type::Id(*ty));
+                let err_ty = self.gen.optional_type_name(result.err.as_ref());
+                let ok_value = ok_results
+                    .first()
+                    .cloned()
+                    .unwrap_or_else(|| "std::monostate{}".to_string());
+                let err_value = err_results
+                    .first()
+                    .cloned()
+                    .unwrap_or_else(|| "std::monostate{}".to_string());
+
+                let result = self.locals.tmp("result");
+                uwriteln!(self.src, "std::optional<{ty}> {result};");
+                uwriteln!(self.src, "switch ({}) {{", operands[0]);
+                uwriteln!(self.src, "case 0: {{");
+                self.src.push_str(&ok);
+                uwriteln!(self.src, "{result}.emplace({ok_value});");
+                uwriteln!(self.src, "break;");
+                uwriteln!(self.src, "}}");
+                uwriteln!(self.src, "case 1: {{");
+                self.src.push_str(&err);
+                uwriteln!(
+                    self.src,
+                    "{result}.emplace(::wit::unexpected<{err_ty}>({err_value}));"
+                );
+                uwriteln!(self.src, "break;");
+                uwriteln!(self.src, "}}");
+                uwriteln!(self.src, "}}");
+                results.push(format!("std::move(*{result})"));
+            }
+
+            Instruction::ListCanonLower { element, realloc } => {
+                let size = self.gen.gen.sizes.size(element);
+                self.lower_list(size, &operands[0], *realloc, results);
+            }
+            Instruction::StringLower { realloc } => {
+                self.lower_list(1, &operands[0], *realloc, results);
+            }
+            Instruction::ListLower { element, realloc } => {
+                let (body, body_results) = self.blocks.pop().unwrap();
+                assert!(body_results.is_empty());
+                let size = self.gen.gen.sizes.size(element);
+                let vec = self.locals.tmp("vec");
+                let ptr = self.locals.tmp("ptr");
+                let i = self.locals.tmp("i");
+
+                uwriteln!(self.src, "auto&& {vec} = {};", operands[0]);
+                uwriteln!(
+                    self.src,
+                    "int32_t {ptr} = (int32_t) (intptr_t) malloc({vec}.size() * {size});"
+                );
+                // Lowered arguments of imports only need to live for the
+                // duration of the call.
+                if realloc.is_none() {
+                    let cleanup = match &self.cleanup {
+                        Some(cleanup) => cleanup.clone(),
+                        None => {
+                            let cleanup = self.locals.tmp("cleanup");
+                            self.cleanup = Some(cleanup.clone());
+                            cleanup
+                        }
+                    };
+                    uwriteln!(self.src, "{cleanup}.push_back((void*) (intptr_t) {ptr});");
+                }
+                uwriteln!(
+                    self.src,
+                    "for (size_t {i} = 0; {i} < {vec}.size(); ++{i}) {{"
+                );
+                uwriteln!(
+                    self.src,
+                    "[[maybe_unused]] int32_t base = {ptr} + (int32_t) {i} * {size};"
+                );
+                uwriteln!(self.src, "[[maybe_unused]] auto&& e = {vec}[{i}];");
+                self.src.push_str(&body);
+                uwriteln!(self.src, "}}");
+
+                results.push(ptr);
+                results.push(format!("(int32_t) {vec}.size()"));
+            }
+
+            Instruction::ListCanonLift { element, .. } => {
+                let ty = self.gen.type_name(element);
+                let ptr = self.locals.tmp("ptr");
+                let len = self.locals.tmp("len");
+                uwriteln!(self.src, "int32_t {ptr} = {};", operands[0]);
+                uwriteln!(self.src, "int32_t {len} = {};", operands[1]);
+                let list = self.lifted(
+                    "list",
+                    &format!("std::vector<{ty}>"),
+                    &format!("std::vector<{ty}>(({ty}*) (intptr_t) {ptr}, ({ty}*) (intptr_t) {ptr} + {len})"),
+                );
+                uwriteln!(self.src, "if ({len} > 0) {{");
+                uwriteln!(self.src, "free((void*) (intptr_t) {ptr});");
+                uwriteln!(self.src, "}}");
+                results.push(list);
+            }
+            Instruction::StringLift => {
+                let ptr = self.locals.tmp("ptr");
+                let len = self.locals.tmp("len");
+                uwriteln!(self.src, "int32_t {ptr} = {};", operands[0]);
+                uwriteln!(self.src, "int32_t {len} = {};", operands[1]);
+                let string = self.lifted(
+                    "string",
+                    "std::string",
+                    &format!("std::string((char const*) (intptr_t) {ptr}, {len})"),
+                );
+                uwriteln!(self.src, "if ({len} > 0) {{");
+                uwriteln!(self.src, "free((void*) (intptr_t) {ptr});");
+                uwriteln!(self.src, "}}");
+                results.push(string);
+            }
+            Instruction::ListLift { element, .. } => {
+                let (body, body_results) = self.blocks.pop().unwrap();
+                assert_eq!(body_results.len(), 1);
+                let ty = self.gen.type_name(element);
+                let size = self.gen.gen.sizes.size(element);
+                let ptr = self.locals.tmp("ptr");
+                let len = self.locals.tmp("len");
+                let list = self.locals.tmp("list");
+                let i = self.locals.tmp("i");
+
+                uwriteln!(self.src, "int32_t {ptr} = {};", operands[0]);
+                uwriteln!(self.src, "int32_t {len} = {};", operands[1]);
+                uwriteln!(self.src, "std::vector<{ty}> {list};");
+                uwriteln!(self.src, "{list}.reserve({len});");
+                uwriteln!(self.src, "for (int32_t {i} = 0; {i} < {len}; ++{i}) {{");
+                uwriteln!(
+                    self.src,
+                    "[[maybe_unused]] int32_t base = {ptr} + {i} * {size};"
+                );
+                self.src.push_str(&body);
+                uwriteln!(self.src, "{list}.push_back({});", body_results[0]);
+                uwriteln!(self.src, "}}");
+                uwriteln!(self.src, "if ({len} > 0) {{");
+                uwriteln!(self.src, "free((void*) (intptr_t) {ptr});");
+                uwriteln!(self.src, "}}");
+                results.push(format!("std::move({list})"));
+            }
+
+            Instruction::IterElem { .. } => results.push("e".to_string()),
+            Instruction::IterBasePointer => results.push("base".to_string()),
+
+            Instruction::CallWasm { sig, .. } => {
+                match sig.results.len() {
+                    0 => {}
+                    1 => {
+                        let ret = self.locals.tmp("ret");
+                        uwrite!(self.src, "{} {ret} = ", wasm_type(sig.results[0]));
+                        results.push(ret);
+                    }
+                    _ => unimplemented!("multi-value return not supported"),
+                }
+                uwriteln!(self.src, "{}({});", self.wasm_import, operands.join(", "));
+
+                if let Some(cleanup) = &self.cleanup {
+                    uwriteln!(self.src, "for (void* allocation : {cleanup}) {{");
+                    uwriteln!(self.src, "free(allocation);");
+                    uwriteln!(self.src, "}}");
+                }
+            }
+
+            Instruction::CallInterface { func } => {
+                let name = self.gen.func_ident(func);
+                let call = match &func.kind {
+                    FunctionKind::Freestanding => {
+                        let ns = self.gen.namespace();
+                        format!("::{ns}::{name}({})", operands.join(", "))
+                    }
+                    FunctionKind::Method(_) => {
+                        format!("({}).{name}({})", operands[0], operands[1..].join(", "))
+                    }
+                    FunctionKind::Static(resource) => {
+                        let path = self.gen.type_path(*resource);
+                        format!("{path}::{name}({})", operands.join(", "))
+                    }
+                    FunctionKind::Constructor(resource) => {
+                        let path = self.gen.type_path(*resource);
+                        format!("{path}::create({})", operands.join(", "))
+                    }
+                };
+                match func.results.len() {
+                    0 => uwriteln!(self.src, "{call};"),
+                    1 => {
+                        let ret = self.locals.tmp("ret");
+                        uwriteln!(self.src, "[[maybe_unused]] auto {ret} = {call};");
+                        results.push(ret);
+                    }
+                    n => {
+                        let ret = self.locals.tmp("ret");
+                        uwriteln!(self.src, "auto {ret} = {call};");
+                        for i in 0..n {
+                            results.push(format!("std::get<{i}>({ret})"));
+                        }
+                    }
+                }
+            }
+
+            Instruction::Return { amt, func } => match amt {
+                0 => {}
+                1 if !self.gen.in_export => {
+                    if let FunctionKind::Constructor(_) = func.kind {
+                        uwriteln!(self.src, "*this = {};", operands[0]);
+                    } else {
+                        // Returning a local by name already moves out of it.
+                        let op = &operands[0];
+                        let op = match op.strip_prefix("std::move(") {
+                            Some(local) if is_ident(&local[..local.len() - 1]) => {
+                                &local[..local.len() - 1]
+                            }
+                            _ => op,
+                        };
+                        uwriteln!(self.src, "return {op};");
+                    }
+                }
+                1 => uwriteln!(self.src, "return {};", operands[0]),
+                _ => {
+                    let ty = self.gen.results_type(func);
+                    uwriteln!(self.src, "return {ty}({});", operands.join(", "));
+                }
+            },
+
+            Instruction::I32Load { offset } => self.load("int32_t", *offset, operands, results),
+            Instruction::I64Load { offset } => self.load("int64_t", *offset, operands, results),
+            Instruction::F32Load { offset } => self.load("float", *offset, operands, results),
+            Instruction::F64Load { offset } => self.load("double", *offset, operands, results),
+            Instruction::I32Store { offset } => self.store("int32_t", *offset, operands),
+            Instruction::I64Store { offset } => self.store("int64_t", *offset, operands),
+            Instruction::F32Store { offset } => self.store("float", *offset, operands),
+            Instruction::F64Store { offset } => self.store("double", *offset, operands),
+            Instruction::I32Store8 { offset } => self.store("int8_t", *offset, operands),
+            Instruction::I32Store16 { offset } => self.store("int16_t", *offset, operands),
+
+            Instruction::I32Load8U { offset } => {
+                self.load_ext("uint8_t", *offset, operands, results)
+            }
+            Instruction::I32Load8S { offset } => {
+                self.load_ext("int8_t", *offset, operands, results)
+            }
+            Instruction::I32Load16U { offset } => {
+                self.load_ext("uint16_t", *offset, operands, results)
+            }
+            Instruction::I32Load16S { offset } => {
+                self.load_ext("int16_t", *offset, operands, results)
+            }
+
+            Instruction::GuestDeallocate { .. } => {
+                uwriteln!(self.src, "free((void*) (intptr_t) ({}));", operands[0]);
+            }
+            Instruction::GuestDeallocateString => {
+                uwriteln!(self.src, "if (({}) > 0) {{", operands[1]);
+                uwriteln!(self.src, "free((void*) (intptr_t) ({}));", operands[0]);
+                uwriteln!(self.src, "}}");
+            }
+            Instruction::GuestDeallocateVariant { blocks } => {
+                let blocks = self
+                    .blocks
+                    .drain(self.blocks.len() - blocks..)
+                    .collect::<Vec<_>>();
+
+                uwriteln!(self.src, "switch ((int32_t) {}) {{", operands[0]);
+                for (i, (block, results)) in blocks.into_iter().enumerate() {
+                    assert!(results.is_empty());
+                    uwriteln!(self.src, "case {}: {{", i);
+                    self.src.push_str(&block);
+                    self.src.push_str("break;\n}\n");
+                }
+                self.src.push_str("}\n");
+            }
+            Instruction::GuestDeallocateList { element } => {
+                let (body, results) = self.blocks.pop().unwrap();
+                assert!(results.is_empty());
+                let ptr = self.locals.tmp("ptr");
+                let len = self.locals.tmp("len");
+                let i = self.locals.tmp("i");
+                uwriteln!(self.src, "int32_t {ptr} = {};", operands[0]);
+                uwriteln!(self.src, "int32_t {len} = {};", operands[1]);
+                uwriteln!(self.src, "for (int32_t {i} = 0; {i} < {len}; ++{i}) {{");
+                let size = self.gen.gen.sizes.size(element);
+                uwriteln!(
+                    self.src,
+                    "[[maybe_unused]] int32_t base = {ptr} + {i} * {size};"
+                );
+                self.src.push_str(&body);
+                uwriteln!(self.src, "}}");
+                uwriteln!(self.src, "if ({len} > 0) {{");
+                uwriteln!(self.src, "free((void*) (intptr_t) {ptr});");
+                uwriteln!(self.src, "}}");
+            }
+
+            Instruction::FutureLower { .. }
+            | Instruction::FutureLift { .. }
+            | Instruction::StreamLower { .. }
+            | Instruction::StreamLift { .. } => {
+                unreachable!("`future` and `stream` types are rejected by `supports_async_types`")
+            }
+
+            i => unimplemented!("{:?}", i),
+        }
+    }
+}
+
+fn docs(src: &mut Source, docs: &Docs) {
+    let docs = match &docs.contents {
+        Some(docs) => docs,
+        None => return,
+    };
+    for line in docs.trim().lines() {
+        src.push_str("// ");
+        src.push_str(line);
+        src.push_str("\n");
+    }
+}
+
+fn type_ident(name: &str) -> String {
+    name.to_upper_camel_case()
+}
+
+/// Returns the name of the struct of the `case` of the variant `variant`,
+/// which can't be the same as that of the variant itself.
+fn case_ident(variant: &str, case: &str) -> String {
+    let ident = case.to_upper_camel_case();
+    if ident == variant {
+        format!("{ident}_")
+    } else {
+        ident
+    }
+}
+
+fn flags_repr(flags: &Flags) -> Int {
+    match flags.repr() {
+        FlagsRepr::U8 => Int::U8,
+        FlagsRepr::U16 => Int::U16,
+        FlagsRepr::U32(0 | 1) => Int::U32,
+        FlagsRepr::U32(2) => Int::U64,
+        repr => panic!("unimplemented flags {:?}", repr),
+    }
+}
+
+fn dealias(resolve: &Resolve, mut id: TypeId) -> TypeId {
+    loop {
+        match &resolve.types[id].kind {
+            TypeDefKind::Type(Type::Id(that_id)) => id = *that_id,
+            _ => break id,
+        }
+    }
+}
+
+/// Follows type aliases to the type they refer to.
+fn resolve_type(resolve: &Resolve, ty: &Type) -> Type {
+    match ty {
+        Type::Id(id) => match &resolve.types[*id].kind {
+            TypeDefKind::Type(t) => resolve_type(resolve, t),
+            _ => *ty,
+        },
+        _ => *ty,
+    }
+}
+
+fn is_ident(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn wasm_type(ty: WasmType) -> &'static str {
+    match ty {
+        WasmType::I32 => "int32_t",
+        WasmType::I64 => "int64_t",
+        WasmType::F32 => "float",
+        WasmType::F64 => "double",
+    }
+}
+
+fn wasm_results(sig: &WasmSignature) -> &'static str {
+    match sig.results.len() {
+        0 => "void",
+        1 => wasm_type(sig.results[0]),
+        _ => unimplemented!("multi-value return not supported"),
+    }
+}
+
+fn wasm_params(sig: &WasmSignature) -> String {
+    sig.params
+        .iter()
+        .map(|ty| wasm_type(*ty))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
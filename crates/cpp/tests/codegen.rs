@@ -0,0 +1,80 @@
+use heck::*;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+macro_rules! codegen_test {
+    ($id:ident $name:tt $test:tt) => {
+        #[test]
+        fn $id() {
+            test_helpers::run_world_codegen_test(
+                "guest-cpp",
+                $test.as_ref(),
+                |resolve, world, files| {
+                    wit_bindgen_cpp::Opts::default()
+                        .build()
+                        .generate(resolve, world, files)
+                        .unwrap()
+                },
+                verify,
+            );
+        }
+    };
+}
+
+test_helpers::codegen_tests!();
+
+fn verify(dir: &Path, name: &str) {
+    let path = PathBuf::from(
+        env::var_os("WASI_SDK_PATH").expect("environment variable WASI_SDK_PATH should be set"),
+    );
+    let mut cmd = Command::new(path.join("bin/clang++"));
+    cmd.arg("--sysroot").arg(path.join("share/wasi-sysroot"));
+    cmd.arg(dir.join(format!("{}.cpp", name.to_snake_case())));
+    cmd.arg("-I").arg(dir);
+    cmd.arg("-std=c++17").arg("-fno-exceptions");
+    cmd.arg("-Wall")
+        .arg("-Wextra")
+        .arg("-Werror")
+        .arg("-Wno-unused-parameter");
+    cmd.arg("-c");
+    cmd.arg("-o").arg(dir.join("obj.o"));
+
+    test_helpers::run_command(&mut cmd);
+}
+
+#[test]
+fn rejects_futures_and_streams() {
+    let mut resolve = wit_bindgen_core::wit_parser::Resolve::default();
+    let pkg = resolve
+        .push(
+            wit_bindgen_core::wit_parser::UnresolvedPackage::parse(
+                "async.wit".as_ref(),
+                "
+                    package my:async;
+
+                    interface jobs {
+                        type done = future<u32>;
+
+                        spawn: func() -> done;
+                    }
+
+                    world component {
+                        export jobs;
+                    }
+                ",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+    let world = resolve.select_world(pkg, None).unwrap();
+    let err = wit_bindgen_cpp::Opts::default()
+        .build()
+        .generate(&resolve, world, &mut Default::default())
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "`future` types are not supported by this generator\n  \
+         --> in type `done` of interface `my:async/jobs`"
+    );
+}
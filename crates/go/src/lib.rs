@@ -1,20 +1,29 @@
 use std::collections::{HashMap, HashSet};
 use std::fmt::Write;
-use std::{collections::BTreeSet, mem};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    mem,
+};
 
 use anyhow::Result;
 use heck::{ToKebabCase, ToSnakeCase, ToUpperCamelCase};
 
 use wit_bindgen_c::{
-    flags_repr, get_nonempty_type, int_repr, is_arg_by_pointer, is_empty_type, owns_anything,
+    flags_repr, get_nonempty_type, int_repr, is_arg_by_pointer, is_empty_type, is_export_copy,
+    owner_namespace, split_resources,
 };
 use wit_bindgen_core::wit_parser::{InterfaceId, Resolve, TypeOwner, WorldId};
 use wit_bindgen_core::{
-    uwriteln,
-    wit_parser::{Field, Function, Handle, SizeAlign, Type, TypeDefKind, TypeId, WorldKey},
-    Files, InterfaceGenerator as _, Source, WorldGenerator,
+    uwrite, uwriteln,
+    wit_parser::{
+        Field, Function, FunctionKind, Handle, Results, SizeAlign, Type, TypeDefKind, TypeId,
+        WorldKey,
+    },
+    CoreSymbols, Files, InterfaceGenerator as _, Source, WitItem, WorldGenerator,
 };
 
+mod wasmimport;
+
 // a list of Go keywords
 const GOKEYWORDS: [&str; 25] = [
     "break",
@@ -54,12 +63,45 @@ fn avoid_keyword(s: &str) -> String {
 
 #[derive(Default, Debug, Clone)]
 #[cfg_attr(feature = "clap", derive(clap::Args))]
-pub struct Opts {}
+pub struct Opts {
+    /// Generate a self-contained package calling `//go:wasmimport` functions
+    /// and exporting `//export` functions directly, instead of going through
+    /// cgo and the C bindings.
+    ///
+    /// The component type isn't embedded in the package in this mode, so the
+    /// module built from it has to be passed to `wasm-tools component embed`.
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub wasmimport: bool,
+
+    /// Map functions returning a `result` to Go functions returning its `ok`
+    /// value, if any, along with an `error`, instead of a `Result`.
+    ///
+    /// The `err` value of an imported function is returned as a
+    /// `ResultError`. Exported functions can return a `ResultError` to pick
+    /// their `err` value, while other errors are only accepted when the `err`
+    /// type is a `string` or missing.
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub result_errors: bool,
+
+    /// The name of the generated Go package, defaulting to the world's name in
+    /// snake case.
+    ///
+    /// The package's import path is determined by the directory the bindings
+    /// are placed in, so this is how to avoid collisions with the names of
+    /// other packages.
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub package_name: Option<String>,
+
+    /// Generate the bindings of each interface in a file of its own, named
+    /// after the world and the interface, instead of in the world's file.
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub file_per_interface: bool,
+}
 
 impl Opts {
     pub fn build(&self) -> Box<dyn WorldGenerator> {
         Box::new(TinyGo {
-            _opts: self.clone(),
+            opts: self.clone(),
             ..TinyGo::default()
         })
     }
@@ -67,12 +109,14 @@ impl Opts {
 
 #[derive(Default)]
 pub struct TinyGo {
-    _opts: Opts,
+    opts: Opts,
     src: Source,
     world: String,
     needs_result_option: bool,
-    needs_import_unsafe: bool,
-    needs_fmt_import: bool,
+    needs_result_errors: bool,
+    imports: Imports,
+    return_pointer_area_size: usize,
+    return_pointer_area_align: usize,
     sizes: SizeAlign,
     interface_names: HashMap<InterfaceId, WorldKey>,
     types: HashMap<TypeId, (HashSet<String>, wit_bindgen_core::Source)>,
@@ -84,6 +128,66 @@ pub struct TinyGo {
     // one set of types is generated and all bindings for both imports and
     // exports use that set of types.
     interfaces_with_types_printed: HashSet<InterfaceId>,
+
+    // Resources defined by exported interfaces.
+    //
+    // Handles to these are represented in Go by the values implementing the
+    // resource, while handles to imported resources are plain integers.
+    exported_resources: HashSet<TypeId>,
+
+    // The file of each interface when generating a file per interface, by
+    // file name.
+    interface_files: BTreeMap<String, InterfaceFile>,
+
+    // A copy of the `Resolve` being generated where interfaces which are both
+    // imported and exported and define resources have been split in two, as
+    // the C bindings do, along with the copy of each such interface that its
+    // export is generated from.
+    split: Option<Resolve>,
+    export_copies: HashMap<InterfaceId, InterfaceId>,
+}
+
+/// The bindings of an interface, when generating a file per interface.
+#[derive(Default)]
+struct InterfaceFile {
+    types: Source,
+    src: Source,
+    imports: Imports,
+    /// The interface the file is generated for.
+    source: Option<WitItem>,
+}
+
+/// The packages imported by a Go file, besides `C`.
+#[derive(Default, Clone, Copy)]
+struct Imports {
+    unsafe_: bool,
+    errors: bool,
+    fmt: bool,
+    math: bool,
+    runtime: bool,
+}
+
+impl Imports {
+    fn merge(&mut self, other: Imports) {
+        self.unsafe_ |= other.unsafe_;
+        self.errors |= other.errors;
+        self.fmt |= other.fmt;
+        self.math |= other.math;
+        self.runtime |= other.runtime;
+    }
+
+    fn print(&self, src: &mut Source) {
+        let imports = [
+            (self.unsafe_, "unsafe"),
+            (self.errors, "errors"),
+            (self.fmt, "fmt"),
+            (self.math, "math"),
+            (self.runtime, "runtime"),
+        ];
+        for (_, name) in imports.iter().filter(|(needed, _)| *needed) {
+            uwriteln!(src, "import \"{name}\"\n");
+        }
+    }
 }
 
 impl TinyGo {
@@ -106,19 +210,109 @@ impl TinyGo {
     }
 
     fn finish_types(&mut self, resolve: &Resolve) {
-        for (id, _) in resolve.types.iter() {
-            if let Some((_, ty)) = self.types.get(&id) {
-                self.src.push_str(&ty);
+        for (id, ty) in resolve.types.iter() {
+            let Some((_, src)) = self.types.get(&id) else {
+                continue;
+            };
+            let key = match ty.owner {
+                TypeOwner::Interface(owner) => self.interface_names.get(&owner),
+                _ => None,
+            };
+            match self.interface_file_name(resolve, key) {
+                Some(file) => {
+                    let file = self.interface_files.entry(file).or_default();
+                    file.source = key.map(|key| WitItem::interface(resolve, key));
+                    file.types.push_str(src);
+                }
+                None => self.src.push_str(src),
             }
         }
     }
+
+    fn package_name(&self) -> String {
+        match &self.opts.package_name {
+            Some(name) => name.clone(),
+            None => self.world.to_snake_case(),
+        }
+    }
+
+    /// Returns the name of the file the bindings of the interface `key` are
+    /// generated in, if it's not the world's file.
+    fn interface_file_name(&self, resolve: &Resolve, key: Option<&WorldKey>) -> Option<String> {
+        if !self.opts.file_per_interface {
+            return None;
+        }
+        // Package names and versions aren't kebab-case on their own.
+        let name = resolve
+            .name_world_key(key?)
+            .replace(|c: char| !c.is_ascii_alphanumeric(), "-");
+        Some(format!(
+            "{}-{}.go",
+            self.world.to_kebab_case(),
+            name.to_kebab_case()
+        ))
+    }
+
+    /// Adds the bindings generated for the interface `key` to its file, where
+    /// `imports` are the imports needed before generating them.
+    fn finish_interface(
+        &mut self,
+        resolve: &Resolve,
+        key: Option<&WorldKey>,
+        header: &str,
+        src: &str,
+        imports: Imports,
+    ) {
+        match self.interface_file_name(resolve, key) {
+            Some(file) => {
+                let interface_imports = mem::replace(&mut self.imports, imports);
+                let file = self.interface_files.entry(file).or_default();
+                file.source = key.map(|key| WitItem::interface(resolve, key));
+                file.src.push_str(header);
+                file.src.push_str(src);
+                file.imports.merge(interface_imports);
+            }
+            None => {
+                self.src.push_str(header);
+                self.src.push_str(src);
+                self.imports.merge(imports);
+            }
+        }
+    }
+
+    /// Prints the package clause and the imports of a Go file.
+    fn print_file_header(&self, src: &mut Source, imports: Imports) {
+        wit_bindgen_core::generated_preamble(src, env!("CARGO_PKG_VERSION"));
+        let package = self.package_name();
+        uwriteln!(src, "package {package}\n");
+
+        if !self.opts.wasmimport {
+            // import C
+            uwriteln!(src, "// #include \"{}.h\"", self.world.to_snake_case());
+            uwriteln!(src, "import \"C\"\n");
+        }
+
+        imports.print(src);
+    }
 }
 
 impl WorldGenerator for TinyGo {
-    fn preprocess(&mut self, resolve: &Resolve, world: WorldId) {
+    fn preprocess(&mut self, resolve: &Resolve, world: WorldId) -> Result<()> {
         let name = &resolve.worlds[world].name;
         self.world = name.to_string();
-        self.sizes.fill(resolve);
+
+        if let Some((split, copies)) = split_resources(resolve, world) {
+            self.split = Some(split);
+            self.export_copies = copies;
+        }
+        self.sizes.fill(self.split.as_ref().unwrap_or(resolve));
+        Ok(())
+    }
+
+    fn core_symbols(&self, resolve: &Resolve, world: WorldId) -> CoreSymbols {
+        let mut symbols = CoreSymbols::new(resolve, world);
+        symbols.export_realloc(resolve, world);
+        symbols
     }
 
     fn import_interface(
@@ -129,9 +323,9 @@ impl WorldGenerator for TinyGo {
         _files: &mut Files,
     ) {
         let name_raw = &resolve.name_world_key(name);
-        self.src
-            .push_str(&format!("// Import functions from {name_raw}\n"));
+        let header = format!("// Import functions from {name_raw}\n");
         self.interface_names.insert(id, name.clone());
+        let imports = mem::take(&mut self.imports);
 
         let binding = Some(name);
         let mut gen = self.interface(resolve, &binding, true);
@@ -146,7 +340,7 @@ impl WorldGenerator for TinyGo {
         gen.finish();
 
         let src = mem::take(&mut gen.src);
-        self.src.push_str(&src);
+        self.finish_interface(resolve, Some(name), &header, &src, imports);
     }
 
     fn import_funcs(
@@ -177,12 +371,21 @@ impl WorldGenerator for TinyGo {
         id: InterfaceId,
         _files: &mut Files,
     ) -> Result<()> {
-        self.interface_names.insert(id, name.clone());
+        // If this interface is also imported and defines resources then the
+        // export is generated from the same copy of it that the C bindings
+        // generate their `exports_`-prefixed types from.
+        let split = self.split.take();
+        let (resolve, id, key) = match self.export_copies.get(&id) {
+            Some(copy) => (split.as_ref().unwrap(), *copy, WorldKey::Interface(*copy)),
+            None => (resolve, id, name.clone()),
+        };
+
+        self.interface_names.insert(id, key.clone());
         let name_raw = &resolve.name_world_key(name);
-        self.src
-            .push_str(&format!("// Export functions from {name_raw}\n"));
+        let header = format!("// Export functions from {name_raw}\n");
+        let imports = mem::take(&mut self.imports);
 
-        let binding = Some(name);
+        let binding = Some(&key);
         let mut gen = self.interface(resolve, &binding, false);
         gen.interface = Some(id);
         if gen.gen.interfaces_with_types_printed.insert(id) {
@@ -196,7 +399,8 @@ impl WorldGenerator for TinyGo {
         gen.finish();
 
         let src = mem::take(&mut gen.src);
-        self.src.push_str(&src);
+        self.finish_interface(resolve, Some(name), &header, &src, imports);
+        self.split = split;
         Ok(())
     }
 
@@ -230,7 +434,7 @@ impl WorldGenerator for TinyGo {
         types: &[(&str, TypeId)],
         _files: &mut Files,
     ) {
-        let mut gen = self.interface(resolve, &None, false);
+        let mut gen = self.interface(resolve, &None, true);
         for (name, id) in types {
             gen.define_type(name, *id);
         }
@@ -240,44 +444,52 @@ impl WorldGenerator for TinyGo {
     }
 
     fn finish(&mut self, resolve: &Resolve, id: WorldId, files: &mut Files) {
-        // make sure all types are defined on top of the file
+        // make sure all types are defined on top of their file, including
+        // those of the copies of any split interfaces
         let src = mem::take(&mut self.src);
-        self.finish_types(resolve);
+        let split = self.split.take();
+        self.finish_types(split.as_ref().unwrap_or(resolve));
         self.src.push_str(&src);
+        if self.needs_result_errors {
+            print_result_errors(&mut self.src);
+            self.imports.errors = true;
+            self.imports.fmt = true;
+        }
+        if self.opts.wasmimport {
+            self.finish_wasm();
+        }
 
         // prepend package and imports header
         let src = mem::take(&mut self.src);
-        wit_bindgen_core::generated_preamble(&mut self.src, env!("CARGO_PKG_VERSION"));
-        let snake = self.world.to_snake_case();
-        // add package
-        self.src.push_str("package ");
-        self.src.push_str(&snake);
-        self.src.push_str("\n\n");
-
-        // import C
-        self.src.push_str("// #include \"");
-        self.src.push_str(self.world.to_snake_case().as_str());
-        self.src.push_str(".h\"\n");
-        self.src.push_str("import \"C\"\n\n");
-
-        if self.needs_import_unsafe {
-            self.src.push_str("import \"unsafe\"\n\n");
-        }
-        if self.needs_fmt_import {
-            self.src.push_str("import \"fmt\"\n\n");
-        }
+        let mut header = Source::default();
+        self.print_file_header(&mut header, self.imports);
+        self.src.push_str(&header);
         self.src.push_str(&src);
 
         let world = &resolve.worlds[id];
+        let world_source = WitItem::world(resolve, id);
         files.push(
             &format!("{}.go", world.name.to_kebab_case()),
             self.src.as_bytes(),
         );
+        files.set_source(
+            &format!("{}.go", world.name.to_kebab_case()),
+            world_source.clone(),
+        );
+        for (name, file) in mem::take(&mut self.interface_files) {
+            let mut src = Source::default();
+            self.print_file_header(&mut src, file.imports);
+            src.push_str(&file.types);
+            src.push_str(&file.src);
+            files.push(&name, src.as_bytes());
+            files.set_source(&name, file.source.unwrap_or_else(|| world_source.clone()));
+        }
+        let package = self.package_name();
         if self.needs_result_option {
             let mut result_option_src = Source::default();
             uwriteln!(
                 result_option_src,
-                "package {snake}
+                "package {package}
 
                 // inspired from https://github.com/moznion/go-optional
 
@@ -390,6 +602,14 @@ impl WorldGenerator for TinyGo {
                 &format!("{}_types.go", world.name.to_kebab_case()),
                 result_option_src.as_bytes(),
             );
+            files.set_source(
+                &format!("{}_types.go", world.name.to_kebab_case()),
+                world_source,
+            );
+        }
+
+        if self.opts.wasmimport {
+            return;
         }
 
         let mut opts = wit_bindgen_c::Opts::default();
@@ -408,12 +628,18 @@ struct InterfaceGenerator<'a> {
     name: &'a Option<&'a WorldKey>,
     public_anonymous_types: BTreeSet<TypeId>,
     in_import: bool,
-    export_funcs: Vec<(String, String)>,
+    // Each exported function's method declaration in the Go interface for
+    // this interface's exports, if any, along with its cgo export.
+    export_funcs: Vec<(Option<String>, String)>,
 }
 
 impl InterfaceGenerator<'_> {
     fn get_func_name(&self, name: &str) -> String {
-        format!("{}{}", self.get_package_name(), name.to_upper_camel_case())
+        format!(
+            "{}{}",
+            self.get_package_name(),
+            name.replace('.', "_").to_upper_camel_case()
+        )
     }
 
     fn get_type_name(&self, ty_name: &str, convert: bool) -> String {
@@ -451,7 +677,7 @@ impl InterfaceGenerator<'_> {
             None => name.push_str(&self.gen.world.to_snake_case()),
         }
         name.push('_');
-        name.push_str(&func_name.to_snake_case());
+        name.push_str(&func_name.to_snake_case().replace('.', "_"));
         name
     }
 
@@ -485,6 +711,11 @@ impl InterfaceGenerator<'_> {
         match key {
             WorldKey::Name(k) => name.push_str(&k.to_upper_camel_case()),
             WorldKey::Interface(id) => {
+                // The types of an exported copy of an interface are kept
+                // apart from those of its import.
+                if is_export_copy(self.resolve, *id) {
+                    name.push_str("Exports");
+                }
                 let iface = &self.resolve.interfaces[*id];
                 let pkg = &self.resolve.packages[iface.package.unwrap()];
                 name.push_str(&pkg.name.namespace.to_upper_camel_case());
@@ -547,6 +778,9 @@ impl InterfaceGenerator<'_> {
                             self.get_optional_ty(r.err.as_ref())
                         )
                     }
+                    wit_bindgen_core::wit_parser::TypeDefKind::Handle(
+                        Handle::Own(resource) | Handle::Borrow(resource),
+                    ) => self.get_ty(&Type::Id(*resource)),
                     _ => {
                         if let Some(name) = &ty.name {
                             if let TypeOwner::Interface(owner) = ty.owner {
@@ -590,22 +824,10 @@ impl InterfaceGenerator<'_> {
                 let ty = &self.resolve.types[*id];
                 match &ty.name {
                     Some(name) => match ty.owner {
-                        TypeOwner::Interface(owner) => {
-                            let key = &self.gen.interface_names[&owner];
-                            let mut ns = match &key {
-                                WorldKey::Name(name) => name.to_snake_case(),
-                                WorldKey::Interface(id) => {
-                                    let mut ns = String::new();
-                                    let iface = &self.resolve.interfaces[*id];
-                                    let pkg = &self.resolve.packages[iface.package.unwrap()];
-                                    ns.push_str(&pkg.name.namespace.to_snake_case());
-                                    ns.push('_');
-                                    ns.push_str(&pkg.name.name.to_snake_case());
-                                    ns.push('_');
-                                    ns.push_str(&iface.name.as_ref().unwrap().to_snake_case());
-                                    ns
-                                }
-                            };
+                        TypeOwner::Interface(_) => {
+                            let mut ns =
+                                owner_namespace(self.resolve, *id, &self.gen.interface_names)
+                                    .unwrap();
                             ns.push('_');
                             ns.push_str(name.to_snake_case().as_str());
                             ns.push_str("_t");
@@ -624,9 +846,13 @@ impl InterfaceGenerator<'_> {
                     },
                     None => match &ty.kind {
                         TypeDefKind::Type(t) => self.get_c_ty_without_package(t),
+                        // Handles of split interfaces are namespaced under
+                        // their interface, like named types.
                         _ => format!(
                             "{namespace}_{name}_t",
-                            namespace = self.gen.world.to_snake_case(),
+                            namespace =
+                                owner_namespace(self.resolve, *id, &self.gen.interface_names)
+                                    .unwrap_or_else(|| self.gen.world.to_snake_case()),
                             name = self.get_c_ty_name(&Type::Id(*id)),
                         ),
                     },
@@ -636,6 +862,15 @@ impl InterfaceGenerator<'_> {
     }
 
     fn get_c_ty(&self, ty: &Type) -> String {
+        // Borrows of exported resources are passed as pointers to the
+        // resource's representation rather than as handles.
+        if let Type::Id(id) = ty {
+            if let TypeDefKind::Handle(Handle::Borrow(resource)) = &self.resolve.types[*id].kind {
+                if self.is_exported_resource(*resource) {
+                    return format!("*C.{}_t", self.get_resource_c_name(*resource));
+                }
+            }
+        }
         let res = self.get_c_ty_without_package(ty);
         if res == "bool" {
             return res;
@@ -643,6 +878,51 @@ impl InterfaceGenerator<'_> {
         format!("C.{res}")
     }
 
+    fn is_exported_resource(&self, resource: TypeId) -> bool {
+        self.gen
+            .exported_resources
+            .contains(&dealias(self.resolve, resource))
+    }
+
+    /// Returns the prefix the C bindings use for functions working with
+    /// `resource`, such as `{prefix}_drop_own`.
+    fn get_resource_c_name(&self, resource: TypeId) -> String {
+        let resource = dealias(self.resolve, resource);
+        let c_ty = self.get_c_ty_without_package(&Type::Id(resource));
+        c_ty.strip_suffix("_t").unwrap().to_string()
+    }
+
+    /// Returns the name of the Go function generated for `func`, without any
+    /// package prefix.
+    ///
+    /// Methods use their plain name while constructors and static functions
+    /// are prefixed with the name of their resource.
+    fn get_func_item_name(&self, func: &Function) -> String {
+        let resource_name = |id: TypeId| {
+            self.resolve.types[id]
+                .name
+                .as_ref()
+                .unwrap()
+                .to_upper_camel_case()
+        };
+        match func.kind {
+            FunctionKind::Freestanding => func.name.to_upper_camel_case(),
+            FunctionKind::Method(_) => func.item_name().to_upper_camel_case(),
+            FunctionKind::Static(id) => format!(
+                "{}{}",
+                resource_name(id),
+                func.item_name().to_upper_camel_case()
+            ),
+            FunctionKind::Constructor(id) => format!("New{}", resource_name(id)),
+        }
+    }
+
+    /// Returns whether a lowered or lifted value of type `ty` should be freed
+    /// once it's no longer needed, which is whenever it owns any memory.
+    fn needs_free(&self, ty: &Type) -> bool {
+        owns_memory(self.resolve, ty)
+    }
+
     fn get_ty_name(&self, ty: &Type) -> String {
         match ty {
             Type::Bool => "Bool".into(),
@@ -774,7 +1054,9 @@ impl InterfaceGenerator<'_> {
 
     fn get_func_params(&mut self, _resolve: &Resolve, func: &Function) -> String {
         let mut params = String::new();
-        for (i, (name, param)) in func.params.iter().enumerate() {
+        // The `self` parameter of a method is its receiver in Go.
+        let skip = usize::from(matches!(func.kind, FunctionKind::Method(_)));
+        for (i, (name, param)) in func.params.iter().skip(skip).enumerate() {
             if i > 0 {
                 params.push_str(", ");
             }
@@ -790,6 +1072,13 @@ impl InterfaceGenerator<'_> {
     fn get_func_results(&mut self, _resolve: &Resolve, func: &Function) -> String {
         let mut results = String::new();
         results.push(' ');
+        if let Some((ok, _)) = self.error_result(func) {
+            match ok {
+                Some(ok) => uwrite!(results, "({}, error) ", self.get_ty(&ok)),
+                None => results.push_str("error "),
+            }
+            return results;
+        }
         match func.results.len() {
             0 => {}
             1 => {
@@ -810,6 +1099,52 @@ impl InterfaceGenerator<'_> {
         results
     }
 
+    /// Returns the `ok` and `err` types of the `result` returned by `func`, if
+    /// it's returned along with an `error` in Go.
+    fn error_result(&mut self, func: &Function) -> Option<(Option<Type>, Option<Type>)> {
+        if !self.gen.opts.result_errors {
+            return None;
+        }
+        let id = match &func.results {
+            Results::Anon(Type::Id(id)) => dealias(self.resolve, *id),
+            _ => return None,
+        };
+        match &self.resolve.types[id].kind {
+            TypeDefKind::Result(r) => {
+                self.gen.needs_result_errors = true;
+                Some((r.ok, r.err))
+            }
+            _ => None,
+        }
+    }
+
+    /// Converts the `Result` returned by an imported function to what its Go
+    /// function returns.
+    fn import_result(&mut self, func: &Function, ret: &str) -> String {
+        match self.error_result(func) {
+            Some((None, _)) => format!("resultToError({ret})"),
+            Some(_) => format!("resultToValue({ret})"),
+            None => ret.to_string(),
+        }
+    }
+
+    /// Converts what the Go implementation of an exported function returns to
+    /// the `Result` returned by the function.
+    fn export_result(&mut self, func: &Function, invoke: &str) -> String {
+        match self.error_result(func) {
+            Some((None, err)) => {
+                let err = self.get_optional_ty(err.as_ref());
+                format!("resultFromError[{err}]({invoke})")
+            }
+            Some((ok, err)) => {
+                let ok = self.get_optional_ty(ok.as_ref());
+                let err = self.get_optional_ty(err.as_ref());
+                format!("resultFromValue[{ok}, {err}]({invoke})")
+            }
+            None => invoke.to_string(),
+        }
+    }
+
     fn print_c_result(&mut self, src: &mut Source, name: &str, param: &Type, in_import: bool) {
         self.print_c_param(src, name, param, in_import);
     }
@@ -947,17 +1282,126 @@ impl InterfaceGenerator<'_> {
         src.to_string()
     }
 
+    /// Returns a deferred statement freeing the C value pointed to by `arg`.
+    ///
+    /// The C bindings' `_free` functions also drop any handles a value
+    /// contains, but ownership of those has moved to either the callee or the
+    /// lifted Go value, so values containing handles only have their strings
+    /// and lists freed.
     fn get_free_c_arg(&mut self, ty: &Type, arg: &str) -> String {
-        let mut ty_name = self.get_c_ty(ty);
-        let it: Vec<&str> = ty_name.split('_').collect();
-        ty_name = it[..it.len() - 1].join("_");
-        format!("defer {ty_name}_free({arg})\n")
+        if contains_handle(self.resolve, ty) {
+            let value = match arg.strip_prefix('&') {
+                Some(value) => value.to_string(),
+                None => format!("(*{arg})"),
+            };
+            let free = self.free_c_value(ty, &value, 0);
+            return format!("defer func() {{\n{free}}}()\n");
+        }
+        let free = self.get_c_free_func(ty);
+        format!("defer {free}({arg})\n")
+    }
+
+    fn get_c_free_func(&self, ty: &Type) -> String {
+        let ty_name = self.get_c_ty(ty);
+        let ty_name = ty_name.strip_suffix("_t").unwrap();
+        format!("{ty_name}_free")
+    }
+
+    /// Returns statements freeing the memory owned by the C value `value`,
+    /// leaving any handles it contains alone.
+    ///
+    /// `depth` keeps the loop variables of nested lists apart.
+    fn free_c_value(&mut self, ty: &Type, value: &str, depth: usize) -> String {
+        if !self.needs_free(ty) {
+            return String::new();
+        }
+        if !contains_handle(self.resolve, ty) {
+            let free = self.get_c_free_func(ty);
+            return format!("{free}(&{value})\n");
+        }
+        let id = match ty {
+            Type::Id(id) => *id,
+            _ => unreachable!(),
+        };
+        self.gen.imports.unsafe_ = true;
+        let mut src = String::new();
+        match &self.resolve.types[id].kind {
+            TypeDefKind::Type(t) => src.push_str(&self.free_c_value(t, value, depth)),
+            TypeDefKind::Record(r) => {
+                for field in r.fields.iter() {
+                    let field_name = field.name.to_snake_case();
+                    let value = format!("{value}.{field_name}");
+                    src.push_str(&self.free_c_value(&field.ty, &value, depth));
+                }
+            }
+            TypeDefKind::Tuple(t) => {
+                for (i, ty) in t.types.iter().enumerate() {
+                    src.push_str(&self.free_c_value(ty, &format!("{value}.f{i}"), depth));
+                }
+            }
+            TypeDefKind::Option(t) => {
+                let free = self.free_c_value(t, &format!("{value}.val"), depth);
+                uwrite!(src, "if {value}.is_some {{\n{free}}}\n");
+            }
+            TypeDefKind::Result(r) => {
+                let mut case = |ty: Option<&Type>| match ty {
+                    Some(ty) if self.needs_free(ty) => {
+                        let c_ty = self.get_c_ty(ty);
+                        let value = format!("(*(*{c_ty})(unsafe.Pointer(&{value}.val)))");
+                        self.free_c_value(ty, &value, depth)
+                    }
+                    _ => String::new(),
+                };
+                let ok = case(r.ok.as_ref());
+                let err = case(r.err.as_ref());
+                uwrite!(src, "if {value}.is_err {{\n{err}}} else {{\n{ok}}}\n");
+            }
+            TypeDefKind::Variant(v) => {
+                src.push_str(&format!("switch {value}.tag {{\n"));
+                for (i, case) in v.cases.iter().enumerate() {
+                    let Some(ty) = case.ty.as_ref().filter(|ty| self.needs_free(ty)) else {
+                        continue;
+                    };
+                    let c_ty = self.get_c_ty(ty);
+                    let value = format!("(*(*{c_ty})(unsafe.Pointer(&{value}.val)))");
+                    let free = self.free_c_value(ty, &value, depth);
+                    uwrite!(src, "case {i}:\n{free}");
+                }
+                src.push_str("}\n");
+            }
+            TypeDefKind::List(t) => {
+                if self.needs_free(t) {
+                    let i = format!("free_i{depth}");
+                    let c_ty = self.get_c_ty(t);
+                    let elem = format!(
+                        "(*(*{c_ty})(unsafe.Pointer(uintptr(unsafe.Pointer({value}.ptr)) + uintptr({i})*unsafe.Sizeof(*{value}.ptr))))"
+                    );
+                    let free = self.free_c_value(t, &elem, depth + 1);
+                    uwrite!(
+                        src,
+                        "for {i} := 0; {i} < int({value}.len); {i}++ {{\n{free}}}\n"
+                    );
+                }
+                uwrite!(
+                    src,
+                    "if {value}.len > 0 {{\nC.free(unsafe.Pointer({value}.ptr))\n}}\n"
+                );
+            }
+            TypeDefKind::Handle(_) => {}
+            TypeDefKind::Flags(_)
+            | TypeDefKind::Enum(_)
+            | TypeDefKind::Resource
+            | TypeDefKind::Future(_)
+            | TypeDefKind::Stream(_)
+            | TypeDefKind::Unknown => unreachable!(),
+        }
+        src
     }
 
     fn get_func_signature_no_interface(&mut self, resolve: &Resolve, func: &Function) -> String {
         format!(
             "{}({}){}",
-            func.name.to_upper_camel_case(),
+            self.get_func_item_name(func),
             self.get_func_params(resolve, func),
             self.get_func_results(resolve, func)
         )
@@ -965,8 +1409,13 @@ impl InterfaceGenerator<'_> {
 
     fn print_func_signature(&mut self, resolve: &Resolve, func: &Function) {
         self.src.push_str("func ");
-        let func_name = self.get_package_name();
-        self.src.push_str(&func_name);
+        if let FunctionKind::Method(resource) = func.kind {
+            let ty = self.get_ty(&Type::Id(resource));
+            self.src.push_str(&format!("(self {ty}) "));
+        } else {
+            let func_name = self.get_package_name();
+            self.src.push_str(&func_name);
+        }
         let func_sig = self.get_func_signature_no_interface(resolve, func);
         self.src.push_str(&func_sig);
         self.src.push_str("{\n");
@@ -1058,7 +1507,7 @@ impl InterfaceGenerator<'_> {
             TypeDefKind::List(_l) => {}
             TypeDefKind::Future(_) => todo!("print_anonymous_type for future"),
             TypeDefKind::Stream(_) => todo!("print_anonymous_type for stream"),
-            TypeDefKind::Handle(_) => todo!("print_anonymous_type for handle"),
+            TypeDefKind::Handle(_) => {}
             TypeDefKind::Unknown => unreachable!(),
         }
     }
@@ -1074,7 +1523,7 @@ impl InterfaceGenerator<'_> {
     }
 
     fn print_accessor_methods(&mut self, name: &str, case_name: &str, ty: &Type) {
-        self.gen.needs_fmt_import = true;
+        self.gen.imports.fmt = true;
         let ty = self.get_ty(ty);
         uwriteln!(
             self.src,
@@ -1131,6 +1580,9 @@ impl InterfaceGenerator<'_> {
     }
 
     fn import(&mut self, resolve: &Resolve, func: &Function) {
+        if self.gen.opts.wasmimport {
+            return self.wasm_import(func);
+        }
         let mut func_bindgen = FunctionBindgen::new(self, func);
         // lower params to c
         func.params.iter().for_each(|(name, ty)| {
@@ -1193,7 +1645,8 @@ impl InterfaceGenerator<'_> {
                     self.src.push_str(&format!("ret := {invoke}\n"));
                 }
                 self.src.push_str(lift_src);
-                self.src.push_str(&format!("return {ret}\n", ret = ret[0]));
+                let ret = self.import_result(func, &ret[0]);
+                self.src.push_str(&format!("return {ret}\n"));
             }
             _n => {
                 for (i, ty) in func.results.iter_types().enumerate() {
@@ -1217,6 +1670,9 @@ impl InterfaceGenerator<'_> {
     }
 
     fn export(&mut self, resolve: &Resolve, func: &Function) {
+        if self.gen.opts.wasmimport {
+            return self.wasm_export(func);
+        }
         let mut func_bindgen = FunctionBindgen::new(self, func);
         match func.results.len() {
             0 => {
@@ -1261,7 +1717,7 @@ impl InterfaceGenerator<'_> {
 
             // free all the parameters
             for (name, ty) in func.params.iter() {
-                if owns_anything(resolve, ty, &|_, _| todo!("support resources")) {
+                if self.needs_free(ty) {
                     let free = self.get_free_c_arg(ty, &avoid_keyword(&name.to_snake_case()));
                     src.push_str(&free);
                 }
@@ -1272,19 +1728,20 @@ impl InterfaceGenerator<'_> {
             src.push_str(&lift_src);
 
             // invoke
-            let invoke = format!(
-                "{}.{}({})",
-                &self.get_interface_var_name(),
-                &func.name.to_upper_camel_case(),
-                args.iter()
-                    .enumerate()
-                    .map(|(i, name)| format!(
-                        "{}{}",
-                        name,
-                        if i < func.params.len() - 1 { ", " } else { "" }
-                    ))
-                    .collect::<String>()
-            );
+            let invoke = match func.kind {
+                FunctionKind::Method(_) => format!(
+                    "{}.{}({})",
+                    args[0],
+                    self.get_func_item_name(func),
+                    args[1..].join(", ")
+                ),
+                _ => format!(
+                    "{}.{}({})",
+                    &self.get_interface_var_name(),
+                    self.get_func_item_name(func),
+                    args.join(", ")
+                ),
+            };
 
             // prepare ret
             match func.results.len() {
@@ -1296,6 +1753,7 @@ impl InterfaceGenerator<'_> {
                     if is_empty_type(self.resolve, return_ty) {
                         src.push_str(&format!("{invoke}\n"));
                     } else {
+                        let invoke = self.export_result(func, &invoke);
                         src.push_str(&format!("result := {invoke}\n"));
                     }
                     src.push_str(&lower_src);
@@ -1325,6 +1783,11 @@ impl InterfaceGenerator<'_> {
             src.push_str("\n}\n");
             src
         };
+        // Methods are declared by the interface of their resource instead.
+        let interface_method_decl = match func.kind {
+            FunctionKind::Method(_) => None,
+            _ => Some(interface_method_decl),
+        };
         self.export_funcs.push((interface_method_decl, export_func));
     }
 
@@ -1335,7 +1798,11 @@ impl InterfaceGenerator<'_> {
         }
         self.src.push_str(&src);
 
-        if !self.in_import && !self.export_funcs.is_empty() {
+        if self.in_import {
+            return;
+        }
+
+        if self.export_funcs.iter().any(|(decl, _)| decl.is_some()) {
             let interface_var_name = &self.get_interface_var_name();
             let interface_name = &self.get_package_name();
 
@@ -1350,14 +1817,15 @@ impl InterfaceGenerator<'_> {
             self.src
                 .push_str(format!("type {interface_name} interface {{\n").as_str());
             for (interface_func_declaration, _) in &self.export_funcs {
-                self.src
-                    .push_str(format!("{interface_func_declaration}\n").as_str());
+                if let Some(decl) = interface_func_declaration {
+                    self.src.push_str(format!("{decl}\n").as_str());
+                }
             }
             self.src.push_str("}\n");
+        }
 
-            for (_, export_func) in &self.export_funcs {
-                self.src.push_str(export_func);
-            }
+        for (_, export_func) in &self.export_funcs {
+            self.src.push_str(export_func);
         }
     }
 }
@@ -1386,9 +1854,115 @@ impl<'a> wit_bindgen_core::InterfaceGenerator<'a> for InterfaceGenerator<'a> {
         self.finish_ty(id, name, prev)
     }
 
-    fn type_resource(&mut self, id: TypeId, name: &str, docs: &wit_bindgen_core::wit_parser::Docs) {
-        _ = (id, name, docs);
-        todo!()
+    fn type_resource(
+        &mut self,
+        id: TypeId,
+        name: &str,
+        _docs: &wit_bindgen_core::wit_parser::Docs,
+    ) {
+        let prev = mem::take(&mut self.src);
+        let snake = name.to_snake_case();
+        let name = self.get_type_name(name, true);
+        let c_name = self.get_resource_c_name(id);
+
+        if self.in_import {
+            // Imported resources are represented by their handle, which is
+            // the same for both `own` and `borrow` handles.
+            self.src.push_str(&format!("type {name} int32\n\n"));
+
+            // A drop function is only available if the resource is ever
+            // passed as an `own` handle.
+            let owned = self.resolve.types.iter().any(|(_, ty)| {
+                matches!(ty.kind, TypeDefKind::Handle(Handle::Own(r)) if dealias(self.resolve, r) == id)
+            });
+            if self.gen.opts.wasmimport {
+                let module = self.wasm_module();
+                let wit_name = self.resolve.types[id].name.as_deref().unwrap();
+                uwriteln!(
+                    self.src,
+                    "//go:wasmimport {module} [resource-drop]{wit_name}
+                    func wasmimport_{c_name}_drop(handle int32)
+                    "
+                );
+                if owned {
+                    uwriteln!(
+                        self.src,
+                        "func (self {name}) Drop() {{
+                            wasmimport_{c_name}_drop(int32(self))
+                        }}
+                        "
+                    );
+                }
+            } else if owned {
+                let world = self.gen.world.to_snake_case();
+                uwriteln!(
+                    self.src,
+                    "func (self {name}) Drop() {{
+                        C.{c_name}_drop_own(C.{world}_own_{snake}_t{{__handle: C.int32_t(self)}})
+                    }}
+                    "
+                );
+            }
+        } else {
+            // Exported resources are implemented by Go values, which are kept
+            // in a table keyed by the representation given to the host.
+            self.gen.exported_resources.insert(id);
+
+            let methods = match self.interface {
+                Some(iface) => self.resolve.interfaces[iface]
+                    .functions
+                    .values()
+                    .filter(|func| func.kind == FunctionKind::Method(id))
+                    .collect::<Vec<_>>(),
+                None => Vec::new(),
+            };
+            self.src.push_str(&format!("type {name} interface {{\n"));
+            for func in methods {
+                let decl = self.get_func_signature_no_interface(self.resolve, func);
+                self.src.push_str(&format!("{decl}\n"));
+            }
+            self.src.push_str("}\n\n");
+
+            if self.gen.opts.wasmimport {
+                let module = self.wasm_module();
+                let wit_name = self.resolve.types[id].name.as_deref().unwrap();
+                uwriteln!(
+                    self.src,
+                    "var {c_name}_reps = make(map[int32]{name})
+                    var {c_name}_next_rep int32 = 1
+
+                    //go:wasmimport [export]{module} [resource-new]{wit_name}
+                    func wasmimport_{c_name}_new(rep int32) int32
+
+                    //go:wasmimport [export]{module} [resource-rep]{wit_name}
+                    func wasmimport_{c_name}_rep(handle int32) int32
+
+                    //go:wasmimport [export]{module} [resource-drop]{wit_name}
+                    func wasmimport_{c_name}_drop(handle int32)
+
+                    //export {module}#[dtor]{wit_name}
+                    func {c_name}_destructor(rep int32) {{
+                        delete({c_name}_reps, rep)
+                    }}
+                    "
+                );
+                return self.finish_ty(id, name, prev);
+            }
+
+            self.gen.imports.unsafe_ = true;
+            uwriteln!(
+                self.src,
+                "var {c_name}_reps = make(map[int32]{name})
+                var {c_name}_next_rep int32 = 1
+
+                //export {c_name}_destructor
+                func {c_name}_destructor(rep *C.{c_name}_t) {{
+                    delete({c_name}_reps, int32(uintptr(unsafe.Pointer(rep))))
+                }}
+                "
+            );
+        }
+        self.finish_ty(id, name, prev)
     }
 
     fn type_flags(
@@ -1470,7 +2044,7 @@ impl<'a> wit_bindgen_core::InterfaceGenerator<'a> for InterfaceGenerator<'a> {
         for case in variant.cases.iter() {
             let case_name = case.name.to_upper_camel_case();
             if let Some(ty) = get_nonempty_type(self.resolve, case.ty.as_ref()) {
-                self.gen.needs_fmt_import = true;
+                self.gen.imports.fmt = true;
                 self.print_accessor_methods(&name, &case_name, ty);
             } else {
                 self.print_constructor_method_without_value(&name, &case_name);
@@ -1564,6 +2138,26 @@ impl<'a> wit_bindgen_core::InterfaceGenerator<'a> for InterfaceGenerator<'a> {
         self.finish_ty(id, name, prev)
     }
 
+    fn type_future(
+        &mut self,
+        _id: wit_bindgen_core::wit_parser::TypeId,
+        _name: &str,
+        _ty: &Option<wit_bindgen_core::wit_parser::Type>,
+        _docs: &wit_bindgen_core::wit_parser::Docs,
+    ) {
+        unreachable!("`future` types are rejected by `supports_async_types`")
+    }
+
+    fn type_stream(
+        &mut self,
+        _id: wit_bindgen_core::wit_parser::TypeId,
+        _name: &str,
+        _stream: &wit_bindgen_core::wit_parser::Stream,
+        _docs: &wit_bindgen_core::wit_parser::Docs,
+    ) {
+        unreachable!("`stream` types are rejected by `supports_async_types`")
+    }
+
     fn type_builtin(
         &mut self,
         _id: wit_bindgen_core::wit_parser::TypeId,
@@ -1607,11 +2201,7 @@ impl<'a, 'b> FunctionBindgen<'a, 'b> {
         // If this variable is in inner node of the recursive call, no need to be freed.
         //    This is because the root node's call to free will recursively free the whole tree.
         // Otherwise, free this variable.
-        if !in_export
-            && owns_anything(self.interface.resolve, ty, &|_, _| {
-                todo!("support resources")
-            })
-        {
+        if !in_export && self.interface.needs_free(ty) {
             self.lower_src
                 .push_str(&self.interface.get_free_c_arg(ty, &format!("&{lower_name}")));
         }
@@ -1666,7 +2256,7 @@ impl<'a, 'b> FunctionBindgen<'a, 'b> {
         uwriteln!(self.lower_src, "if {param}.IsOk() {{");
         if let Some(ok_inner) = ok {
             if !is_empty_type(self.interface.resolve, &ok_inner) {
-                self.interface.gen.needs_import_unsafe = true;
+                self.interface.gen.imports.unsafe_ = true;
                 let c_target_name = self.interface.get_c_ty(&ok_inner);
                 uwriteln!(
                     self.lower_src,
@@ -1683,7 +2273,7 @@ impl<'a, 'b> FunctionBindgen<'a, 'b> {
         self.lower_src.push_str("} else {\n");
         if let Some(err_inner) = err {
             if !is_empty_type(self.interface.resolve, &err_inner) {
-                self.interface.gen.needs_import_unsafe = true;
+                self.interface.gen.imports.unsafe_ = true;
                 let c_target_name = self.interface.get_c_ty(&err_inner);
                 uwriteln!(
                     self.lower_src,
@@ -1713,7 +2303,7 @@ impl<'a, 'b> FunctionBindgen<'a, 'b> {
                 uwriteln!(self.lower_src, "{lower_name} := {param}",);
             }
             Type::String => {
-                self.interface.gen.needs_import_unsafe = true;
+                self.interface.gen.imports.unsafe_ = true;
                 uwriteln!(
                     self.lower_src,
                     "var {lower_name} {value}",
@@ -1796,7 +2386,7 @@ impl<'a, 'b> FunctionBindgen<'a, 'b> {
                         );
                     }
                     TypeDefKind::List(l) => {
-                        self.interface.gen.needs_import_unsafe = true;
+                        self.interface.gen.imports.unsafe_ = true;
                         let c_typedef_target = self.interface.get_c_ty(&Type::Id(*id));
 
                         uwriteln!(self.lower_src, "var {lower_name} {c_typedef_target}");
@@ -1812,7 +2402,7 @@ impl<'a, 'b> FunctionBindgen<'a, 'b> {
                         uwriteln!(self.lower_src, "{lower_name} = {lower_name}_val");
                     }
                     TypeDefKind::Variant(v) => {
-                        self.interface.gen.needs_import_unsafe = true;
+                        self.interface.gen.imports.unsafe_ = true;
 
                         let c_typedef_target = self.interface.get_c_ty(&Type::Id(*id));
                         let ty = self.interface.get_ty(&Type::Id(*id));
@@ -1859,10 +2449,47 @@ impl<'a, 'b> FunctionBindgen<'a, 'b> {
                             self.lower_src.push_str("}\n");
                         }
                     }
+                    TypeDefKind::Handle(Handle::Own(resource))
+                        if self.interface.is_exported_resource(*resource) =>
+                    {
+                        // Hand a new representation for the Go value to the
+                        // host, which creates a handle for it.
+                        self.interface.gen.imports.unsafe_ = true;
+                        let c_name = self.interface.get_resource_c_name(*resource);
+                        uwriteln!(
+                            self.lower_src,
+                            "{lower_name}_rep := {c_name}_next_rep
+                            {c_name}_next_rep++
+                            {c_name}_reps[{lower_name}_rep] = {param}
+                            {lower_name} := C.{c_name}_new((*C.{c_name}_t)(unsafe.Pointer(uintptr({lower_name}_rep))))"
+                        );
+                    }
+                    TypeDefKind::Handle(Handle::Borrow(resource))
+                        if self.interface.is_exported_resource(*resource) =>
+                    {
+                        // Borrows of exported resources are passed as their
+                        // representation, which is only needed for the call.
+                        self.interface.gen.imports.unsafe_ = true;
+                        let c_name = self.interface.get_resource_c_name(*resource);
+                        uwriteln!(
+                            self.lower_src,
+                            "{lower_name}_rep := {c_name}_next_rep
+                            {c_name}_next_rep++
+                            {c_name}_reps[{lower_name}_rep] = {param}
+                            defer delete({c_name}_reps, {lower_name}_rep)
+                            {lower_name} := (*C.{c_name}_t)(unsafe.Pointer(uintptr({lower_name}_rep)))"
+                        );
+                    }
+                    TypeDefKind::Handle(_) => {
+                        let c_ty = self.interface.get_c_ty(&Type::Id(*id));
+                        uwriteln!(
+                            self.lower_src,
+                            "{lower_name} := {c_ty}{{__handle: C.int32_t({param})}}"
+                        );
+                    }
                     TypeDefKind::Future(_) => todo!("impl future"),
                     TypeDefKind::Stream(_) => todo!("impl stream"),
-                    TypeDefKind::Resource => todo!("impl resource"),
-                    TypeDefKind::Handle(_) => todo!("impl handle"),
+                    TypeDefKind::Resource => unreachable!(),
                     TypeDefKind::Unknown => unreachable!(),
                 }
             }
@@ -1889,7 +2516,7 @@ impl<'a, 'b> FunctionBindgen<'a, 'b> {
                 uwriteln!(self.lift_src, "{lift_name} := {param}");
             }
             Type::String => {
-                self.interface.gen.needs_import_unsafe = true;
+                self.interface.gen.imports.unsafe_ = true;
                 uwriteln!(
                     self.lift_src,
                     "var {name} {value}
@@ -1970,7 +2597,7 @@ impl<'a, 'b> FunctionBindgen<'a, 'b> {
                         if let Some(err_inner) = err {
                             let err_inner_name = self.interface.get_c_ty(&err_inner);
                             if !is_empty_type(self.interface.resolve, &err_inner) {
-                                self.interface.gen.needs_import_unsafe = true;
+                                self.interface.gen.imports.unsafe_ = true;
                                 uwriteln!(self.lift_src, "{lift_name}_ptr := *(*{err_inner_name})(unsafe.Pointer(&{param}.val))");
                             }
                             self.lift_value(
@@ -1986,7 +2613,7 @@ impl<'a, 'b> FunctionBindgen<'a, 'b> {
                         if let Some(ok_inner) = ok {
                             let ok_inner_name = self.interface.get_c_ty(&ok_inner);
                             if !is_empty_type(self.interface.resolve, &ok_inner) {
-                                self.interface.gen.needs_import_unsafe = true;
+                                self.interface.gen.imports.unsafe_ = true;
                                 uwriteln!(self.lift_src, "{lift_name}_ptr := *(*{ok_inner_name})(unsafe.Pointer(&{param}.val))");
                             }
                             self.lift_value(
@@ -1999,7 +2626,7 @@ impl<'a, 'b> FunctionBindgen<'a, 'b> {
                         uwriteln!(self.lift_src, "}}");
                     }
                     TypeDefKind::List(l) => {
-                        self.interface.gen.needs_import_unsafe = true;
+                        self.interface.gen.imports.unsafe_ = true;
                         let list_ty = self.interface.get_ty(&Type::Id(*id));
                         let c_ty_name = self.interface.get_c_ty(l);
                         uwriteln!(self.lift_src, "var {lift_name} {list_ty}",);
@@ -2043,7 +2670,7 @@ impl<'a, 'b> FunctionBindgen<'a, 'b> {
                         uwriteln!(self.lift_src, "{lift_name} = {lift_name}_val");
                     }
                     TypeDefKind::Variant(v) => {
-                        self.interface.gen.needs_import_unsafe = true;
+                        self.interface.gen.imports.unsafe_ = true;
                         let name = self.interface.get_ty(&Type::Id(*id));
                         uwriteln!(self.lift_src, "var {lift_name} {name}");
                         for (i, case) in v.cases.iter().enumerate() {
@@ -2083,10 +2710,40 @@ impl<'a, 'b> FunctionBindgen<'a, 'b> {
                             self.lift_src.push_str("}\n");
                         }
                     }
+                    TypeDefKind::Handle(handle) => {
+                        let resource = match handle {
+                            Handle::Own(resource) | Handle::Borrow(resource) => *resource,
+                        };
+                        let c_name = self.interface.get_resource_c_name(resource);
+                        match handle {
+                            Handle::Borrow(_) if self.interface.is_exported_resource(resource) => {
+                                self.interface.gen.imports.unsafe_ = true;
+                                uwriteln!(
+                                    self.lift_src,
+                                    "{lift_name} := {c_name}_reps[int32(uintptr(unsafe.Pointer({param})))]"
+                                );
+                            }
+                            Handle::Own(_) if self.interface.is_exported_resource(resource) => {
+                                // Take the Go value back from the handle and
+                                // drop the handle, which removes the value
+                                // from the table through the destructor.
+                                self.interface.gen.imports.unsafe_ = true;
+                                uwriteln!(
+                                    self.lift_src,
+                                    "{lift_name}_rep := int32(uintptr(unsafe.Pointer(C.{c_name}_rep({param}))))
+                                    {lift_name} := {c_name}_reps[{lift_name}_rep]
+                                    C.{c_name}_drop_own({param})"
+                                );
+                            }
+                            _ => {
+                                let ty = self.interface.get_ty(&Type::Id(*id));
+                                uwriteln!(self.lift_src, "{lift_name} := {ty}({param}.__handle)");
+                            }
+                        }
+                    }
                     TypeDefKind::Future(_) => todo!("impl future"),
                     TypeDefKind::Stream(_) => todo!("impl stream"),
-                    TypeDefKind::Resource => todo!("impl resource"),
-                    TypeDefKind::Handle(_) => todo!("impl handle"),
+                    TypeDefKind::Resource => unreachable!(),
                     TypeDefKind::Unknown => unreachable!(),
                 }
             }
@@ -2103,3 +2760,124 @@ impl<'a, 'b> FunctionBindgen<'a, 'b> {
         field.name.to_snake_case()
     }
 }
+
+/// Prints the conversions between `Result`s and Go `error`s used by functions
+/// returning a `result` when `Opts::result_errors` is set.
+fn print_result_errors(src: &mut Source) {
+    uwriteln!(
+        src,
+        "
+        // ResultError is the error returned in place of the `err` value of a
+        // `result`.
+        type ResultError[E any] struct {{
+            Err E
+        }}
+
+        func (e ResultError[E]) Error() string {{
+            return fmt.Sprint(e.Err)
+        }}
+
+        func resultToValue[T any, E any](r Result[T, E]) (T, error) {{
+            if r.IsErr() {{
+                return r.Val, ResultError[E]{{r.Err}}
+            }}
+            return r.Val, nil
+        }}
+
+        func resultToError[T any, E any](r Result[T, E]) error {{
+            if r.IsErr() {{
+                return ResultError[E]{{r.Err}}
+            }}
+            return nil
+        }}
+
+        func resultFromValue[T any, E any](val T, err error) Result[T, E] {{
+            var r Result[T, E]
+            if err == nil {{
+                r.Set(val)
+                return r
+            }}
+            var resultErr ResultError[E]
+            if errors.As(err, &resultErr) {{
+                r.SetErr(resultErr.Err)
+                return r
+            }}
+            // Other errors only have an `err` value to map to when it's a
+            // string or there's none at all.
+            var e E
+            switch p := any(&e).(type) {{
+            case *string:
+                *p = err.Error()
+            case *struct{{}}:
+            default:
+                panic(err)
+            }}
+            r.SetErr(e)
+            return r
+        }}
+
+        func resultFromError[E any](err error) Result[struct{{}}, E] {{
+            return resultFromValue[struct{{}}, E](struct{{}}{{}}, err)
+        }}
+        "
+    );
+}
+
+fn dealias(resolve: &Resolve, mut id: TypeId) -> TypeId {
+    loop {
+        match &resolve.types[id].kind {
+            TypeDefKind::Type(Type::Id(that_id)) => id = *that_id,
+            _ => break id,
+        }
+    }
+}
+
+fn contains_handle(resolve: &Resolve, ty: &Type) -> bool {
+    let optional = |ty: Option<&Type>| ty.is_some_and(|ty| contains_handle(resolve, ty));
+    let id = match ty {
+        Type::Id(id) => *id,
+        _ => return false,
+    };
+    match &resolve.types[id].kind {
+        TypeDefKind::Handle(_) => true,
+        TypeDefKind::Type(t) | TypeDefKind::List(t) | TypeDefKind::Option(t) => {
+            contains_handle(resolve, t)
+        }
+        TypeDefKind::Record(r) => r.fields.iter().any(|f| contains_handle(resolve, &f.ty)),
+        TypeDefKind::Tuple(t) => t.types.iter().any(|t| contains_handle(resolve, t)),
+        TypeDefKind::Variant(v) => v.cases.iter().any(|c| optional(c.ty.as_ref())),
+        TypeDefKind::Result(r) => optional(r.ok.as_ref()) || optional(r.err.as_ref()),
+        TypeDefKind::Flags(_)
+        | TypeDefKind::Enum(_)
+        | TypeDefKind::Resource
+        | TypeDefKind::Future(_)
+        | TypeDefKind::Stream(_) => false,
+        TypeDefKind::Unknown => unreachable!(),
+    }
+}
+
+/// Returns whether values of type `ty` own any memory, such as the buffers of
+/// strings and lists, which is freed separately from any handles they contain.
+fn owns_memory(resolve: &Resolve, ty: &Type) -> bool {
+    let optional = |ty: Option<&Type>| ty.is_some_and(|ty| owns_memory(resolve, ty));
+    let id = match ty {
+        Type::Id(id) => *id,
+        Type::String => return true,
+        _ => return false,
+    };
+    match &resolve.types[id].kind {
+        TypeDefKind::List(_) => true,
+        TypeDefKind::Type(t) | TypeDefKind::Option(t) => owns_memory(resolve, t),
+        TypeDefKind::Record(r) => r.fields.iter().any(|f| owns_memory(resolve, &f.ty)),
+        TypeDefKind::Tuple(t) => t.types.iter().any(|t| owns_memory(resolve, t)),
+        TypeDefKind::Variant(v) => v.cases.iter().any(|c| optional(c.ty.as_ref())),
+        TypeDefKind::Result(r) => optional(r.ok.as_ref()) || optional(r.err.as_ref()),
+        TypeDefKind::Handle(_)
+        | TypeDefKind::Flags(_)
+        | TypeDefKind::Enum(_)
+        | TypeDefKind::Resource
+        | TypeDefKind::Future(_)
+        | TypeDefKind::Stream(_) => false,
+        TypeDefKind::Unknown => unreachable!(),
+    }
+}
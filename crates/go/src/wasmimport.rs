@@ -0,0 +1,1115 @@
+//! Bindings calling `//go:wasmimport` functions and exporting `//export`
+//! functions with the raw wasm signatures of the canonical ABI, which don't
+//! depend on cgo or the C bindings.
+
+use std::fmt::Write;
+use std::mem;
+
+use heck::{ToSnakeCase, ToUpperCamelCase};
+use wit_bindgen_c::is_empty_type;
+use wit_bindgen_core::abi::{
+    self, AbiVariant, Bindgen, Bitcast, Instruction, LiftLower, WasmSignature, WasmType,
+};
+use wit_bindgen_core::wit_parser::{
+    FlagsRepr, Function, FunctionKind, Handle, Resolve, SizeAlign, Type, TypeDefKind, TypeId,
+};
+use wit_bindgen_core::{uwrite, uwriteln, Ns, Source};
+
+use crate::{avoid_keyword, dealias, InterfaceGenerator, TinyGo};
+
+impl TinyGo {
+    /// Prints the allocator the host uses to pass lists and strings to the
+    /// module, along with the return area of functions.
+    pub(crate) fn finish_wasm(&mut self) {
+        self.imports.unsafe_ = true;
+        uwriteln!(
+            self.src,
+            "
+            // Allocations made through `cabi_realloc`, which are kept alive until
+            // they are freed.
+            var cabi_allocations = make(map[int32][]byte)
+
+            //export cabi_realloc
+            func cabi_realloc(ptr, old_size, align, new_size int32) int32 {{
+                if new_size == 0 {{
+                    return align
+                }}
+                buf := make([]byte, new_size+align-1)
+                addr := (int32(uintptr(unsafe.Pointer(&buf[0]))) + align - 1) &^ (align - 1)
+                if ptr != 0 {{
+                    copy(unsafe.Slice((*byte)(unsafe.Pointer(uintptr(addr))), new_size), unsafe.Slice((*byte)(unsafe.Pointer(uintptr(ptr))), old_size))
+                    cabi_free(ptr)
+                }}
+                cabi_allocations[addr] = buf
+                return addr
+            }}
+
+            func cabi_free(ptr int32) {{
+                delete(cabi_allocations, ptr)
+            }}
+            "
+        );
+        if self.return_pointer_area_size > 0 {
+            // Canonical ABI alignments are at most 8, which is that of
+            // `uint64`.
+            let len = self.return_pointer_area_size.div_ceil(8);
+            uwriteln!(self.src, "var cabi_ret_area [{len}]uint64");
+        }
+    }
+}
+
+impl InterfaceGenerator<'_> {
+    /// Returns the name of the module the functions of this interface are
+    /// imported from, or the prefix of the names they're exported with.
+    pub(crate) fn wasm_module(&self) -> String {
+        match self.name {
+            Some(key) => self.resolve.name_world_key(key),
+            None => "$root".to_string(),
+        }
+    }
+
+    pub(crate) fn wasm_import(&mut self, func: &Function) {
+        let resolve = self.resolve;
+        let sig = resolve.wasm_signature(AbiVariant::GuestImport, func);
+
+        // Declare the actual wasm import that's called, which has the raw
+        // wasm signature.
+        let import_fn = format!("wasmimport_{}", self.get_c_func_name(&func.name));
+        uwriteln!(
+            self.src,
+            "//go:wasmimport {} {}
+            func {import_fn}({}){}
+            ",
+            self.wasm_module(),
+            func.name,
+            wasm_params(&sig),
+            wasm_results(&sig),
+        );
+
+        // Define the Go function, which lowers its arguments to call the
+        // import and lifts its results.
+        self.print_func_signature(resolve, func);
+        let mut f = FunctionBindgen::new(self, import_fn);
+        let skip = match func.kind {
+            FunctionKind::Method(_) => {
+                f.locals.insert("self").unwrap();
+                f.params.push("self".to_string());
+                1
+            }
+            _ => 0,
+        };
+        for (name, ty) in func.params.iter().skip(skip) {
+            let name = avoid_keyword(&name.to_snake_case());
+            f.locals.insert(&name).unwrap();
+            if points_to_memory(resolve, ty) {
+                f.keep_alive.push(name.clone());
+            }
+            f.params.push(name);
+        }
+        abi::call(
+            resolve,
+            AbiVariant::GuestImport,
+            LiftLower::LowerArgsLiftResults,
+            func,
+            &mut f,
+        );
+
+        let FunctionBindgen { src, cleanup, .. } = f;
+        if let Some(cleanup) = cleanup {
+            uwriteln!(self.src, "var {cleanup} []int32");
+        }
+        self.src.push_str(&src);
+        self.src.push_str("}\n\n");
+    }
+
+    pub(crate) fn wasm_export(&mut self, func: &Function) {
+        let resolve = self.resolve;
+        let sig = resolve.wasm_signature(AbiVariant::GuestExport, func);
+        let core_module_name = self.name.map(|key| resolve.name_world_key(key));
+        let export_name = func.core_export_name(core_module_name.as_deref());
+        let c_name = self.get_c_func_name(&func.name);
+
+        // Define the function with the raw wasm signature that's exported,
+        // which lifts its arguments to call the Go implementation and lowers
+        // its results.
+        let mut f = FunctionBindgen::new(self, String::new());
+        let mut params = Vec::new();
+        for param in sig.params.iter() {
+            let name = f.locals.tmp("arg");
+            params.push(format!("{name} {}", wasm_type(*param)));
+            f.params.push(name);
+        }
+        abi::call(
+            resolve,
+            AbiVariant::GuestExport,
+            LiftLower::LiftArgsLowerResults,
+            func,
+            &mut f,
+        );
+        let FunctionBindgen { src, borrows, .. } = f;
+
+        let mut export_func = Source::default();
+        uwriteln!(
+            export_func,
+            "//export {export_name}
+            func wasmexport_{c_name}({}){} {{",
+            params.join(", "),
+            wasm_results(&sig),
+        );
+        for (_, borrows) in borrows.iter() {
+            uwriteln!(export_func, "var {borrows} []int32");
+        }
+        export_func.push_str(&src);
+        export_func.push_str("}\n\n");
+
+        if abi::guest_export_needs_post_return(resolve, func) {
+            let mut f = FunctionBindgen::new(self, String::new());
+            let mut params = Vec::new();
+            for result in sig.results.iter() {
+                let name = f.locals.tmp("arg");
+                params.push(format!("{name} {}", wasm_type(*result)));
+                f.params.push(name);
+            }
+            abi::post_return(resolve, func, &mut f);
+            let FunctionBindgen { src, .. } = f;
+
+            uwriteln!(
+                export_func,
+                "//export cabi_post_{export_name}
+                func wasmexport_post_{c_name}({}) {{",
+                params.join(", "),
+            );
+            export_func.push_str(&src);
+            export_func.push_str("}\n\n");
+        }
+
+        // Methods are declared by the interface of their resource instead.
+        let interface_method_decl = match func.kind {
+            FunctionKind::Method(_) => None,
+            _ => Some(self.get_func_signature_no_interface(resolve, func)),
+        };
+        self.export_funcs
+            .push((interface_method_decl, export_func.to_string()));
+    }
+}
+
+struct FunctionBindgen<'a, 'b> {
+    gen: &'a mut InterfaceGenerator<'b>,
+    locals: Ns,
+    src: Source,
+    block_storage: Vec<Source>,
+    blocks: Vec<(String, Vec<String>)>,
+    payloads: Vec<String>,
+    params: Vec<String>,
+    wasm_import: String,
+
+    // The parameters of an import referring to Go memory that's passed to the
+    // import, which have to be kept alive until it returns.
+    keep_alive: Vec<String>,
+
+    // The allocations made to lower the arguments of an import, which are
+    // freed once it returns.
+    cleanup: Option<String>,
+
+    // The handles of imported resources borrowed by an export, which are
+    // dropped once the call finishes.
+    borrows: Vec<(TypeId, String)>,
+}
+
+impl<'a, 'b> FunctionBindgen<'a, 'b> {
+    fn new(gen: &'a mut InterfaceGenerator<'b>, wasm_import: String) -> FunctionBindgen<'a, 'b> {
+        FunctionBindgen {
+            gen,
+            locals: Ns::default(),
+            src: Source::default(),
+            block_storage: Vec::new(),
+            blocks: Vec::new(),
+            payloads: Vec::new(),
+            params: Vec::new(),
+            wasm_import,
+            keep_alive: Vec::new(),
+            cleanup: None,
+            borrows: Vec::new(),
+        }
+    }
+
+    fn load(&mut self, ty: &str, offset: i32, operands: &[String], results: &mut Vec<String>) {
+        self.gen.gen.imports.unsafe_ = true;
+        results.push(format!(
+            "*(*{ty})(unsafe.Pointer(uintptr({} + {offset})))",
+            operands[0]
+        ));
+    }
+
+    fn load_ext(&mut self, ty: &str, offset: i32, operands: &[String], results: &mut Vec<String>) {
+        self.load(ty, offset, operands, results);
+        let result = results.pop().unwrap();
+        results.push(format!("int32({result})"));
+    }
+
+    fn store(&mut self, ty: &str, offset: i32, operands: &[String]) {
+        let value = &operands[0];
+        let value = if value.starts_with(&format!("{ty}(")) {
+            value.clone()
+        } else {
+            format!("{ty}({value})")
+        };
+        self.gen.gen.imports.unsafe_ = true;
+        uwriteln!(
+            self.src,
+            "*(*{ty})(unsafe.Pointer(uintptr({} + {offset}))) = {value}",
+            operands[1],
+        );
+    }
+
+    fn bitcast(&mut self, func: &str, op: &str) -> String {
+        self.gen.gen.imports.math = true;
+        format!("math.{func}({op})")
+    }
+
+    fn cleanup(&mut self) -> String {
+        match &self.cleanup {
+            Some(cleanup) => cleanup.clone(),
+            None => {
+                let cleanup = self.locals.tmp("cleanup");
+                self.cleanup = Some(cleanup.clone());
+                cleanup
+            }
+        }
+    }
+
+    /// Allocates `len` elements of `size` bytes through `cabi_realloc`, which
+    /// imports free once they return and exports in their post-return.
+    fn alloc(&mut self, len: &str, size: usize, align: usize, realloc: Option<&str>) -> String {
+        let ptr = self.locals.tmp("ptr");
+        uwriteln!(
+            self.src,
+            "{ptr} := cabi_realloc(0, 0, {align}, int32({len})*{size})"
+        );
+        if realloc.is_none() {
+            let cleanup = self.cleanup();
+            uwriteln!(self.src, "{cleanup} = append({cleanup}, {ptr})");
+        }
+        ptr
+    }
+
+    fn lower_list(
+        &mut self,
+        element: &str,
+        data: &str,
+        size: usize,
+        align: usize,
+        op: &str,
+        realloc: Option<&str>,
+    ) -> [String; 2] {
+        self.gen.gen.imports.unsafe_ = true;
+        let vec = self.locals.tmp("vec");
+        uwriteln!(self.src, "{vec} := {op}");
+        let ptr = match realloc {
+            // Imports pass the memory of the Go value, which is kept alive
+            // for the duration of the call.
+            None => format!("int32(uintptr(unsafe.Pointer(unsafe.{data}({vec}))))"),
+            // Exports transfer ownership of a copy, freed in the post-return.
+            Some(_) => {
+                let ptr = self.alloc(&format!("len({vec})"), size, align, realloc);
+                uwriteln!(
+                    self.src,
+                    "copy(unsafe.Slice((*{element})(unsafe.Pointer(uintptr({ptr}))), len({vec})), {vec})"
+                );
+                ptr
+            }
+        };
+        [ptr, format!("int32(len({vec}))")]
+    }
+
+    /// Declares the variables that the lowered representation of a variant is
+    /// assigned to in each case.
+    fn variant_results(&mut self, name: &str, tys: &[WasmType], results: &mut Vec<String>) {
+        for ty in tys {
+            let local = self.locals.tmp(name);
+            uwriteln!(self.src, "var {local} {}", wasm_type(*ty));
+            results.push(local);
+        }
+    }
+
+    /// Prints a case of the lowering of a variant, declaring its payload if
+    /// it's used.
+    fn variant_case(
+        &mut self,
+        payload: &str,
+        value: Option<String>,
+        block: &str,
+        results: &[String],
+        block_results: &[String],
+    ) {
+        if let Some(value) = value {
+            if mentions(block, payload) || block_results.iter().any(|r| mentions(r, payload)) {
+                uwriteln!(self.src, "{payload} := {value}");
+            }
+        }
+        self.src.push_str(block);
+        for (result, op) in results.iter().zip(block_results) {
+            uwriteln!(self.src, "{result} = {op}");
+        }
+    }
+
+    fn borrows(&mut self, resource: TypeId) -> String {
+        match self.borrows.iter().find(|(id, _)| *id == resource) {
+            Some((_, name)) => name.clone(),
+            None => {
+                let name = self.locals.tmp("borrows");
+                self.borrows.push((resource, name.clone()));
+                name
+            }
+        }
+    }
+}
+
+impl Bindgen for FunctionBindgen<'_, '_> {
+    type Operand = String;
+
+    fn sizes(&self) -> &SizeAlign {
+        &self.gen.gen.sizes
+    }
+
+    fn push_block(&mut self) {
+        let prev = mem::take(&mut self.src);
+        self.block_storage.push(prev);
+    }
+
+    fn finish_block(&mut self, operands: &mut Vec<String>) {
+        let to_restore = self.block_storage.pop().unwrap();
+        let src = mem::replace(&mut self.src, to_restore);
+        self.blocks.push((src.into(), mem::take(operands)));
+    }
+
+    fn return_pointer(&mut self, size: usize, align: usize) -> String {
+        let gen = &mut *self.gen.gen;
+        gen.return_pointer_area_size = gen.return_pointer_area_size.max(size);
+        gen.return_pointer_area_align = gen.return_pointer_area_align.max(align);
+        gen.imports.unsafe_ = true;
+        let ptr = self.locals.tmp("ptr");
+        uwriteln!(
+            self.src,
+            "{ptr} := int32(uintptr(unsafe.Pointer(&cabi_ret_area)))"
+        );
+        ptr
+    }
+
+    fn is_list_canonical(&self, resolve: &Resolve, ty: &Type) -> bool {
+        match ty {
+            Type::Id(id) => match &resolve.types[*id].kind {
+                TypeDefKind::Type(t) => self.is_list_canonical(resolve, t),
+                _ => false,
+            },
+            Type::U8
+            | Type::S8
+            | Type::U16
+            | Type::S16
+            | Type::U32
+            | Type::S32
+            | Type::U64
+            | Type::S64
+            | Type::Float32
+            | Type::Float64 => true,
+            Type::Bool | Type::Char | Type::String => false,
+        }
+    }
+
+    fn emit(
+        &mut self,
+        resolve: &Resolve,
+        inst: &Instruction<'_>,
+        operands: &mut Vec<String>,
+        results: &mut Vec<String>,
+    ) {
+        match inst {
+            Instruction::GetArg { nth } => results.push(self.params[*nth].clone()),
+            Instruction::I32Const { val } => results.push(val.to_string()),
+            Instruction::ConstZero { tys } => {
+                for _ in tys.iter() {
+                    results.push("0".to_string());
+                }
+            }
+
+            Instruction::U8FromI32 => results.push(format!("uint8({})", operands[0])),
+            Instruction::S8FromI32 => results.push(format!("int8({})", operands[0])),
+            Instruction::U16FromI32 => results.push(format!("uint16({})", operands[0])),
+            Instruction::S16FromI32 => results.push(format!("int16({})", operands[0])),
+            Instruction::U32FromI32 => results.push(format!("uint32({})", operands[0])),
+            Instruction::S32FromI32 => results.push(format!("int32({})", operands[0])),
+            Instruction::U64FromI64 => results.push(format!("uint64({})", operands[0])),
+            Instruction::S64FromI64 => results.push(format!("int64({})", operands[0])),
+            Instruction::CharFromI32 => results.push(format!("rune({})", operands[0])),
+
+            Instruction::I32FromU8
+            | Instruction::I32FromS8
+            | Instruction::I32FromU16
+            | Instruction::I32FromS16
+            | Instruction::I32FromU32
+            | Instruction::I32FromS32
+            | Instruction::I32FromChar => results.push(format!("int32({})", operands[0])),
+            Instruction::I64FromU64 | Instruction::I64FromS64 => {
+                results.push(format!("int64({})", operands[0]))
+            }
+
+            Instruction::Float32FromF32 | Instruction::F32FromFloat32 => {
+                results.push(format!("float32({})", operands[0]))
+            }
+            Instruction::Float64FromF64 | Instruction::F64FromFloat64 => {
+                results.push(format!("float64({})", operands[0]))
+            }
+
+            Instruction::Bitcasts { casts } => {
+                for (cast, op) in casts.iter().zip(operands) {
+                    let result = match cast {
+                        Bitcast::I32ToF32 | Bitcast::I64ToF32 => {
+                            self.bitcast("Float32frombits", &format!("uint32({op})"))
+                        }
+                        Bitcast::F32ToI32 => {
+                            format!("int32({})", self.bitcast("Float32bits", op))
+                        }
+                        Bitcast::F32ToI64 => {
+                            format!("int64({})", self.bitcast("Float32bits", op))
+                        }
+                        Bitcast::I64ToF64 => {
+                            self.bitcast("Float64frombits", &format!("uint64({op})"))
+                        }
+                        Bitcast::F64ToI64 => {
+                            format!("int64({})", self.bitcast("Float64bits", op))
+                        }
+                        Bitcast::I32ToI64 => format!("int64({op})"),
+                        Bitcast::I64ToI32 => format!("int32({op})"),
+                        Bitcast::None => op.clone(),
+                    };
+                    results.push(result);
+                }
+            }
+
+            Instruction::BoolFromI32 => results.push(format!("({} != 0)", operands[0])),
+            Instruction::I32FromBool => {
+                let result = self.locals.tmp("flag");
+                uwriteln!(self.src, "var {result} int32");
+                uwriteln!(self.src, "if {} {{", operands[0]);
+                uwriteln!(self.src, "{result} = 1");
+                uwriteln!(self.src, "}}");
+                results.push(result);
+            }
+
+            Instruction::RecordLower { record, .. } => {
+                let op = &operands[0];
+                for field in record.fields.iter() {
+                    results.push(format!("{op}.{}", self.gen.get_field_name(field)));
+                }
+            }
+            Instruction::RecordLift { record, ty, .. } => {
+                let name = self.gen.get_ty(&Type::Id(*ty));
+                let fields = record
+                    .fields
+                    .iter()
+                    .zip(operands.iter())
+                    .map(|(field, op)| format!("{}: {op}", self.gen.get_field_name(field)))
+                    .collect::<Vec<_>>();
+                results.push(format!("{name}{{{}}}", fields.join(", ")));
+            }
+
+            Instruction::TupleLower { tuple, .. } => {
+                let op = &operands[0];
+                for i in 0..tuple.types.len() {
+                    results.push(format!("{op}.F{i}"));
+                }
+            }
+            Instruction::TupleLift { ty, .. } => {
+                let name = self.gen.get_ty(&Type::Id(*ty));
+                let fields = operands
+                    .iter()
+                    .enumerate()
+                    .map(|(i, op)| format!("F{i}: {op}"))
+                    .collect::<Vec<_>>();
+                results.push(format!("{name}{{{}}}", fields.join(", ")));
+            }
+
+            Instruction::HandleLower { handle, .. } => {
+                let op = &operands[0];
+                let (Handle::Own(resource) | Handle::Borrow(resource)) = handle;
+                let resource = dealias(resolve, *resource);
+                match handle {
+                    Handle::Own(_) if self.gen.is_exported_resource(resource) => {
+                        // Hand a new representation for the Go value to the
+                        // host, which creates a handle for it.
+                        let c_name = self.gen.get_resource_c_name(resource);
+                        let rep = self.locals.tmp("rep");
+                        let handle = self.locals.tmp("handle");
+                        uwriteln!(
+                            self.src,
+                            "{rep} := {c_name}_next_rep
+                            {c_name}_next_rep++
+                            {c_name}_reps[{rep}] = {op}
+                            {handle} := wasmimport_{c_name}_new({rep})"
+                        );
+                        results.push(handle);
+                    }
+                    Handle::Borrow(_) if self.gen.is_exported_resource(resource) => {
+                        // Borrows of exported resources are passed as their
+                        // representation, which is only needed for the call.
+                        let c_name = self.gen.get_resource_c_name(resource);
+                        let rep = self.locals.tmp("rep");
+                        uwriteln!(
+                            self.src,
+                            "{rep} := {c_name}_next_rep
+                            {c_name}_next_rep++
+                            {c_name}_reps[{rep}] = {op}
+                            defer delete({c_name}_reps, {rep})"
+                        );
+                        results.push(rep);
+                    }
+                    _ => results.push(format!("int32({op})")),
+                }
+            }
+            Instruction::HandleLift { handle, .. } => {
+                let op = &operands[0];
+                let (Handle::Own(resource) | Handle::Borrow(resource)) = handle;
+                let resource = dealias(resolve, *resource);
+                let c_name = self.gen.get_resource_c_name(resource);
+                match handle {
+                    Handle::Borrow(_) if self.gen.is_exported_resource(resource) => {
+                        results.push(format!("{c_name}_reps[{op}]"));
+                    }
+                    Handle::Own(_) if self.gen.is_exported_resource(resource) => {
+                        // Take the Go value back from the handle and drop the
+                        // handle, which removes the value from the table
+                        // through the destructor.
+                        let rep = self.locals.tmp("rep");
+                        let value = self.locals.tmp("value");
+                        uwriteln!(
+                            self.src,
+                            "{rep} := wasmimport_{c_name}_rep({op})
+                            {value} := {c_name}_reps[{rep}]
+                            wasmimport_{c_name}_drop({op})"
+                        );
+                        results.push(value);
+                    }
+                    Handle::Borrow(_) if !self.gen.in_import => {
+                        // Borrowed handles are owned by the callee for the
+                        // duration of the call, so they're dropped once the
+                        // call finishes.
+                        let borrows = self.borrows(resource);
+                        uwriteln!(self.src, "{borrows} = append({borrows}, {op})");
+                        let ty = self.gen.get_ty(&Type::Id(resource));
+                        results.push(format!("{ty}({op})"));
+                    }
+                    _ => {
+                        let ty = self.gen.get_ty(&Type::Id(resource));
+                        results.push(format!("{ty}({op})"));
+                    }
+                }
+            }
+
+            Instruction::FlagsLower { flags, .. } => {
+                let op = &operands[0];
+                for i in 0..flags_count(flags.repr()) {
+                    if i == 0 {
+                        results.push(format!("int32({op})"));
+                    } else {
+                        results.push(format!("int32(({op}) >> {})", i * 32));
+                    }
+                }
+            }
+            Instruction::FlagsLift { ty, .. } => {
+                let name = self.gen.get_ty(&Type::Id(*ty));
+                let value = operands
+                    .iter()
+                    .enumerate()
+                    .map(|(i, op)| match i {
+                        0 => format!("{name}(uint32({op}))"),
+                        _ => format!("{name}(uint32({op}))<<{}", i * 32),
+                    })
+                    .collect::<Vec<_>>();
+                if value.is_empty() {
+                    results.push(format!("{name}(0)"));
+                } else {
+                    results.push(value.join(" | "));
+                }
+            }
+
+            Instruction::VariantPayloadName => {
+                let name = self.locals.tmp("payload");
+                results.push(name.clone());
+                self.payloads.push(name);
+            }
+
+            Instruction::VariantLower {
+                variant,
+                ty,
+                results: result_types,
+                ..
+            } => {
+                let blocks = self
+                    .blocks
+                    .drain(self.blocks.len() - variant.cases.len()..)
+                    .collect::<Vec<_>>();
+                let payloads = self
+                    .payloads
+                    .drain(self.payloads.len() - variant.cases.len()..)
+                    .collect::<Vec<_>>();
+
+                let name = self.gen.get_ty(&Type::Id(*ty));
+                self.variant_results("variant", result_types, results);
+                let op = &operands[0];
+                uwriteln!(self.src, "switch {op}.Kind() {{");
+                for ((case, (block, block_results)), payload) in
+                    variant.cases.iter().zip(blocks).zip(payloads)
+                {
+                    let case_name = case.name.to_upper_camel_case();
+                    uwriteln!(self.src, "case {name}Kind{case_name}:");
+                    let value = case
+                        .ty
+                        .filter(|ty| !is_empty_type(resolve, ty))
+                        .map(|_| format!("{op}.Get{case_name}()"));
+                    self.variant_case(&payload, value, &block, results, &block_results);
+                }
+                uwriteln!(self.src, "}}");
+            }
+
+            Instruction::VariantLift { variant, ty, .. } => {
+                let blocks = self
+                    .blocks
+                    .drain(self.blocks.len() - variant.cases.len()..)
+                    .collect::<Vec<_>>();
+
+                let name = self.gen.get_ty(&Type::Id(*ty));
+                let result = self.locals.tmp("variant");
+                uwriteln!(self.src, "var {result} {name}");
+                uwriteln!(self.src, "switch {} {{", operands[0]);
+                for (i, (case, (block, block_results))) in
+                    variant.cases.iter().zip(blocks).enumerate()
+                {
+                    let case_name = case.name.to_upper_camel_case();
+                    uwriteln!(self.src, "case {i}:");
+                    self.src.push_str(&block);
+                    match case.ty.filter(|ty| !is_empty_type(resolve, ty)) {
+                        Some(_) => uwriteln!(
+                            self.src,
+                            "{result} = {name}{case_name}({})",
+                            block_results[0]
+                        ),
+                        None => uwriteln!(self.src, "{result} = {name}{case_name}()"),
+                    }
+                }
+                uwriteln!(self.src, "}}");
+                results.push(result);
+            }
+
+            Instruction::EnumLower { .. } => {
+                results.push(format!("int32({}.Kind())", operands[0]));
+            }
+            Instruction::EnumLift { ty, .. } => {
+                let name = self.gen.get_ty(&Type::Id(*ty));
+                results.push(format!("{name}{{kind: {name}Kind({})}}", operands[0]));
+            }
+
+            Instruction::OptionLower {
+                results: result_types,
+                ..
+            } => {
+                let (some, some_results) = self.blocks.pop().unwrap();
+                let (none, none_results) = self.blocks.pop().unwrap();
+                let some_payload = self.payloads.pop().unwrap();
+                let _none_payload = self.payloads.pop().unwrap();
+
+                self.variant_results("option", result_types, results);
+                let op = &operands[0];
+                uwriteln!(self.src, "if {op}.IsSome() {{");
+                let value = Some(format!("{op}.Unwrap()"));
+                self.variant_case(&some_payload, value, &some, results, &some_results);
+                uwriteln!(self.src, "}} else {{");
+                self.variant_case("", None, &none, results, &none_results);
+                uwriteln!(self.src, "}}");
+            }
+
+            Instruction::OptionLift { ty, .. } => {
+                let (some, some_results) = self.blocks.pop().unwrap();
+                let (_none, none_results) = self.blocks.pop().unwrap();
+                assert!(none_results.is_empty());
+                assert!(some_results.len() == 1);
+
+                let name = self.gen.get_ty(&Type::Id(*ty));
+                let result = self.locals.tmp("option");
+                uwriteln!(self.src, "var {result} {name}");
+                uwriteln!(self.src, "if {} != 0 {{", operands[0]);
+                self.src.push_str(&some);
+                uwriteln!(self.src, "{result}.Set({})", some_results[0]);
+                uwriteln!(self.src, "}}");
+                results.push(result);
+            }
+
+            Instruction::ResultLower {
+                result,
+                results: result_types,
+                ..
+            } => {
+                let (err, err_results) = self.blocks.pop().unwrap();
+                let (ok, ok_results) = self.blocks.pop().unwrap();
+                let err_payload = self.payloads.pop().unwrap();
+                let ok_payload = self.payloads.pop().unwrap();
+
+                self.variant_results("result", result_types, results);
+                let op = &operands[0];
+                uwriteln!(self.src, "if {op}.IsOk() {{");
+                let value = result.ok.map(|_| format!("{op}.Unwrap()"));
+                self.variant_case(&ok_payload, value, &ok, results, &ok_results);
+                uwriteln!(self.src, "}} else {{");
+                let value = result.err.map(|_| format!("{op}.UnwrapErr()"));
+                self.variant_case(&err_payload, value, &err, results, &err_results);
+                uwriteln!(self.src, "}}");
+            }
+
+            Instruction::ResultLift { ty, .. } => {
+                let (err, err_results) = self.blocks.pop().unwrap();
+                let (ok, ok_results) = self.blocks.pop().unwrap();
+
+                let name = self.gen.get_ty(&Type::Id(*ty));
+                let ok_value = ok_results
+                    .first()
+                    .cloned()
+                    .unwrap_or_else(|| "struct{}{}".to_string());
+                let err_value = err_results
+                    .first()
+                    .cloned()
+                    .unwrap_or_else(|| "struct{}{}".to_string());
+
+                let result = self.locals.tmp("result");
+                uwriteln!(self.src, "var {result} {name}");
+                uwriteln!(self.src, "switch {} {{", operands[0]);
+                uwriteln!(self.src, "case 0:");
+                self.src.push_str(&ok);
+                uwriteln!(self.src, "{result}.Set({ok_value})");
+                uwriteln!(self.src, "case 1:");
+                self.src.push_str(&err);
+                uwriteln!(self.src, "{result}.SetErr({err_value})");
+                uwriteln!(self.src, "}}");
+                results.push(result);
+            }
+
+            Instruction::ListCanonLower { element, realloc } => {
+                let ty = self.gen.get_ty(element);
+                let size = self.gen.gen.sizes.size(element);
+                let align = self.gen.gen.sizes.align(element);
+                let lowered =
+                    self.lower_list(&ty, "SliceData", size, align, &operands[0], *realloc);
+                results.extend(lowered);
+            }
+            Instruction::StringLower { realloc } => {
+                let lowered = self.lower_list("byte", "StringData", 1, 1, &operands[0], *realloc);
+                results.extend(lowered);
+            }
+            Instruction::ListLower { element, realloc } => {
+                let (body, body_results) = self.blocks.pop().unwrap();
+                assert!(body_results.is_empty());
+                let size = self.gen.gen.sizes.size(element);
+                let align = self.gen.gen.sizes.align(element);
+                let vec = self.locals.tmp("vec");
+                let i = self.locals.tmp("i");
+
+                uwriteln!(self.src, "{vec} := {}", operands[0]);
+                let ptr = self.alloc(&format!("len({vec})"), size, align, *realloc);
+                let uses_base = mentions(&body, "base");
+                match (uses_base, mentions(&body, "e")) {
+                    (true, true) => uwriteln!(self.src, "for {i}, e := range {vec} {{"),
+                    (true, false) => uwriteln!(self.src, "for {i} := range {vec} {{"),
+                    (false, true) => uwriteln!(self.src, "for _, e := range {vec} {{"),
+                    (false, false) => uwriteln!(self.src, "for range {vec} {{"),
+                }
+                if uses_base {
+                    uwriteln!(self.src, "base := {ptr} + int32({i})*{size}");
+                }
+                self.src.push_str(&body);
+                uwriteln!(self.src, "}}");
+
+                results.push(ptr);
+                results.push(format!("int32(len({vec}))"));
+            }
+
+            Instruction::ListCanonLift { element, .. } => {
+                self.gen.gen.imports.unsafe_ = true;
+                let ty = self.gen.get_ty(element);
+                let ptr = self.locals.tmp("ptr");
+                let len = self.locals.tmp("length");
+                let list = self.locals.tmp("list");
+                uwriteln!(
+                    self.src,
+                    "{ptr} := {}
+                    {len} := {}
+                    {list} := make([]{ty}, {len})
+                    copy({list}, unsafe.Slice((*{ty})(unsafe.Pointer(uintptr({ptr}))), {len}))
+                    cabi_free({ptr})",
+                    operands[0],
+                    operands[1],
+                );
+                results.push(list);
+            }
+            Instruction::StringLift => {
+                self.gen.gen.imports.unsafe_ = true;
+                let ptr = self.locals.tmp("ptr");
+                let len = self.locals.tmp("length");
+                let str = self.locals.tmp("str");
+                uwriteln!(
+                    self.src,
+                    "{ptr} := {}
+                    {len} := {}
+                    {str} := string(unsafe.Slice((*byte)(unsafe.Pointer(uintptr({ptr}))), {len}))
+                    cabi_free({ptr})",
+                    operands[0],
+                    operands[1],
+                );
+                results.push(str);
+            }
+            Instruction::ListLift { element, .. } => {
+                let (body, body_results) = self.blocks.pop().unwrap();
+                assert_eq!(body_results.len(), 1);
+                let ty = self.gen.get_ty(element);
+                let size = self.gen.gen.sizes.size(element);
+                let ptr = self.locals.tmp("ptr");
+                let len = self.locals.tmp("length");
+                let list = self.locals.tmp("list");
+                let i = self.locals.tmp("i");
+
+                uwriteln!(
+                    self.src,
+                    "{ptr} := {}
+                    {len} := {}
+                    {list} := make([]{ty}, {len})
+                    for {i} := int32(0); {i} < {len}; {i}++ {{",
+                    operands[0],
+                    operands[1],
+                );
+                if mentions(&body, "base") || mentions(&body_results[0], "base") {
+                    uwriteln!(self.src, "base := {ptr} + {i}*{size}");
+                }
+                self.src.push_str(&body);
+                uwriteln!(self.src, "{list}[{i}] = {}", body_results[0]);
+                uwriteln!(self.src, "}}");
+                uwriteln!(self.src, "cabi_free({ptr})");
+                results.push(list);
+            }
+
+            Instruction::IterElem { .. } => results.push("e".to_string()),
+            Instruction::IterBasePointer => results.push("base".to_string()),
+
+            Instruction::CallWasm { sig, .. } => {
+                match sig.results.len() {
+                    0 => {}
+                    1 => {
+                        let ret = self.locals.tmp("ret");
+                        uwrite!(self.src, "{ret} := ");
+                        results.push(ret);
+                    }
+                    _ => unimplemented!("multi-value return not supported"),
+                }
+                uwriteln!(self.src, "{}({})", self.wasm_import, operands.join(", "));
+
+                if !self.keep_alive.is_empty() {
+                    self.gen.gen.imports.runtime = true;
+                    for param in self.keep_alive.iter() {
+                        uwriteln!(self.src, "runtime.KeepAlive({param})");
+                    }
+                }
+                if let Some(cleanup) = &self.cleanup {
+                    uwriteln!(self.src, "for _, allocation := range {cleanup} {{");
+                    uwriteln!(self.src, "cabi_free(allocation)");
+                    uwriteln!(self.src, "}}");
+                }
+            }
+
+            Instruction::CallInterface { func } => {
+                let name = self.gen.get_func_item_name(func);
+                let call = match func.kind {
+                    FunctionKind::Method(_) => {
+                        format!("{}.{name}({})", operands[0], operands[1..].join(", "))
+                    }
+                    _ => format!(
+                        "{}.{name}({})",
+                        self.gen.get_interface_var_name(),
+                        operands.join(", ")
+                    ),
+                };
+
+                // Values of empty types aren't needed to lower them, and Go
+                // doesn't allow unused variables.
+                let mut rets = Vec::new();
+                for ty in func.results.iter_types() {
+                    if is_empty_type(resolve, ty) {
+                        rets.push("_".to_string());
+                        results.push(format!("{}{{}}", self.gen.get_ty(ty)));
+                    } else {
+                        let ret = self.locals.tmp("ret");
+                        rets.push(ret.clone());
+                        results.push(ret);
+                    }
+                }
+                let call = self.gen.export_result(func, &call);
+                if rets.is_empty() {
+                    uwriteln!(self.src, "{call}");
+                } else if rets.iter().all(|ret| ret == "_") {
+                    uwriteln!(self.src, "{} = {call}", rets.join(", "));
+                } else {
+                    uwriteln!(self.src, "{} := {call}", rets.join(", "));
+                }
+
+                for (resource, borrows) in self.borrows.iter() {
+                    let c_name = self.gen.get_resource_c_name(*resource);
+                    uwriteln!(self.src, "for _, handle := range {borrows} {{");
+                    uwriteln!(self.src, "wasmimport_{c_name}_drop(handle)");
+                    uwriteln!(self.src, "}}");
+                }
+            }
+
+            Instruction::Return { amt, func } => match amt {
+                0 => {}
+                _ if !self.wasm_import.is_empty() => {
+                    let ret = self.gen.import_result(func, &operands.join(", "));
+                    uwriteln!(self.src, "return {ret}");
+                }
+                _ => uwriteln!(self.src, "return {}", operands.join(", ")),
+            },
+
+            Instruction::I32Load { offset } => self.load("int32", *offset, operands, results),
+            Instruction::I64Load { offset } => self.load("int64", *offset, operands, results),
+            Instruction::F32Load { offset } => self.load("float32", *offset, operands, results),
+            Instruction::F64Load { offset } => self.load("float64", *offset, operands, results),
+            Instruction::I32Store { offset } => self.store("int32", *offset, operands),
+            Instruction::I64Store { offset } => self.store("int64", *offset, operands),
+            Instruction::F32Store { offset } => self.store("float32", *offset, operands),
+            Instruction::F64Store { offset } => self.store("float64", *offset, operands),
+            Instruction::I32Store8 { offset } => self.store("uint8", *offset, operands),
+            Instruction::I32Store16 { offset } => self.store("uint16", *offset, operands),
+
+            Instruction::I32Load8U { offset } => self.load_ext("uint8", *offset, operands, results),
+            Instruction::I32Load8S { offset } => self.load_ext("int8", *offset, operands, results),
+            Instruction::I32Load16U { offset } => {
+                self.load_ext("uint16", *offset, operands, results)
+            }
+            Instruction::I32Load16S { offset } => {
+                self.load_ext("int16", *offset, operands, results)
+            }
+
+            Instruction::GuestDeallocate { .. } | Instruction::GuestDeallocateString => {
+                uwriteln!(self.src, "cabi_free({})", operands[0]);
+            }
+            Instruction::GuestDeallocateVariant { blocks } => {
+                let blocks = self
+                    .blocks
+                    .drain(self.blocks.len() - blocks..)
+                    .collect::<Vec<_>>();
+
+                uwriteln!(self.src, "switch {} {{", operands[0]);
+                for (i, (block, results)) in blocks.into_iter().enumerate() {
+                    assert!(results.is_empty());
+                    uwriteln!(self.src, "case {i}:");
+                    self.src.push_str(&block);
+                }
+                uwriteln!(self.src, "}}");
+            }
+            Instruction::GuestDeallocateList { element } => {
+                let (body, results) = self.blocks.pop().unwrap();
+                assert!(results.is_empty());
+                // Only elements owning memory need to be deallocated
+                // themselves.
+                if !mentions(&body, "base") {
+                    uwriteln!(self.src, "cabi_free({})", operands[0]);
+                    return;
+                }
+                let size = self.gen.gen.sizes.size(element);
+                let ptr = self.locals.tmp("ptr");
+                let len = self.locals.tmp("length");
+                let i = self.locals.tmp("i");
+                uwriteln!(
+                    self.src,
+                    "{ptr} := {}
+                    {len} := {}
+                    for {i} := int32(0); {i} < {len}; {i}++ {{
+                        base := {ptr} + {i}*{size}",
+                    operands[0],
+                    operands[1],
+                );
+                self.src.push_str(&body);
+                uwriteln!(self.src, "}}");
+                uwriteln!(self.src, "cabi_free({ptr})");
+            }
+
+            i => unimplemented!("{:?}", i),
+        }
+    }
+}
+
+/// Returns whether the lowered representation of `ty` points to the memory
+/// of the Go value.
+fn points_to_memory(resolve: &Resolve, ty: &Type) -> bool {
+    let optional = |ty: Option<&Type>| ty.is_some_and(|ty| points_to_memory(resolve, ty));
+    let id = match ty {
+        Type::Id(id) => *id,
+        Type::String => return true,
+        _ => return false,
+    };
+    match &resolve.types[id].kind {
+        TypeDefKind::List(_) => true,
+        TypeDefKind::Type(t) | TypeDefKind::Option(t) => points_to_memory(resolve, t),
+        TypeDefKind::Record(r) => r.fields.iter().any(|f| points_to_memory(resolve, &f.ty)),
+        TypeDefKind::Tuple(t) => t.types.iter().any(|t| points_to_memory(resolve, t)),
+        TypeDefKind::Variant(v) => v.cases.iter().any(|c| optional(c.ty.as_ref())),
+        TypeDefKind::Result(r) => optional(r.ok.as_ref()) || optional(r.err.as_ref()),
+        TypeDefKind::Flags(_)
+        | TypeDefKind::Enum(_)
+        | TypeDefKind::Resource
+        | TypeDefKind::Handle(_)
+        | TypeDefKind::Future(_)
+        | TypeDefKind::Stream(_) => false,
+        TypeDefKind::Unknown => unreachable!(),
+    }
+}
+
+/// Returns whether the Go source `src` refers to the variable `name`.
+fn mentions(src: &str, name: &str) -> bool {
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    src.match_indices(name).any(|(i, _)| {
+        // Fields named `name` aren't the variable.
+        !src[..i]
+            .chars()
+            .next_back()
+            .is_some_and(|c| is_ident(c) || c == '.')
+            && !src[i + name.len()..].chars().next().is_some_and(is_ident)
+    })
+}
+
+fn flags_count(repr: FlagsRepr) -> usize {
+    match repr {
+        FlagsRepr::U8 | FlagsRepr::U16 => 1,
+        FlagsRepr::U32(n) => n,
+    }
+}
+
+fn wasm_type(ty: WasmType) -> &'static str {
+    match ty {
+        WasmType::I32 => "int32",
+        WasmType::I64 => "int64",
+        WasmType::F32 => "float32",
+        WasmType::F64 => "float64",
+    }
+}
+
+fn wasm_results(sig: &WasmSignature) -> String {
+    match sig.results.len() {
+        0 => String::new(),
+        1 => format!(" {}", wasm_type(sig.results[0])),
+        _ => unimplemented!("multi-value return not supported"),
+    }
+}
+
+fn wasm_params(sig: &WasmSignature) -> String {
+    sig.params
+        .iter()
+        .enumerate()
+        .map(|(i, ty)| format!("arg{i} {}", wasm_type(*ty)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -5,21 +5,8 @@ use std::path::Path;
 use std::process::Command;
 
 macro_rules! codegen_test {
-    // todo: implement resource support and then remove the following lines:
-    (resources $name:tt $test:tt) => {};
-    (resource_alias $name:tt $test:tt) => {};
-    (return_resource_from_export $name:tt $test:tt) => {};
-    (import_and_export_resource $name:tt $test:tt) => {};
-    (import_and_export_resource_alias $name:tt $test:tt) => {};
-    (resources_with_lists $name:tt $test:tt) => {};
-    (resource_local_alias $name:tt $test:tt) => {};
-    (resource_local_alias_borrow $name:tt $test:tt) => {};
-    (resource_local_alias_borrow_import $name:tt $test:tt) => {};
-    (resource_borrow_in_record $name:tt $test:tt) => {};
-    (resource_borrow_in_record_export $name:tt $test:tt) => {};
-    (resource_own_in_other_interface $name:tt $test:tt) => {};
-    (resources_in_aggregates $name:tt $test:tt) => {};
-    (issue668 $name:tt $test:tt) => {};
+    // todo: include package versions in generated names and then remove the
+    // following line:
     (multiversion $name:tt $test:tt) => {};
 
     ($id:ident $name:tt $test:tt) => {
@@ -35,7 +22,38 @@ macro_rules! codegen_test {
                         .unwrap()
                 },
                 verify,
-            )
+            );
+            test_helpers::run_world_codegen_test(
+                "guest-go-wasmimport",
+                $test.as_ref(),
+                |resolve, world, files| {
+                    let mut opts = wit_bindgen_go::Opts::default();
+                    opts.wasmimport = true;
+                    opts.build().generate(resolve, world, files).unwrap()
+                },
+                verify,
+            );
+            test_helpers::run_world_codegen_test(
+                "guest-go-result-errors",
+                $test.as_ref(),
+                |resolve, world, files| {
+                    let mut opts = wit_bindgen_go::Opts::default();
+                    opts.result_errors = true;
+                    opts.build().generate(resolve, world, files).unwrap()
+                },
+                verify,
+            );
+            test_helpers::run_world_codegen_test(
+                "guest-go-file-per-interface",
+                $test.as_ref(),
+                |resolve, world, files| {
+                    let mut opts = wit_bindgen_go::Opts::default();
+                    opts.file_per_interface = true;
+                    opts.package_name = Some("bindings".to_string());
+                    opts.build().generate(resolve, world, files).unwrap()
+                },
+                verify,
+            );
         }
     };
 }
@@ -49,27 +67,17 @@ fn verify(dir: &Path, name: &str) {
     // The generated go package is named after the world's name.
     // But tinygo currently does not support non-main package and requires
     // a `main()` function in the module to compile.
-    // The following code replaces the package name to `package main` and
-    // adds a `func main() {}` function at the bottom of the file.
+    // The following code replaces the package name to `package main` in each
+    // file and adds a `func main() {}` function at the bottom of the main file.
 
     // TODO: However, there is still an issue. Since the go module does not
     // invoke the imported functions, they will be skipped by the compiler.
     // This will weaken the test's ability to verify imported functions
-    let file = std::fs::OpenOptions::new()
-        .read(true)
-        .write(true)
-        .open(&main)
-        .expect("failed to open file");
-    let mut reader = BufReader::new(file);
-    let mut buf = Vec::new();
-    reader.read_until(b'\n', &mut buf).unwrap();
-    // Skip over `package $WORLD` line
-    reader.read_until(b'\n', &mut Vec::new()).unwrap();
-    buf.append(&mut "package main\n".as_bytes().to_vec());
 
     // check if {name}_types.go exists
     let types_file = dir.join(format!("{name}_types.go"));
-    if std::fs::metadata(types_file).is_ok() {
+    let has_types = std::fs::metadata(types_file).is_ok();
+    if has_types {
         // create a directory called option and move the type file to option
         std::fs::create_dir(dir.join("option")).expect("Failed to create directory");
         std::fs::rename(
@@ -77,12 +85,39 @@ fn verify(dir: &Path, name: &str) {
             dir.join("option").join(format!("{name}_types.go")),
         )
         .expect("Failed to move file");
-        buf.append(&mut format!("import . \"{name}/option\"\n").as_bytes().to_vec());
     }
 
-    reader.read_to_end(&mut buf).expect("Failed to read file");
-    buf.append(&mut "func main() {}".as_bytes().to_vec());
-    std::fs::write(&main, buf).expect("Failed to write to file");
+    for entry in std::fs::read_dir(dir).expect("failed to read directory") {
+        let path = entry.expect("failed to read directory entry").path();
+        if path.extension().is_some_and(|ext| ext == "go") {
+            let is_main = path == main;
+            let file = std::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(&path)
+                .expect("failed to open file");
+            let mut reader = BufReader::new(file);
+            let mut buf = Vec::new();
+            reader.read_until(b'\n', &mut buf).unwrap();
+            // Skip over `package $WORLD` line
+            reader.read_until(b'\n', &mut Vec::new()).unwrap();
+            buf.append(&mut "package main\n".as_bytes().to_vec());
+
+            let mut rest = Vec::new();
+            reader.read_to_end(&mut rest).expect("Failed to read file");
+            // Only import the option package where it's used, as Go doesn't
+            // allow unused imports.
+            let rest_str = String::from_utf8_lossy(&rest);
+            if has_types && (rest_str.contains("Option[") || rest_str.contains("Result[")) {
+                buf.append(&mut format!("import . \"{name}/option\"\n").as_bytes().to_vec());
+            }
+            buf.append(&mut rest);
+            if is_main {
+                buf.append(&mut "func main() {}".as_bytes().to_vec());
+            }
+            std::fs::write(&path, buf).expect("Failed to write to file");
+        }
+    }
 
     // create go.mod file
     let mod_file = dir.join("go.mod");
@@ -97,7 +132,7 @@ fn verify(dir: &Path, name: &str) {
     cmd.arg("-target=wasi");
     cmd.arg("-o");
     cmd.arg("go.wasm");
-    cmd.arg(format!("{name}.go"));
+    cmd.arg(".");
     cmd.current_dir(dir);
     test_helpers::run_command(&mut cmd);
 }
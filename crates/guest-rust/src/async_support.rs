@@ -0,0 +1,554 @@
+//! Runtime support for the `future` and `stream` types of the component model
+//! along with `async` exports.
+//!
+//! Futures and streams are passed across the component boundary as handles.
+//! Reading from or writing to one of these handles is done with intrinsics
+//! which may indicate that the operation could not complete immediately, in
+//! which case the operation's completion is later delivered as an event by the
+//! `[task-wait]` intrinsic. The types in this module wrap all of this up in
+//! Rust's [`Future`] and [`Stream`] traits.
+//!
+//! A small single-threaded executor is also provided to drive these futures.
+//! Generated `async` exports run on it via [`block_on`], and additional tasks
+//! may be started from within them with [`spawn`].
+//!
+//! This module is only available when the `async` feature of this crate is
+//! enabled.
+
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::vec::Vec;
+use core::alloc::Layout;
+#[cfg(not(target_arch = "wasm32"))]
+use core::cell::RefCell;
+#[cfg(target_arch = "wasm32")]
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::future::{poll_fn, Future};
+use core::mem::ManuallyDrop;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+pub use futures_core::Stream;
+
+mod executor;
+
+pub use executor::{block_on, spawn, yield_now, JoinHandle};
+
+/// Status returned by a read or write intrinsic when the operation could not
+/// be completed immediately.
+const BLOCKED: u32 = 0xffff_ffff;
+
+/// Bit set in a status when the other end of a future or stream was closed.
+const CLOSED: u32 = 0x8000_0000;
+
+const EVENT_STREAM_READ: i32 = 6;
+const EVENT_STREAM_WRITE: i32 = 7;
+const EVENT_FUTURE_READ: i32 = 8;
+const EVENT_FUTURE_WRITE: i32 = 9;
+
+/// Number of elements read from a stream at a time.
+const STREAM_READ_CAPACITY: usize = 64;
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T>>>;
+
+/// The intrinsics used to operate on a `future<T>` for one particular `T`.
+///
+/// This is filled in by generated code and is not intended to be constructed
+/// by hand.
+#[doc(hidden)]
+pub struct FutureVtable<T> {
+    /// Layout of `T` in linear memory.
+    pub layout: Layout,
+    /// Invokes the `[future-new-N]` intrinsic.
+    pub new: unsafe fn() -> u32,
+    /// Invokes the `[future-read-N]` intrinsic.
+    pub start_read: unsafe fn(future: u32, ptr: *mut u8) -> u32,
+    /// Invokes the `[future-write-N]` intrinsic.
+    pub start_write: unsafe fn(future: u32, ptr: *mut u8) -> u32,
+    /// Lifts a `T` out of linear memory at `ptr`.
+    pub lift: unsafe fn(ptr: *mut u8) -> T,
+    /// Lowers a `T` into linear memory at `ptr`.
+    pub lower: unsafe fn(value: T, ptr: *mut u8),
+    /// Invokes the `[future-close-readable-N]` intrinsic.
+    pub close_readable: unsafe fn(future: u32),
+    /// Invokes the `[future-close-writable-N]` intrinsic.
+    pub close_writable: unsafe fn(future: u32),
+}
+
+/// The intrinsics used to operate on a `stream<T>` for one particular `T`.
+///
+/// This is filled in by generated code and is not intended to be constructed
+/// by hand.
+#[doc(hidden)]
+pub struct StreamVtable<T> {
+    /// Layout of a single `T` in linear memory.
+    pub layout: Layout,
+    /// Invokes the `[stream-new-N]` intrinsic.
+    pub new: unsafe fn() -> u32,
+    /// Invokes the `[stream-read-N]` intrinsic.
+    pub start_read: unsafe fn(stream: u32, ptr: *mut u8, len: usize) -> u32,
+    /// Invokes the `[stream-write-N]` intrinsic.
+    pub start_write: unsafe fn(stream: u32, ptr: *mut u8, len: usize) -> u32,
+    /// Lifts a single `T` out of linear memory at `ptr`.
+    pub lift: unsafe fn(ptr: *mut u8) -> T,
+    /// Lowers a single `T` into linear memory at `ptr`.
+    pub lower: unsafe fn(value: T, ptr: *mut u8),
+    /// Invokes the `[stream-close-readable-N]` intrinsic.
+    pub close_readable: unsafe fn(stream: u32),
+    /// Invokes the `[stream-close-writable-N]` intrinsic.
+    pub close_writable: unsafe fn(stream: u32),
+}
+
+/// Creates a new future, returning its writable and readable ends.
+#[doc(hidden)]
+pub fn future_new<T>(vtable: &'static FutureVtable<T>) -> (FutureWriter<T>, FutureReader<T>) {
+    unsafe {
+        let handle = (vtable.new)();
+        (
+            FutureWriter::from_handle(handle, vtable),
+            FutureReader::from_handle(handle, vtable),
+        )
+    }
+}
+
+/// Creates a new stream, returning its writable and readable ends.
+#[doc(hidden)]
+pub fn stream_new<T>(vtable: &'static StreamVtable<T>) -> (StreamWriter<T>, StreamReader<T>) {
+    unsafe {
+        let handle = (vtable.new)();
+        (
+            StreamWriter::from_handle(handle, vtable),
+            StreamReader::from_handle(handle, vtable),
+        )
+    }
+}
+
+/// The readable end of a `future<T>`.
+///
+/// This implements [`Future`] and resolves to `Some(value)` once the writer
+/// has sent a value, or `None` if the writable end was closed without a value
+/// being sent.
+pub struct FutureReader<T: 'static> {
+    handle: u32,
+    vtable: &'static FutureVtable<T>,
+    read: Option<BoxFuture<Option<T>>>,
+}
+
+impl<T> FutureReader<T> {
+    #[doc(hidden)]
+    pub unsafe fn from_handle(handle: u32, vtable: &'static FutureVtable<T>) -> Self {
+        Self {
+            handle,
+            vtable,
+            read: None,
+        }
+    }
+
+    #[doc(hidden)]
+    pub fn into_handle(self) -> u32 {
+        assert!(self.read.is_none(), "cannot transfer a future being read");
+        ManuallyDrop::new(self).handle
+    }
+
+    #[doc(hidden)]
+    pub fn handle(&self) -> u32 {
+        self.handle
+    }
+}
+
+impl<T> Future for FutureReader<T> {
+    type Output = Option<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let me = self.get_mut();
+        let handle = me.handle;
+        let vtable = me.vtable;
+        let read = me.read.get_or_insert_with(|| {
+            Box::pin(async move {
+                let buffer = Buffer::new(vtable.layout);
+                let status = unsafe { (vtable.start_read)(handle, buffer.ptr) };
+                let status = wait(handle, EVENT_FUTURE_READ, status).await;
+                if status & !CLOSED == 0 {
+                    None
+                } else {
+                    Some(unsafe { (vtable.lift)(buffer.ptr) })
+                }
+            })
+        });
+        read.as_mut().poll(cx)
+    }
+}
+
+impl<T> Unpin for FutureReader<T> {}
+
+impl<T> fmt::Debug for FutureReader<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FutureReader")
+            .field("handle", &self.handle)
+            .finish()
+    }
+}
+
+impl<T> Drop for FutureReader<T> {
+    fn drop(&mut self) {
+        // Close the handle before any in-progress read is dropped to ensure
+        // that its buffer isn't written to after being deallocated.
+        unsafe {
+            (self.vtable.close_readable)(self.handle);
+        }
+        forget_completion(self.handle, EVENT_FUTURE_READ);
+        self.read = None;
+    }
+}
+
+/// The writable end of a `future<T>`.
+pub struct FutureWriter<T: 'static> {
+    handle: u32,
+    vtable: &'static FutureVtable<T>,
+}
+
+impl<T> FutureWriter<T> {
+    #[doc(hidden)]
+    pub unsafe fn from_handle(handle: u32, vtable: &'static FutureVtable<T>) -> Self {
+        Self { handle, vtable }
+    }
+
+    #[doc(hidden)]
+    pub fn into_handle(self) -> u32 {
+        ManuallyDrop::new(self).handle
+    }
+
+    #[doc(hidden)]
+    pub fn handle(&self) -> u32 {
+        self.handle
+    }
+
+    /// Sends `value` to the readable end of this future.
+    ///
+    /// Returns `true` if the value was received, or `false` if the readable
+    /// end was closed first.
+    pub async fn write(self, value: T) -> bool {
+        let buffer = Buffer::new(self.vtable.layout);
+        // Declared after `buffer` so the handle is closed before the buffer is
+        // deallocated if this write is cancelled.
+        let this = self;
+        let status = unsafe {
+            (this.vtable.lower)(value, buffer.ptr);
+            (this.vtable.start_write)(this.handle, buffer.ptr)
+        };
+        let status = wait(this.handle, EVENT_FUTURE_WRITE, status).await;
+        status & !CLOSED != 0
+    }
+}
+
+impl<T> fmt::Debug for FutureWriter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FutureWriter")
+            .field("handle", &self.handle)
+            .finish()
+    }
+}
+
+impl<T> Drop for FutureWriter<T> {
+    fn drop(&mut self) {
+        unsafe {
+            (self.vtable.close_writable)(self.handle);
+        }
+        forget_completion(self.handle, EVENT_FUTURE_WRITE);
+    }
+}
+
+/// The readable end of a `stream<T>`.
+///
+/// This implements [`Stream`], yielding each element written to the stream
+/// until the writable end is closed.
+pub struct StreamReader<T: 'static> {
+    handle: u32,
+    vtable: &'static StreamVtable<T>,
+    buffered: VecDeque<T>,
+    read: Option<BoxFuture<Option<Vec<T>>>>,
+}
+
+impl<T> StreamReader<T> {
+    #[doc(hidden)]
+    pub unsafe fn from_handle(handle: u32, vtable: &'static StreamVtable<T>) -> Self {
+        Self {
+            handle,
+            vtable,
+            buffered: VecDeque::new(),
+            read: None,
+        }
+    }
+
+    #[doc(hidden)]
+    pub fn into_handle(self) -> u32 {
+        assert!(
+            self.read.is_none() && self.buffered.is_empty(),
+            "cannot transfer a stream being read"
+        );
+        ManuallyDrop::new(self).handle
+    }
+
+    #[doc(hidden)]
+    pub fn handle(&self) -> u32 {
+        self.handle
+    }
+
+    /// Returns the next element of this stream, or `None` once the writable
+    /// end has been closed.
+    pub async fn next(&mut self) -> Option<T> {
+        poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+}
+
+impl<T> Stream for StreamReader<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let me = self.get_mut();
+        loop {
+            if let Some(value) = me.buffered.pop_front() {
+                return Poll::Ready(Some(value));
+            }
+            let handle = me.handle;
+            let vtable = me.vtable;
+            let read = me.read.get_or_insert_with(|| {
+                Box::pin(async move {
+                    let stride = vtable.layout.pad_to_align().size();
+                    let layout = Layout::from_size_align(
+                        stride * STREAM_READ_CAPACITY,
+                        vtable.layout.align(),
+                    )
+                    .unwrap();
+                    let buffer = Buffer::new(layout);
+                    let status =
+                        unsafe { (vtable.start_read)(handle, buffer.ptr, STREAM_READ_CAPACITY) };
+                    let status = wait(handle, EVENT_STREAM_READ, status).await;
+                    let count = (status & !CLOSED) as usize;
+                    if count == 0 && status & CLOSED != 0 {
+                        return None;
+                    }
+                    Some(
+                        (0..count)
+                            .map(|i| unsafe { (vtable.lift)(buffer.ptr.add(i * stride)) })
+                            .collect(),
+                    )
+                })
+            });
+            match read.as_mut().poll(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(None) => {
+                    me.read = None;
+                    return Poll::Ready(None);
+                }
+                Poll::Ready(Some(values)) => {
+                    me.read = None;
+                    me.buffered.extend(values);
+                }
+            }
+        }
+    }
+}
+
+impl<T> Unpin for StreamReader<T> {}
+
+impl<T> fmt::Debug for StreamReader<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamReader")
+            .field("handle", &self.handle)
+            .finish()
+    }
+}
+
+impl<T> Drop for StreamReader<T> {
+    fn drop(&mut self) {
+        unsafe {
+            (self.vtable.close_readable)(self.handle);
+        }
+        forget_completion(self.handle, EVENT_STREAM_READ);
+        self.read = None;
+    }
+}
+
+/// The writable end of a `stream<T>`.
+pub struct StreamWriter<T: 'static> {
+    handle: u32,
+    vtable: &'static StreamVtable<T>,
+}
+
+impl<T> StreamWriter<T> {
+    #[doc(hidden)]
+    pub unsafe fn from_handle(handle: u32, vtable: &'static StreamVtable<T>) -> Self {
+        Self { handle, vtable }
+    }
+
+    #[doc(hidden)]
+    pub fn into_handle(self) -> u32 {
+        ManuallyDrop::new(self).handle
+    }
+
+    #[doc(hidden)]
+    pub fn handle(&self) -> u32 {
+        self.handle
+    }
+
+    /// Writes all of `values` to this stream.
+    ///
+    /// Returns the number of values which were received, which is less than
+    /// `values.len()` only if the readable end was closed first.
+    pub async fn write(&mut self, values: Vec<T>) -> usize {
+        let len = values.len();
+        let stride = self.vtable.layout.pad_to_align().size();
+        let layout = Layout::from_size_align(stride * len, self.vtable.layout.align()).unwrap();
+        let buffer = Buffer::new(layout);
+        for (i, value) in values.into_iter().enumerate() {
+            unsafe { (self.vtable.lower)(value, buffer.ptr.add(i * stride)) }
+        }
+        let mut written = 0;
+        while written < len {
+            let status = unsafe {
+                (self.vtable.start_write)(
+                    self.handle,
+                    buffer.ptr.add(written * stride),
+                    len - written,
+                )
+            };
+            let status = wait(self.handle, EVENT_STREAM_WRITE, status).await;
+            written += (status & !CLOSED) as usize;
+            if status & CLOSED != 0 {
+                break;
+            }
+        }
+        written
+    }
+}
+
+impl<T> fmt::Debug for StreamWriter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamWriter")
+            .field("handle", &self.handle)
+            .finish()
+    }
+}
+
+impl<T> Drop for StreamWriter<T> {
+    fn drop(&mut self) {
+        unsafe {
+            (self.vtable.close_writable)(self.handle);
+        }
+        forget_completion(self.handle, EVENT_STREAM_WRITE);
+    }
+}
+
+/// A buffer in linear memory which a future or stream operation reads from or
+/// writes to.
+struct Buffer {
+    ptr: *mut u8,
+    layout: Layout,
+}
+
+impl Buffer {
+    fn new(layout: Layout) -> Buffer {
+        let ptr = if layout.size() == 0 {
+            layout.align() as *mut u8
+        } else {
+            let ptr = unsafe { alloc::alloc::alloc(layout) };
+            if ptr.is_null() {
+                alloc::alloc::handle_alloc_error(layout);
+            }
+            ptr
+        };
+        Buffer { ptr, layout }
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        if self.layout.size() != 0 {
+            unsafe { alloc::alloc::dealloc(self.ptr, self.layout) }
+        }
+    }
+}
+
+/// State of an operation which returned `BLOCKED`, keyed by the handle it was
+/// started on along with the event which signals its completion.
+#[derive(Default)]
+struct Completion {
+    waker: Option<Waker>,
+    status: Option<u32>,
+}
+
+#[cfg(target_arch = "wasm32")]
+struct Completions(UnsafeCell<BTreeMap<(u32, i32), Completion>>);
+
+// SAFETY: components are single-threaded, so this is never actually shared
+// between threads.
+#[cfg(target_arch = "wasm32")]
+unsafe impl Sync for Completions {}
+
+#[cfg(target_arch = "wasm32")]
+static COMPLETIONS: Completions = Completions(UnsafeCell::new(BTreeMap::new()));
+
+#[cfg(not(target_arch = "wasm32"))]
+std::thread_local! {
+    static COMPLETIONS: RefCell<BTreeMap<(u32, i32), Completion>> =
+        const { RefCell::new(BTreeMap::new()) };
+}
+
+#[cfg(target_arch = "wasm32")]
+fn with_completions<R>(f: impl FnOnce(&mut BTreeMap<(u32, i32), Completion>) -> R) -> R {
+    unsafe { f(&mut *COMPLETIONS.0.get()) }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn with_completions<R>(f: impl FnOnce(&mut BTreeMap<(u32, i32), Completion>) -> R) -> R {
+    COMPLETIONS.with(|completions| f(&mut completions.borrow_mut()))
+}
+
+/// Waits for the operation on `handle` which returned `status` to complete,
+/// returning its final status.
+async fn wait(handle: u32, event: i32, status: u32) -> u32 {
+    if status != BLOCKED {
+        return status;
+    }
+    poll_fn(|cx| {
+        with_completions(|completions| {
+            let completion = completions.entry((handle, event)).or_default();
+            match completion.status {
+                Some(status) => {
+                    completions.remove(&(handle, event));
+                    Poll::Ready(status)
+                }
+                None => {
+                    completion.waker = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        })
+    })
+    .await
+}
+
+fn forget_completion(handle: u32, event: i32) {
+    with_completions(|completions| completions.remove(&(handle, event)));
+}
+
+/// Records the completion of an operation reported by `[task-wait]` or
+/// `[task-poll]` and wakes the task waiting for it, if any.
+fn deliver_event(event: i32, payload: [u32; 2]) {
+    match event {
+        EVENT_FUTURE_READ | EVENT_FUTURE_WRITE | EVENT_STREAM_READ | EVENT_STREAM_WRITE => {
+            let [handle, status] = payload;
+            let waker = with_completions(|completions| {
+                let completion = completions.entry((handle, event)).or_default();
+                completion.status = Some(status);
+                completion.waker.take()
+            });
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }
+        _ => {}
+    }
+}
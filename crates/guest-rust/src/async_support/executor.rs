@@ -0,0 +1,457 @@
+//! A minimal single-threaded executor for driving `async` exports.
+//!
+//! Tasks are stored in a global table and identified by an index which is
+//! also used as the data pointer of their [`Waker`]. Waking a task simply
+//! marks it as ready to be polled again. When no task is ready the executor
+//! blocks on `[task-wait]`, and events delivered by the host wake whichever
+//! task was waiting on them.
+//!
+//! When not targeting wasm32, such as in native tests, each thread has its
+//! own executor and tasks must be woken on the thread running them.
+
+use super::{deliver_event, BoxFuture};
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::rc::Rc;
+use core::cell::RefCell;
+#[cfg(target_arch = "wasm32")]
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::future::Future;
+use core::pin::{pin, Pin};
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+/// Task identifier used for the future passed to [`block_on`].
+const MAIN_TASK: usize = usize::MAX;
+
+/// Event code returned by `[task-poll]` when no event is pending.
+const EVENT_NONE: i32 = 0;
+
+struct Executor {
+    /// Spawned tasks which have not yet completed.
+    ///
+    /// A task is removed from this map while it's being polled.
+    tasks: BTreeMap<usize, BoxFuture<()>>,
+    /// Tasks which have been woken and need to be polled, in the order they
+    /// were woken.
+    ready: VecDeque<usize>,
+    next_id: usize,
+    /// Whether `block_on` is currently running.
+    running: bool,
+}
+
+#[cfg(target_arch = "wasm32")]
+struct ExecutorCell(UnsafeCell<Executor>);
+
+// SAFETY: components are single-threaded, so this is never actually shared
+// between threads.
+#[cfg(target_arch = "wasm32")]
+unsafe impl Sync for ExecutorCell {}
+
+#[cfg(target_arch = "wasm32")]
+static EXECUTOR: ExecutorCell = ExecutorCell(UnsafeCell::new(Executor::new()));
+
+#[cfg(not(target_arch = "wasm32"))]
+std::thread_local! {
+    // Each thread gets its own executor, so tasks never move between threads.
+    static EXECUTOR: RefCell<Executor> = const { RefCell::new(Executor::new()) };
+}
+
+/// Provides temporary access to the global executor state.
+///
+/// The closure `f` must not poll any futures, as they may re-enter this
+/// function through their waker.
+#[cfg(target_arch = "wasm32")]
+fn with_executor<R>(f: impl FnOnce(&mut Executor) -> R) -> R {
+    unsafe { f(&mut *EXECUTOR.0.get()) }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn with_executor<R>(f: impl FnOnce(&mut Executor) -> R) -> R {
+    EXECUTOR.with(|executor| f(&mut executor.borrow_mut()))
+}
+
+impl Executor {
+    const fn new() -> Executor {
+        Executor {
+            tasks: BTreeMap::new(),
+            ready: VecDeque::new(),
+            next_id: 0,
+            running: false,
+        }
+    }
+
+    /// Marks task `id` as ready to be polled, after any other ready tasks.
+    fn wake(&mut self, id: usize) {
+        if !self.ready.contains(&id) {
+            self.ready.push_back(id);
+        }
+    }
+}
+
+/// Spawns `future` as a new task on the executor.
+///
+/// The task makes progress whenever the executor is running, which is while
+/// an `async` export is being driven by [`block_on`]. Tasks which haven't
+/// completed by the time that export returns are resumed the next time the
+/// executor runs.
+///
+/// The returned [`JoinHandle`] can be awaited to get the task's output.
+/// Dropping it detaches the task, which keeps running in the background.
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + 'static,
+{
+    let state = Rc::new(RefCell::new(JoinState {
+        output: None,
+        waker: None,
+    }));
+    let task_state = state.clone();
+    let task = Box::pin(async move {
+        let output = future.await;
+        let waker = {
+            let mut state = task_state.borrow_mut();
+            state.output = Some(output);
+            state.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    });
+    with_executor(|executor| {
+        let id = executor.next_id;
+        executor.next_id += 1;
+        executor.tasks.insert(id, task);
+        executor.wake(id);
+    });
+    JoinHandle { state }
+}
+
+/// Runs `future` to completion, along with any spawned tasks, blocking on
+/// `[task-wait]` whenever none of them can make progress.
+///
+/// This is used by generated bindings to implement `async` exports.
+///
+/// # Panics
+///
+/// Panics if called from within a future which is itself being driven by
+/// `block_on`.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let was_running = with_executor(|executor| {
+        executor.wake(MAIN_TASK);
+        core::mem::replace(&mut executor.running, true)
+    });
+    assert!(!was_running, "cannot call `block_on` recursively");
+    let _guard = RunningGuard;
+
+    let mut future = pin!(future);
+    let main_waker = task_waker(MAIN_TASK);
+    loop {
+        // Pick up any events which have already been delivered without
+        // blocking, so tasks waiting on them are polled in this round.
+        while poll_for_event() {}
+
+        while let Some(id) = with_executor(|executor| executor.ready.pop_front()) {
+            if id == MAIN_TASK {
+                let mut cx = Context::from_waker(&main_waker);
+                if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                    return output;
+                }
+            } else {
+                run_task(id);
+            }
+        }
+
+        wait_for_event();
+    }
+}
+
+/// Polls the spawned task `id` once, if it still exists.
+fn run_task(id: usize) {
+    let Some(mut task) = with_executor(|executor| executor.tasks.remove(&id)) else {
+        return;
+    };
+    let waker = task_waker(id);
+    let mut cx = Context::from_waker(&waker);
+    if task.as_mut().poll(&mut cx).is_pending() {
+        with_executor(|executor| executor.tasks.insert(id, task));
+    }
+}
+
+/// Resets the executor's `running` flag when `block_on` returns or unwinds.
+struct RunningGuard;
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        with_executor(|executor| {
+            executor.running = false;
+            executor.ready.retain(|id| *id != MAIN_TASK);
+        });
+    }
+}
+
+/// Yields to the executor, allowing other ready tasks to run before the
+/// current one continues.
+pub async fn yield_now() {
+    let mut yielded = false;
+    core::future::poll_fn(|cx| {
+        if yielded {
+            Poll::Ready(())
+        } else {
+            yielded = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    })
+    .await
+}
+
+/// A handle to a task created with [`spawn`].
+///
+/// This implements [`Future`], resolving to the task's output once it has
+/// completed.
+pub struct JoinHandle<T> {
+    state: Rc<RefCell<JoinState<T>>>,
+}
+
+struct JoinState<T> {
+    output: Option<T>,
+    waker: Option<Waker>,
+}
+
+impl<T> JoinHandle<T> {
+    /// Returns whether the task has completed and its output has not yet
+    /// been taken by awaiting this handle.
+    pub fn is_finished(&self) -> bool {
+        self.state.borrow().output.is_some()
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut state = self.state.borrow_mut();
+        match state.output.take() {
+            Some(output) => Poll::Ready(output),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T> Unpin for JoinHandle<T> {}
+
+impl<T> fmt::Debug for JoinHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JoinHandle")
+            .field("finished", &self.is_finished())
+            .finish()
+    }
+}
+
+/// Creates a waker which marks task `id` as ready when woken.
+fn task_waker(id: usize) -> Waker {
+    const VTABLE: RawWakerVTable =
+        RawWakerVTable::new(|data| RawWaker::new(data, &VTABLE), wake, wake, |_| {});
+
+    fn wake(data: *const ()) {
+        with_executor(|executor| executor.wake(data as usize));
+    }
+
+    unsafe { Waker::from_raw(RawWaker::new(id as *const (), &VTABLE)) }
+}
+
+/// Blocks until the next event is delivered and then wakes the task waiting
+/// for it, if any.
+fn wait_for_event() {
+    let mut payload = [0u32; 2];
+    let event = unsafe { task_wait(payload.as_mut_ptr().cast()) };
+    deliver_event(event, payload);
+}
+
+/// Delivers an already-pending event, if any, without blocking.
+///
+/// Returns whether an event was delivered.
+fn poll_for_event() -> bool {
+    let mut payload = [0u32; 2];
+    let event = unsafe { task_poll(payload.as_mut_ptr().cast()) };
+    if event == EVENT_NONE {
+        return false;
+    }
+    deliver_event(event, payload);
+    true
+}
+
+#[cfg(target_arch = "wasm32")]
+#[link(wasm_import_module = "$root")]
+extern "C" {
+    #[link_name = "[task-wait]"]
+    fn task_wait(results: *mut u8) -> i32;
+    #[link_name = "[task-poll]"]
+    fn task_poll(results: *mut u8) -> i32;
+}
+
+/// Outside of a component there's no host to deliver events, so a task
+/// could only be woken by another task, and none of them are ready.
+#[cfg(not(target_arch = "wasm32"))]
+unsafe fn task_wait(_results: *mut u8) -> i32 {
+    panic!(
+        "`block_on` can't make progress: all tasks are pending and events \
+         from the host are only delivered when running in a component"
+    )
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+unsafe fn task_poll(_results: *mut u8) -> i32 {
+    EVENT_NONE
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use alloc::vec::Vec;
+    use core::future::poll_fn;
+
+    fn run<F: Future>(future: F) -> F::Output {
+        block_on(future)
+    }
+
+    #[test]
+    fn block_on_returns_output() {
+        assert_eq!(run(async { 1 + 2 }), 3);
+    }
+
+    #[test]
+    fn spawned_tasks_are_joined() {
+        let output = run(async {
+            let a = spawn(async { "a" });
+            let b = spawn(async {
+                yield_now().await;
+                "b"
+            });
+            (b.await, a.await)
+        });
+        assert_eq!(output, ("b", "a"));
+    }
+
+    #[test]
+    fn tasks_run_in_spawn_order() {
+        let order = Rc::new(RefCell::new(Vec::new()));
+        run(async {
+            let handles = (0..3)
+                .map(|i| {
+                    let order = order.clone();
+                    spawn(async move { order.borrow_mut().push(i) })
+                })
+                .collect::<Vec<_>>();
+            assert!(!handles[0].is_finished());
+            for handle in handles {
+                handle.await;
+            }
+        });
+        assert_eq!(*order.borrow(), [0, 1, 2]);
+    }
+
+    #[test]
+    fn yield_now_runs_other_tasks_first() {
+        let order = Rc::new(RefCell::new(Vec::new()));
+        run(async {
+            let first = {
+                let order = order.clone();
+                spawn(async move {
+                    order.borrow_mut().push("first: start");
+                    yield_now().await;
+                    order.borrow_mut().push("first: end");
+                })
+            };
+            let second = {
+                let order = order.clone();
+                spawn(async move { order.borrow_mut().push("second") })
+            };
+            first.await;
+            second.await;
+        });
+        assert_eq!(*order.borrow(), ["first: start", "second", "first: end"]);
+    }
+
+    #[test]
+    fn woken_tasks_are_polled_again() {
+        let waker = Rc::new(RefCell::new(None::<Waker>));
+        let woken = Rc::new(RefCell::new(false));
+        let output = run(async {
+            let waiter = {
+                let (waker, woken) = (waker.clone(), woken.clone());
+                spawn(poll_fn(move |cx| {
+                    if *woken.borrow() {
+                        Poll::Ready(42)
+                    } else {
+                        *waker.borrow_mut() = Some(cx.waker().clone());
+                        Poll::Pending
+                    }
+                }))
+            };
+            yield_now().await;
+            assert!(!waiter.is_finished());
+
+            *woken.borrow_mut() = true;
+            waker.borrow_mut().take().unwrap().wake();
+            waiter.await
+        });
+        assert_eq!(output, 42);
+    }
+
+    #[test]
+    fn detached_tasks_resume_in_later_runs() {
+        let done = Rc::new(RefCell::new(false));
+        let mut handle = None;
+        run(async {
+            let done = done.clone();
+            handle = Some(spawn(async move {
+                yield_now().await;
+                yield_now().await;
+                *done.borrow_mut() = true;
+            }));
+        });
+        let handle = handle.unwrap();
+        assert!(!handle.is_finished());
+        run(async {
+            while !handle.is_finished() {
+                yield_now().await;
+            }
+        });
+        assert!(*done.borrow());
+    }
+
+    #[test]
+    fn block_on_is_not_reentrant() {
+        let result = std::panic::catch_unwind(|| block_on(async { block_on(async {}) }));
+        assert!(result.is_err());
+        // The executor is usable again afterwards.
+        assert_eq!(block_on(async { 1 }), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "`block_on` can't make progress")]
+    fn stalled_tasks_panic_natively() {
+        run(core::future::pending::<()>());
+    }
+
+    #[test]
+    fn threads_have_separate_executors() {
+        let mut handle = None;
+        run(async {
+            handle = Some(spawn(yield_now()));
+        });
+        let handle = handle.unwrap();
+        let other = std::thread::spawn(|| run(async { spawn(async { 1 }).await }));
+        assert_eq!(other.join().unwrap(), 1);
+        // The other thread didn't run this thread's task.
+        assert!(!handle.is_finished());
+        run(handle);
+    }
+}
@@ -2,6 +2,11 @@
 
 extern crate alloc;
 
+// Natively the async runtime keeps its state in thread-locals, since tests
+// may drive it from several threads at once.
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+extern crate std;
+
 use alloc::boxed::Box;
 use core::fmt;
 use core::marker;
@@ -11,6 +16,9 @@ use core::ops::{Deref, DerefMut};
 #[cfg(feature = "macros")]
 pub use wit_bindgen_rust_macro::*;
 
+#[cfg(feature = "async")]
+pub mod async_support;
+
 // Re-export `bitflags` so that we can reference it from macros.
 #[doc(hidden)]
 pub use bitflags;
@@ -22,6 +30,9 @@ pub mod rt {
 
     pub use crate::{Resource, RustResource, WasmResource};
 
+    #[cfg(feature = "async")]
+    pub use crate::async_support;
+
     /// Provide a hook for generated export functions to run static
     /// constructors at most once. wit-bindgen-rust generates a call to this
     /// function at the start of all component export functions. Importantly,
@@ -4,7 +4,7 @@ use pulldown_cmark::{html, Event, LinkType, Parser, Tag};
 use std::collections::HashMap;
 use std::fmt::Write;
 use wit_bindgen_core::{
-    uwriteln, wit_parser, Files, InterfaceGenerator as _, Source, WorldGenerator,
+    uwriteln, wit_parser, Files, InterfaceGenerator as _, Source, WitItem, WorldGenerator,
 };
 use wit_parser::*;
 
@@ -37,7 +37,11 @@ impl Opts {
 }
 
 impl WorldGenerator for Markdown {
-    fn preprocess(&mut self, resolve: &Resolve, world: WorldId) {
+    fn supports_async_types(&self) -> bool {
+        true
+    }
+
+    fn preprocess(&mut self, resolve: &Resolve, world: WorldId) -> Result<()> {
         self.sizes.fill(resolve);
 
         let world = &resolve.worlds[world];
@@ -109,6 +113,7 @@ impl WorldGenerator for Markdown {
             }
         }
         gen.push_str("\n");
+        Ok(())
     }
 
     fn import_interface(
@@ -201,6 +206,7 @@ impl WorldGenerator for Markdown {
     }
 
     fn finish(&mut self, resolve: &Resolve, world: WorldId, files: &mut Files) {
+        let source = WitItem::world(resolve, world);
         let world = &resolve.worlds[world];
         let parser = Parser::new(&self.src);
         let mut events = Vec::new();
@@ -226,7 +232,9 @@ impl WorldGenerator for Markdown {
             // Write the html output to an html file, and md output to a md file.
             files.push(&format!("{}.md", world.name), self.src.as_bytes());
             files.push(&format!("{}.html", world.name), html_output.as_bytes());
+            files.set_source(&format!("{}.html", world.name), source.clone());
         }
+        files.set_source(&format!("{}.md", world.name), source);
     }
 }
 
@@ -654,6 +662,52 @@ impl<'a> wit_bindgen_core::InterfaceGenerator<'a> for InterfaceGenerator<'a> {
         self.docs(docs);
     }
 
+    fn type_future(&mut self, _id: TypeId, name: &str, ty: &Option<Type>, docs: &Docs) {
+        self.print_type_header("type", name);
+        match ty {
+            Some(ty) => {
+                self.push_str("future<");
+                self.print_ty(ty);
+                self.push_str(">");
+            }
+            None => {
+                self.push_str("future");
+            }
+        }
+        self.push_str("\n<p>");
+        self.docs(docs);
+        self.push_str("\n");
+    }
+
+    fn type_stream(&mut self, _id: TypeId, name: &str, stream: &Stream, docs: &Docs) {
+        self.print_type_header("type", name);
+        match (stream.element, stream.end) {
+            (Some(element), Some(end)) => {
+                self.push_str("stream<");
+                self.print_ty(&element);
+                self.push_str(", ");
+                self.print_ty(&end);
+                self.push_str(">");
+            }
+            (None, Some(end)) => {
+                self.push_str("stream<_, ");
+                self.print_ty(&end);
+                self.push_str(">");
+            }
+            (Some(element), None) => {
+                self.push_str("stream<");
+                self.print_ty(&element);
+                self.push_str(">");
+            }
+            (None, None) => {
+                self.push_str("stream");
+            }
+        }
+        self.push_str("\n<p>");
+        self.docs(docs);
+        self.push_str("\n");
+    }
+
     fn type_alias(&mut self, _id: TypeId, name: &str, ty: &Type, docs: &Docs) {
         self.print_type_header("type", name);
         self.print_ty(ty);
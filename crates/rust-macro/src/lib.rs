@@ -1,12 +1,13 @@
 use proc_macro2::{Span, TokenStream};
 use quote::ToTokens;
 use std::collections::HashMap;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use syn::parse::{Error, Parse, ParseStream, Result};
 use syn::punctuated::Punctuated;
 use syn::{braced, token, Token};
-use wit_bindgen_core::wit_parser::{PackageId, Resolve, UnresolvedPackage, WorldId};
-use wit_bindgen_rust::{Opts, Ownership};
+use wit_bindgen_core::wit_parser::{Resolve, UnresolvedPackage, WorldId};
+use wit_bindgen_core::{push_deps, push_path, Parsed};
+use wit_bindgen_rust::{AsyncExports, Opts, Ownership};
 
 #[proc_macro]
 pub fn generate(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
@@ -34,6 +35,7 @@ impl Parse for Config {
         let mut opts = Opts::default();
         let mut world = None;
         let mut source = None;
+        let mut deps = Vec::new();
 
         if input.peek(token::Brace) {
             let content;
@@ -63,6 +65,7 @@ impl Parse for Config {
                     Opt::RawStrings => opts.raw_strings = true,
                     Opt::Ownership(ownership) => opts.ownership = ownership,
                     Opt::Skip(list) => opts.skip.extend(list.iter().map(|i| i.value())),
+                    Opt::Deps(list) => deps.extend(list.iter().map(|i| i.value())),
                     Opt::RuntimePath(path) => opts.runtime_path = Some(path.value()),
                     Opt::BitflagsPath(path) => opts.bitflags_path = Some(path.value()),
                     Opt::Exports(exports) => opts.exports.extend(
@@ -81,6 +84,24 @@ impl Parse for Config {
                             .collect()
                     }
                     Opt::With(with) => opts.with.extend(with),
+                    Opt::AsyncExports(async_exports) => opts.async_exports = async_exports,
+                    Opt::SkipComponentType => opts.skip_component_type = true,
+                    Opt::ExportMacro => opts.export_macro = true,
+                    Opt::PubExportMacro => {
+                        opts.export_macro = true;
+                        opts.pub_export_macro = true;
+                    }
+                    Opt::DefaultBindingsModule(module) => {
+                        opts.default_bindings_module = Some(module.value())
+                    }
+                    Opt::MockImports => opts.mock_imports = true,
+                    Opt::ComparisonDerives => opts.comparison_derives = true,
+                    Opt::Serde => opts.serde = true,
+                    Opt::SerdePath(path) => {
+                        opts.serde = true;
+                        opts.serde_path = Some(path.value())
+                    }
+                    Opt::MappedTypes(types) => opts.mapped_types.extend(types),
                 }
             }
         } else {
@@ -89,11 +110,8 @@ impl Parse for Config {
                 source = Some(Source::Path(input.parse::<syn::LitStr>()?.value()));
             }
         }
-        let (resolve, pkg, files) =
-            parse_source(&source).map_err(|err| Error::new(call_site, format!("{err:?}")))?;
-        let world = resolve
-            .select_world(pkg, world.as_deref())
-            .map_err(|e| Error::new(call_site, format!("{e:?}")))?;
+        let (resolve, world, files) = parse_source(&source, &deps, world.as_deref())
+            .map_err(|err| Error::new(call_site, format!("{err:?}")))?;
         Ok(Config {
             opts,
             resolve,
@@ -103,30 +121,33 @@ impl Parse for Config {
     }
 }
 
-fn parse_source(source: &Option<Source>) -> anyhow::Result<(Resolve, PackageId, Vec<PathBuf>)> {
+fn parse_source(
+    source: &Option<Source>,
+    deps: &[String],
+    world: Option<&str>,
+) -> anyhow::Result<(Resolve, WorldId, Vec<PathBuf>)> {
     let mut resolve = Resolve::default();
-    let mut files = Vec::new();
     let root = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
-    let mut parse = |path: &Path| -> anyhow::Result<_> {
-        if path.is_dir() {
-            let (pkg, sources) = resolve.push_dir(path)?;
-            files = sources;
-            Ok(pkg)
-        } else {
-            let pkg = UnresolvedPackage::parse_file(path)?;
-            files.extend(pkg.source_files().map(|s| s.to_owned()));
-            resolve.push(pkg)
-        }
-    };
-    let pkg = match source {
+    let deps = deps.iter().map(|dep| root.join(dep)).collect::<Vec<_>>();
+    let mut files = push_deps(&mut resolve, &deps)?;
+    let parsed = match source {
         Some(Source::Inline(s)) => {
-            resolve.push(UnresolvedPackage::parse("macro-input".as_ref(), s)?)?
+            Parsed::Package(resolve.push(UnresolvedPackage::parse("macro-input".as_ref(), s)?)?)
+        }
+        Some(Source::Path(s)) => {
+            let (parsed, sources) = push_path(&mut resolve, &root.join(s))?;
+            files.extend(sources);
+            parsed
+        }
+        None => {
+            let (parsed, sources) = push_path(&mut resolve, &root.join("wit"))?;
+            files.extend(sources);
+            parsed
         }
-        Some(Source::Path(s)) => parse(&root.join(s))?,
-        None => parse(&root.join("wit"))?,
     };
+    let world = parsed.select_world(&resolve, world)?;
 
-    Ok((resolve, pkg, files))
+    Ok((resolve, world, files))
 }
 
 impl Config {
@@ -140,13 +161,16 @@ impl Config {
         let src = std::str::from_utf8(src).unwrap();
         let mut contents = src.parse::<TokenStream>().unwrap();
 
-        // Include a dummy `include_str!` for any files we read so rustc knows that
-        // we depend on the contents of those files.
+        // Include a dummy `include_bytes!` for any files we read so rustc knows
+        // that we depend on the contents of those files.
         for file in self.files.iter() {
             contents.extend(
-                format!("const _: &str = include_str!(r#\"{}\"#);\n", file.display())
-                    .parse::<TokenStream>()
-                    .unwrap(),
+                format!(
+                    "const _: &[u8] = include_bytes!(r#\"{}\"#);\n",
+                    file.display()
+                )
+                .parse::<TokenStream>()
+                .unwrap(),
             );
         }
 
@@ -169,6 +193,17 @@ mod kw {
     syn::custom_keyword!(export_prefix);
     syn::custom_keyword!(additional_derives);
     syn::custom_keyword!(with);
+    syn::custom_keyword!(async_exports);
+    syn::custom_keyword!(skip_component_type);
+    syn::custom_keyword!(deps);
+    syn::custom_keyword!(export_macro);
+    syn::custom_keyword!(pub_export_macro);
+    syn::custom_keyword!(default_bindings_module);
+    syn::custom_keyword!(mock_imports);
+    syn::custom_keyword!(comparison_derives);
+    syn::custom_keyword!(serde);
+    syn::custom_keyword!(serde_path);
+    syn::custom_keyword!(mapped_types);
 }
 
 #[derive(Clone)]
@@ -220,6 +255,7 @@ enum Opt {
     UseStdFeature,
     RawStrings,
     Skip(Vec<syn::LitStr>),
+    Deps(Vec<syn::LitStr>),
     Ownership(Ownership),
     RuntimePath(syn::LitStr),
     BitflagsPath(syn::LitStr),
@@ -229,6 +265,16 @@ enum Opt {
     // Parse as paths so we can take the concrete types/macro names rather than raw strings
     AdditionalDerives(Vec<syn::Path>),
     With(HashMap<String, String>),
+    AsyncExports(AsyncExports),
+    SkipComponentType,
+    ExportMacro,
+    PubExportMacro,
+    DefaultBindingsModule(syn::LitStr),
+    MockImports,
+    ComparisonDerives,
+    Serde,
+    SerdePath(syn::LitStr),
+    MappedTypes(HashMap<String, String>),
 }
 
 impl Parse for Opt {
@@ -304,6 +350,13 @@ impl Parse for Opt {
             syn::bracketed!(contents in input);
             let list = Punctuated::<_, Token![,]>::parse_terminated(&contents)?;
             Ok(Opt::Skip(list.iter().cloned().collect()))
+        } else if l.peek(kw::deps) {
+            input.parse::<kw::deps>()?;
+            input.parse::<Token![:]>()?;
+            let contents;
+            syn::bracketed!(contents in input);
+            let list = Punctuated::<_, Token![,]>::parse_terminated(&contents)?;
+            Ok(Opt::Deps(list.iter().cloned().collect()))
         } else if l.peek(kw::runtime_path) {
             input.parse::<kw::runtime_path>()?;
             input.parse::<Token![:]>()?;
@@ -334,6 +387,68 @@ impl Parse for Opt {
             let fields: Punctuated<_, Token![,]> =
                 contents.parse_terminated(with_field_parse, Token![,])?;
             Ok(Opt::With(HashMap::from_iter(fields.into_iter())))
+        } else if l.peek(kw::async_exports) {
+            input.parse::<kw::async_exports>()?;
+            input.parse::<Token![:]>()?;
+            if input.peek(token::Bracket) {
+                let contents;
+                syn::bracketed!(contents in input);
+                let list = Punctuated::<syn::LitStr, Token![,]>::parse_terminated(&contents)?;
+                Ok(Opt::AsyncExports(AsyncExports::Some(
+                    list.iter().map(|s| s.value()).collect(),
+                )))
+            } else {
+                let async_exports = input.parse::<syn::Ident>()?;
+                Ok(Opt::AsyncExports(
+                    match async_exports.to_string().as_str() {
+                        "all" => AsyncExports::All,
+                        "none" => AsyncExports::None,
+                        name => {
+                            return Err(Error::new(
+                                async_exports.span(),
+                                format!(
+                                    "unrecognized async exports: `{name}`; \
+                                 expected `all`, `none`, or a list of function names"
+                                ),
+                            ));
+                        }
+                    },
+                ))
+            }
+        } else if l.peek(kw::skip_component_type) {
+            input.parse::<kw::skip_component_type>()?;
+            Ok(Opt::SkipComponentType)
+        } else if l.peek(kw::export_macro) {
+            input.parse::<kw::export_macro>()?;
+            Ok(Opt::ExportMacro)
+        } else if l.peek(kw::pub_export_macro) {
+            input.parse::<kw::pub_export_macro>()?;
+            Ok(Opt::PubExportMacro)
+        } else if l.peek(kw::default_bindings_module) {
+            input.parse::<kw::default_bindings_module>()?;
+            input.parse::<Token![:]>()?;
+            Ok(Opt::DefaultBindingsModule(input.parse()?))
+        } else if l.peek(kw::mock_imports) {
+            input.parse::<kw::mock_imports>()?;
+            Ok(Opt::MockImports)
+        } else if l.peek(kw::comparison_derives) {
+            input.parse::<kw::comparison_derives>()?;
+            Ok(Opt::ComparisonDerives)
+        } else if l.peek(kw::serde) {
+            input.parse::<kw::serde>()?;
+            Ok(Opt::Serde)
+        } else if l.peek(kw::serde_path) {
+            input.parse::<kw::serde_path>()?;
+            input.parse::<Token![:]>()?;
+            Ok(Opt::SerdePath(input.parse()?))
+        } else if l.peek(kw::mapped_types) {
+            input.parse::<kw::mapped_types>()?;
+            input.parse::<Token![:]>()?;
+            let contents;
+            let _lbrace = braced!(contents in input);
+            let fields: Punctuated<_, Token![,]> =
+                contents.parse_terminated(with_field_parse, Token![,])?;
+            Ok(Opt::MappedTypes(HashMap::from_iter(fields)))
         } else {
             Err(l.error())
         }
@@ -15,6 +15,9 @@ pub(super) struct FunctionBindgen<'a, 'b> {
     block_storage: Vec<(Source, Vec<(String, String)>)>,
     tmp: usize,
     pub needs_cleanup_list: bool,
+    /// Whether custom types converted for lowering are kept alive in
+    /// `mapped_values` until the call returns.
+    pub needs_mapped_values: bool,
     cleanup: Vec<(String, String)>,
     pub import_return_pointer_area_size: usize,
     pub import_return_pointer_area_align: usize,
@@ -33,6 +36,7 @@ impl<'a, 'b> FunctionBindgen<'a, 'b> {
             block_storage: Vec::new(),
             tmp: 0,
             needs_cleanup_list: false,
+            needs_mapped_values: false,
             cleanup: Vec::new(),
             import_return_pointer_area_size: 0,
             import_return_pointer_area_align: 0,
@@ -284,11 +288,57 @@ impl Bindgen for FunctionBindgen<'_, '_> {
             return false;
         }
         match ty {
-            Type::Id(id) => !self.gen.gen.types.get(*id).has_resource,
+            Type::Id(id) => {
+                !self.gen.gen.types.get(*id).has_resource && !self.gen.contains_mapped(ty)
+            }
             _ => true,
         }
     }
 
+    fn before_lower(
+        &mut self,
+        _resolve: &Resolve,
+        ty: TypeId,
+        realloc: Option<&str>,
+        operand: &mut String,
+    ) {
+        let Some(custom) = self.gen.mapped_type(ty) else {
+            return;
+        };
+        let wit = self.gen.mapped_wit_type(ty);
+        let root = self.gen.path_to_root();
+        let tmp = self.tmp();
+        uwriteln!(
+            self.src,
+            "let mapped{tmp} = <{custom} as {root}MappedType<{wit}>>::to_wit(\
+                ::core::borrow::Borrow::<{custom}>::borrow(&{operand}));"
+        );
+        *operand = if realloc.is_none() {
+            // Lowered values are only borrowed by the call, so the converted
+            // value must outlive the block it's converted in.
+            self.needs_mapped_values = true;
+            uwriteln!(
+                self.src,
+                "let mapped{tmp} = {rt}::boxed::Box::new(mapped{tmp});
+                let ptr{tmp}: *const {wit} = &*mapped{tmp};
+                mapped_values.push(mapped{tmp});",
+                rt = self.gen.gen.runtime_path(),
+            );
+            format!("(&*ptr{tmp})")
+        } else {
+            format!("mapped{tmp}")
+        };
+    }
+
+    fn after_lift(&mut self, _resolve: &Resolve, ty: TypeId, operand: &mut String) {
+        let Some(custom) = self.gen.mapped_type(ty) else {
+            return;
+        };
+        let wit = self.gen.mapped_wit_type(ty);
+        let root = self.gen.path_to_root();
+        *operand = format!("<{custom} as {root}MappedType<{wit}>>::from_wit({operand})");
+    }
+
     fn emit(
         &mut self,
         resolve: &Resolve,
@@ -457,6 +507,43 @@ impl Bindgen for FunctionBindgen<'_, '_> {
                 );
             }
 
+            Instruction::FutureLower { .. } | Instruction::StreamLower { .. } => {
+                let op = &operands[0];
+                results.push(format!("({op}).into_handle() as i32"))
+            }
+
+            Instruction::FutureLift { payload, .. } => {
+                let op = &operands[0];
+                let rt = self.gen.gen.runtime_path().to_string();
+                let path_to_root = self.gen.path_to_root();
+                let payload = match payload {
+                    Some(ty) => self.gen.type_name_owned(ty),
+                    None => "()".to_string(),
+                };
+                results.push(format!(
+                    "{rt}::async_support::FutureReader::from_handle(\
+                        {op} as u32, \
+                        <{payload} as {path_to_root}wit_future::FuturePayload>::VTABLE,\
+                    )"
+                ));
+            }
+
+            Instruction::StreamLift { stream, .. } => {
+                let op = &operands[0];
+                let rt = self.gen.gen.runtime_path().to_string();
+                let path_to_root = self.gen.path_to_root();
+                let payload = match &stream.element {
+                    Some(ty) => self.gen.type_name_owned(ty),
+                    None => "()".to_string(),
+                };
+                results.push(format!(
+                    "{rt}::async_support::StreamReader::from_handle(\
+                        {op} as u32, \
+                        <{payload} as {path_to_root}wit_stream::StreamPayload>::VTABLE,\
+                    )"
+                ));
+            }
+
             Instruction::RecordLower { ty, record, .. } => {
                 self.record_lower(*ty, record, &operands[0], results);
             }
@@ -802,41 +889,79 @@ impl Bindgen for FunctionBindgen<'_, '_> {
 
             Instruction::CallInterface { func, .. } => {
                 self.let_results(func.results.len(), results);
+                // Async exports are driven to completion before returning.
+                let async_ = self.gen.gen.opts.async_exports.is_async(&func.name);
+                let block_on = if async_ {
+                    self.gen.gen.uses_executor = true;
+                    format!(
+                        "{rt}::async_support::block_on(",
+                        rt = self.gen.gen.runtime_path()
+                    )
+                } else {
+                    String::new()
+                };
+                // With an `export!` macro resources are implemented by the
+                // associated types of `Guest`, and wrapped in the type-erased
+                // `{camel}` when constructed.
+                let export_macro = self.gen.gen.opts.export_macro;
+                let resource_impl = |camel: &str| {
+                    if export_macro {
+                        format!("_GuestImpl::{camel}")
+                    } else {
+                        format!("_{camel}Impl")
+                    }
+                };
                 match &func.kind {
                     FunctionKind::Freestanding => {
                         self.push_str(&format!(
-                            "<_GuestImpl as Guest>::{}",
+                            "{block_on}<_GuestImpl as Guest>::{}",
                             to_rust_ident(&func.name)
                         ));
                     }
                     FunctionKind::Method(ty) | FunctionKind::Static(ty) => {
+                        let camel = resolve.types[*ty]
+                            .name
+                            .as_deref()
+                            .unwrap()
+                            .to_upper_camel_case();
+                        let resource_impl = resource_impl(&camel);
+                        if export_macro && matches!(func.kind, FunctionKind::Method(_)) {
+                            operands[0] = format!("({}).get::<{resource_impl}>()", operands[0]);
+                        }
                         self.push_str(&format!(
-                            "<_{0}Impl as Guest{0}>::{1}",
-                            resolve.types[*ty]
-                                .name
-                                .as_deref()
-                                .unwrap()
-                                .to_upper_camel_case(),
+                            "{block_on}<{resource_impl} as Guest{camel}>::{}",
                             to_rust_ident(func.item_name())
                         ));
                     }
                     FunctionKind::Constructor(ty) => {
                         self.gen.mark_resource_owned(*ty);
+                        let camel = resolve.types[*ty]
+                            .name
+                            .as_deref()
+                            .unwrap()
+                            .to_upper_camel_case();
+                        let resource_impl = resource_impl(&camel);
+                        let wrap = if export_macro {
+                            format!("{camel}::new(")
+                        } else {
+                            String::new()
+                        };
                         self.push_str(&format!(
-                            "Own{0}::new(<_{0}Impl as Guest{0}>::new",
-                            resolve.types[*ty]
-                                .name
-                                .as_deref()
-                                .unwrap()
-                                .to_upper_camel_case()
+                            "Own{camel}::new({wrap}{block_on}<{resource_impl} as Guest{camel}>::new"
                         ));
                     }
                 }
                 self.push_str("(");
                 self.push_str(&operands.join(", "));
                 self.push_str(")");
+                if async_ {
+                    self.push_str(")");
+                }
                 if let FunctionKind::Constructor(_) = &func.kind {
                     self.push_str(")");
+                    if export_macro {
+                        self.push_str(")");
+                    }
                 }
                 self.push_str(";\n");
             }
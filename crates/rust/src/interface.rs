@@ -1,7 +1,8 @@
 use crate::bindgen::FunctionBindgen;
 use crate::{
-    dealias, int_repr, to_rust_ident, to_upper_camel_case, wasm_type, Direction, ExportKey, FnSig,
-    Identifier, InterfaceName, Ownership, RustFlagsRepr, RustWasm, TypeMode,
+    dealias, int_repr, mapped_type_keys, to_rust_ident, to_upper_camel_case, wasm_type,
+    AsyncPayload, Direction, ExportKey, FnSig, Identifier, InterfaceName, Ownership, RustFlagsRepr,
+    RustWasm, TypeMode,
 };
 use anyhow::Result;
 use heck::*;
@@ -9,7 +10,18 @@ use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Write as _;
 use std::mem;
 use wit_bindgen_core::abi::{self, AbiVariant, LiftLower};
-use wit_bindgen_core::{uwrite, uwriteln, wit_parser::*, Source, TypeInfo};
+use wit_bindgen_core::{uwrite, uwriteln, wit_parser::*, CoreImport, Source, TypeInfo, WitItem};
+
+/// A trait of exported functions generated by `generate_exports`, either
+/// `Guest` or one per exported resource.
+struct ExportTrait {
+    name: String,
+    local_impl_name: String,
+    export_key: ExportKey,
+    item: WitItem,
+    methods: Vec<String>,
+    has_async: bool,
+}
 
 pub struct InterfaceGenerator<'a> {
     pub src: Source,
@@ -75,12 +87,47 @@ impl InterfaceGenerator<'_> {
         }
     }
 
+    /// Returns the WIT item which an export trait is generated for, either
+    /// this interface (or world) or the resource `resource` within it.
+    fn export_item(&self, resource: Option<&str>) -> WitItem {
+        match (&self.identifier, resource) {
+            (Identifier::World(world), None) => WitItem::world(self.resolve, *world),
+            (Identifier::Interface(_, key), None) => WitItem::interface(self.resolve, key),
+            (identifier, Some(name)) => WitItem::Type {
+                interface: match identifier {
+                    Identifier::World(_) => None,
+                    Identifier::Interface(_, key) => Some(self.resolve.name_world_key(key)),
+                },
+                name: name.to_string(),
+            },
+        }
+    }
+
     pub(super) fn generate_exports<'a>(
         &mut self,
         funcs: impl Iterator<Item = &'a Function> + Clone,
     ) -> Result<()> {
         let mut traits = BTreeMap::new();
 
+        // With an `export!` macro the types implementing exported resources
+        // are only known once it's invoked, so they're associated types of
+        // the `Guest` trait which the macro is invoked with.
+        if self.gen.opts.export_macro {
+            if let Identifier::Interface(id, _) = self.identifier {
+                for ty in self.resolve.interfaces[id].types.values() {
+                    let ty = &self.resolve.types[*ty];
+                    if !matches!(ty.kind, TypeDefKind::Resource) {
+                        continue;
+                    }
+                    let name = ty.name.as_deref().unwrap();
+                    let camel = name.to_upper_camel_case();
+                    let (_, guest) = self.export_trait(&mut traits, None);
+                    guest.push(format!("type {camel}: Guest{camel};\n"));
+                    self.export_trait(&mut traits, Some(name));
+                }
+            }
+        }
+
         for func in funcs {
             if self.gen.skip.contains(&func.name) {
                 continue;
@@ -88,36 +135,28 @@ impl InterfaceGenerator<'_> {
 
             // First generate the exported function which performs lift/lower
             // operations and delegates to a trait (that doesn't exist just yet).
-            self.src.push_str("const _: () = {\n");
-            self.generate_guest_export(func);
-            self.src.push_str("};\n");
+            //
+            // With an `export!` macro this is a generic function which the
+            // macro instantiates instead, so it needs to be nameable.
+            if self.gen.opts.export_macro {
+                self.generate_guest_export(func);
+            } else {
+                self.src.push_str("const _: () = {\n");
+                self.generate_guest_export(func);
+                self.src.push_str("};\n");
+            }
 
             // Next generate a trait signature for this method and insert it
             // into `traits`. Note that `traits` will have a trait-per-resource.
-            let (trait_name, local_impl_name, export_key) = match func.kind {
-                FunctionKind::Freestanding => (
-                    "Guest".to_string(),
-                    "_GuestImpl".to_string(),
-                    self.export_key(None),
-                ),
+            let resource = match func.kind {
+                FunctionKind::Freestanding => None,
                 FunctionKind::Method(id)
                 | FunctionKind::Constructor(id)
-                | FunctionKind::Static(id) => {
-                    let resource_name = self.resolve.types[id].name.as_deref().unwrap();
-                    let camel = resource_name.to_upper_camel_case();
-                    let trait_name = format!("Guest{camel}");
-                    let export_key = self.export_key(Some(&resource_name));
-                    let local_impl_name = format!("_{camel}Impl");
-                    (trait_name, local_impl_name, export_key)
-                }
+                | FunctionKind::Static(id) => self.resolve.types[id].name.as_deref(),
             };
-
-            let (_, _, methods) =
-                traits
-                    .entry(export_key)
-                    .or_insert((trait_name, local_impl_name, Vec::new()));
             let prev = mem::take(&mut self.src);
             let mut sig = FnSig {
+                async_: self.gen.opts.async_exports.is_async(&func.name),
                 use_item_name: true,
                 private: true,
                 ..Default::default()
@@ -129,7 +168,9 @@ impl InterfaceGenerator<'_> {
             self.print_signature(func, TypeMode::Owned, &sig);
             self.src.push_str(";\n");
             let trait_method = mem::replace(&mut self.src, prev);
-            methods.push(trait_method);
+            let (has_async, methods) = self.export_trait(&mut traits, resource);
+            *has_async |= sig.async_;
+            methods.push(trait_method.into());
         }
 
         // Once all the traits have been assembled then they can be emitted.
@@ -137,16 +178,30 @@ impl InterfaceGenerator<'_> {
         // Additionally alias the user-configured item for each trait here as
         // there's only one implementation of this trait and it must be
         // pre-configured.
-        for (export_key, (trait_name, local_impl_name, methods)) in traits {
-            let impl_name = self.gen.lookup_export(&export_key)?;
-            let path_to_root = self.path_to_root();
-            uwriteln!(
-                self.src,
-                "use {path_to_root}{impl_name} as {local_impl_name};"
-            );
+        for (_, trait_) in traits {
+            // Implementations are only known once `export!` is invoked.
+            if !self.gen.opts.export_macro {
+                let impl_name = self.gen.lookup_export(&trait_.export_key, trait_.item)?;
+                let path_to_root = self.path_to_root();
+                uwriteln!(
+                    self.src,
+                    "use {path_to_root}{impl_name} as {};",
+                    trait_.local_impl_name
+                );
+            }
 
-            uwriteln!(self.src, "pub trait {trait_name} {{");
-            for method in methods {
+            if trait_.has_async {
+                uwriteln!(self.src, "#[allow(async_fn_in_trait)]");
+            }
+            // Resource implementations are type-erased through `Any` when
+            // exported through the `export!` macro.
+            let bounds = if self.gen.opts.export_macro && trait_.name != "Guest" {
+                ": 'static"
+            } else {
+                ""
+            };
+            uwriteln!(self.src, "pub trait {}{bounds} {{", trait_.name);
+            for method in trait_.methods {
                 self.src.push_str(&method);
             }
             uwriteln!(self.src, "}}");
@@ -155,10 +210,213 @@ impl InterfaceGenerator<'_> {
         Ok(())
     }
 
+    /// Returns the `has_async` flag and methods of the trait implemented
+    /// for `resource`, or for the freestanding functions if `None`, adding it
+    /// to `traits` if it isn't there yet.
+    fn export_trait<'b>(
+        &self,
+        traits: &'b mut BTreeMap<ExportKey, ExportTrait>,
+        resource: Option<&str>,
+    ) -> (&'b mut bool, &'b mut Vec<String>) {
+        let export_key = self.export_key(resource);
+        let trait_ = traits.entry(export_key.clone()).or_insert_with(|| {
+            let (name, local_impl_name) = match resource {
+                Some(resource) => {
+                    let camel = resource.to_upper_camel_case();
+                    (format!("Guest{camel}"), format!("_{camel}Impl"))
+                }
+                None => ("Guest".to_string(), "_GuestImpl".to_string()),
+            };
+            ExportTrait {
+                name,
+                local_impl_name,
+                export_key,
+                item: self.export_item(resource),
+                methods: Vec::new(),
+                has_async: false,
+            }
+        });
+        (&mut trait_.has_async, &mut trait_.methods)
+    }
+
     pub fn generate_imports<'a>(&mut self, funcs: impl Iterator<Item = &'a Function>) {
+        let mut mock_methods = Vec::new();
         for func in funcs {
             self.generate_guest_import(func);
+            if self.is_mocked(func) {
+                mock_methods.push(self.generate_mock_import(func));
+            }
+        }
+        if self.gen.opts.mock_imports {
+            // Handles of mocked resources are dropped through the mock too.
+            for ty in self.defined_resources() {
+                let name = self.resolve.types[ty].name.as_deref().unwrap();
+                mock_methods.push(format!(
+                    "
+                        /// Called when a handle to a `{name}` is dropped.
+                        fn {}_drop(&self, _handle: u32) {{}}
+                    ",
+                    name.to_snake_case()
+                ));
+            }
+        }
+        if !mock_methods.is_empty() {
+            self.generate_mock_registry(&mock_methods);
+        }
+    }
+
+    /// Returns the resources defined by this interface, or imported by this
+    /// world.
+    fn defined_resources(&self) -> Vec<TypeId> {
+        let types = match self.identifier {
+            Identifier::Interface(id, _) => self.resolve.interfaces[id]
+                .types
+                .values()
+                .copied()
+                .collect(),
+            Identifier::World(world) => self.resolve.worlds[world]
+                .imports
+                .values()
+                .filter_map(|item| match item {
+                    WorldItem::Type(ty) => Some(*ty),
+                    _ => None,
+                })
+                .collect::<Vec<_>>(),
+        };
+        types
+            .into_iter()
+            .filter(|ty| matches!(self.resolve.types[*ty].kind, TypeDefKind::Resource))
+            .collect()
+    }
+
+    /// Returns whether `func` is routed through the `Mock` trait when not
+    /// targeting wasm32.
+    fn is_mocked(&self, func: &Function) -> bool {
+        self.gen.opts.mock_imports && !self.gen.skip.contains(&func.name)
+    }
+
+    /// Generates the native version of the import `func`, which forwards to
+    /// the installed `Mock`, and returns the signature of the corresponding
+    /// `Mock` method.
+    ///
+    /// Functions of resources are `Mock` methods prefixed with the resource's
+    /// name, which are passed the handle of `self` and return the handle of
+    /// a constructed resource.
+    fn generate_mock_import(&mut self, func: &Function) -> String {
+        let param_mode = TypeMode::AllBorrowed("'_");
+        let mut sig = FnSig::default();
+        let mut mock_sig = FnSig {
+            private: true,
+            self_arg: Some("&self".into()),
+            ..Default::default()
+        };
+        let mut mock_name = to_rust_ident(&func.name);
+        match func.kind {
+            FunctionKind::Freestanding => {}
+            FunctionKind::Method(id) | FunctionKind::Static(id) | FunctionKind::Constructor(id) => {
+                let name = self.resolve.types[id].name.as_deref().unwrap();
+                uwriteln!(self.src, "impl {} {{", to_upper_camel_case(name));
+                sig.use_item_name = true;
+                let item = match func.kind {
+                    FunctionKind::Constructor(_) => "new",
+                    _ => func.item_name(),
+                };
+                mock_name = to_rust_ident(&format!(
+                    "{}_{}",
+                    name.to_snake_case(),
+                    item.to_snake_case()
+                ));
+                mock_sig.name = Some(mock_name.clone());
+                if let FunctionKind::Method(_) = func.kind {
+                    sig.self_arg = Some("&self".into());
+                    sig.self_is_first_param = true;
+                    mock_sig.self_arg = Some("&self, self_: u32".into());
+                    mock_sig.self_is_first_param = true;
+                }
+            }
         }
+        self.src
+            .push_str("#[cfg(not(target_arch = \"wasm32\"))]\n#[allow(clippy::all)]\n");
+        let mut params = self.print_signature(func, param_mode, &sig);
+        if let FunctionKind::Method(_) = func.kind {
+            params[0] = "self.handle()".to_string();
+        }
+        let call = format!("Mock::{mock_name}(&*__mock(), {})", params.join(", "));
+        match func.kind {
+            FunctionKind::Constructor(_) => {
+                uwriteln!(self.src, "{{ unsafe {{ Self::from_handle({call}) }} }}")
+            }
+            _ => uwriteln!(self.src, "{{ {call} }}"),
+        }
+        if func.kind != FunctionKind::Freestanding {
+            self.src.push_str("}\n");
+        }
+
+        let prev = mem::take(&mut self.src);
+        if let FunctionKind::Constructor(_) = func.kind {
+            self.print_docs_and_params(func, param_mode, &mock_sig);
+            self.push_str(" -> u32");
+        } else {
+            self.print_signature(func, param_mode, &mock_sig);
+        }
+        self.src.push_str(";\n");
+        mem::replace(&mut self.src, prev).into()
+    }
+
+    /// Generates the `Mock` trait with `methods` along with the thread-local
+    /// registry which native versions of imports forward to.
+    fn generate_mock_registry(&mut self, methods: &[String]) {
+        let name = match self.identifier {
+            Identifier::Interface(_, key) => self.resolve.name_world_key(key),
+            Identifier::World(_) => "$root".to_string(),
+        };
+        uwriteln!(
+            self.src,
+            "
+                /// Implementation of the imported functions of `{name}` used
+                /// when not targeting wasm32, for example in `cargo test`.
+                ///
+                /// Install one on the current thread with [`set_mock`].
+                #[cfg(not(target_arch = \"wasm32\"))]
+                pub trait Mock {{
+            "
+        );
+        for method in methods {
+            self.src.push_str(method);
+        }
+        uwriteln!(
+            self.src,
+            "
+                }}
+
+                #[cfg(not(target_arch = \"wasm32\"))]
+                ::std::thread_local! {{
+                    static __MOCK: ::core::cell::RefCell<Option<::std::rc::Rc<dyn Mock>>> =
+                        ::core::cell::RefCell::new(None);
+                }}
+
+                /// Installs `mock` as the implementation of the imported
+                /// functions of `{name}` on the current thread, returning the
+                /// previously installed one.
+                #[cfg(not(target_arch = \"wasm32\"))]
+                pub fn set_mock(mock: impl Mock + 'static) -> Option<::std::rc::Rc<dyn Mock>> {{
+                    __MOCK.with(|m| m.borrow_mut().replace(::std::rc::Rc::new(mock)))
+                }}
+
+                /// Removes the mock installed on the current thread, if any.
+                #[cfg(not(target_arch = \"wasm32\"))]
+                pub fn clear_mock() -> Option<::std::rc::Rc<dyn Mock>> {{
+                    __MOCK.with(|m| m.borrow_mut().take())
+                }}
+
+                #[cfg(not(target_arch = \"wasm32\"))]
+                fn __mock() -> ::std::rc::Rc<dyn Mock> {{
+                    __MOCK
+                        .with(|m| m.borrow().clone())
+                        .expect(\"no mock installed for the imports of `{name}` on this thread\")
+                }}
+            "
+        );
     }
 
     pub fn finish(&mut self) -> String {
@@ -182,7 +440,26 @@ impl InterfaceGenerator<'_> {
         mem::take(&mut self.src).into()
     }
 
-    fn path_to_root(&self) -> String {
+    /// Returns the path of this interface's module relative to the root of
+    /// the bindings, ending in `::` unless empty.
+    fn path_from_root(&self) -> String {
+        match self.identifier {
+            Identifier::World(_) => String::new(),
+            Identifier::Interface(_, key) => {
+                // The innermost module is named after the identifier of the
+                // interface, as in `start_append_submodule`.
+                let mut path = crate::compute_module_path(key, self.resolve, !self.in_import);
+                let name = match key {
+                    WorldKey::Name(name) => name,
+                    WorldKey::Interface(id) => self.resolve.interfaces[*id].name.as_ref().unwrap(),
+                };
+                *path.last_mut().unwrap() = to_rust_ident(name);
+                format!("{}::", path.join("::"))
+            }
+        }
+    }
+
+    pub(super) fn path_to_root(&self) -> String {
         let mut path_to_root = String::new();
 
         if let Identifier::Interface(_, key) = self.identifier {
@@ -243,6 +520,7 @@ impl InterfaceGenerator<'_> {
         if self.gen.skip.contains(&func.name) {
             return;
         }
+        self.register_async_payloads(func);
 
         let mut sig = FnSig::default();
         let param_mode = TypeMode::AllBorrowed("'_");
@@ -259,6 +537,9 @@ impl InterfaceGenerator<'_> {
                 }
             }
         }
+        if self.is_mocked(func) {
+            self.src.push_str("#[cfg(target_arch = \"wasm32\")]\n");
+        }
         self.src.push_str("#[allow(clippy::all)]\n");
         let params = self.print_signature(func, param_mode, &sig);
         self.src.push_str("{\n");
@@ -281,6 +562,7 @@ impl InterfaceGenerator<'_> {
         );
         let FunctionBindgen {
             needs_cleanup_list,
+            needs_mapped_values,
             src,
             import_return_pointer_area_size,
             import_return_pointer_area_align,
@@ -290,6 +572,13 @@ impl InterfaceGenerator<'_> {
         if needs_cleanup_list {
             self.src.push_str("let mut cleanup_list = Vec::new();\n");
         }
+        if needs_mapped_values {
+            uwriteln!(
+                self.src,
+                "let mut mapped_values: Vec<{rt}::boxed::Box<dyn ::core::any::Any>> = Vec::new();",
+                rt = self.gen.runtime_path()
+            );
+        }
         if import_return_pointer_area_size > 0 {
             uwrite!(
                 self.src,
@@ -317,25 +606,53 @@ impl InterfaceGenerator<'_> {
         if self.gen.skip.contains(&func.name) {
             return;
         }
+        self.register_async_payloads(func);
 
         let name_snake = func.name.to_snake_case().replace('.', "_");
         let wasm_module_export_name = match self.identifier {
             Identifier::Interface(_, key) => Some(self.resolve.name_world_key(key)),
             Identifier::World(_) => None,
         };
-        let export_prefix = self.gen.opts.export_prefix.as_deref().unwrap_or("");
+        let export_prefix = self.gen.opts.export_prefix.clone().unwrap_or_default();
         let export_name = func.core_export_name(wasm_module_export_name.as_deref());
-        uwrite!(
-            self.src,
-            "
-                #[doc(hidden)]
-                #[export_name = \"{export_prefix}{export_name}\"]
-                #[allow(non_snake_case)]
-                unsafe extern \"C\" fn __export_{name_snake}(\
-            ",
-        );
-
+        let through_macro = self.gen.opts.export_macro;
         let sig = self.resolve.wasm_signature(AbiVariant::GuestExport, func);
+        let needs_post_return = abi::guest_export_needs_post_return(self.resolve, func);
+        if through_macro {
+            let path = self.path_from_root();
+            self.gen.export_shims.push(crate::ExportShim {
+                export_name: format!("{export_prefix}{export_name}"),
+                name: name_snake.clone(),
+                params: sig.params.clone(),
+                result: sig.results.first().copied(),
+                cabi: format!("{path}_export_{name_snake}_cabi"),
+                post_return: needs_post_return.then(|| {
+                    (
+                        format!("{export_prefix}cabi_post_{export_name}"),
+                        format!("{path}_post_return_{name_snake}"),
+                    )
+                }),
+            });
+            uwrite!(
+                self.src,
+                "
+                    #[doc(hidden)]
+                    #[allow(non_snake_case)]
+                    pub unsafe fn _export_{name_snake}_cabi<_GuestImpl: Guest>(\
+                ",
+            );
+        } else {
+            uwrite!(
+                self.src,
+                "
+                    #[doc(hidden)]
+                    #[export_name = \"{export_prefix}{export_name}\"]
+                    #[allow(non_snake_case)]
+                    unsafe extern \"C\" fn __export_{name_snake}(\
+                ",
+            );
+        }
+
         let mut params = Vec::new();
         for (i, param) in sig.params.iter().enumerate() {
             let name = format!("arg{}", i);
@@ -395,18 +712,28 @@ impl InterfaceGenerator<'_> {
         self.src.push_str(&String::from(src));
         self.src.push_str("}\n");
 
-        if abi::guest_export_needs_post_return(self.resolve, func) {
-            let export_prefix = self.gen.opts.export_prefix.as_deref().unwrap_or("");
-            uwrite!(
-                self.src,
-                "
-                    const _: () = {{
-                    #[doc(hidden)]
-                    #[export_name = \"{export_prefix}cabi_post_{export_name}\"]
-                    #[allow(non_snake_case)]
-                    unsafe extern \"C\" fn __post_return_{name_snake}(\
-                "
-            );
+        if needs_post_return {
+            if through_macro {
+                uwrite!(
+                    self.src,
+                    "
+                        #[doc(hidden)]
+                        #[allow(non_snake_case)]
+                        pub unsafe fn _post_return_{name_snake}(\
+                    "
+                );
+            } else {
+                uwrite!(
+                    self.src,
+                    "
+                        const _: () = {{
+                        #[doc(hidden)]
+                        #[export_name = \"{export_prefix}cabi_post_{export_name}\"]
+                        #[allow(non_snake_case)]
+                        unsafe extern \"C\" fn __post_return_{name_snake}(\
+                    "
+                );
+            }
             let mut params = Vec::new();
             for (i, result) in sig.results.iter().enumerate() {
                 let name = format!("arg{}", i);
@@ -425,7 +752,9 @@ impl InterfaceGenerator<'_> {
             assert!(!needs_cleanup_list);
             self.src.push_str(&String::from(src));
             self.src.push_str("}\n");
-            self.src.push_str("};\n");
+            if !through_macro {
+                self.src.push_str("};\n");
+            }
         }
     }
 
@@ -435,6 +764,7 @@ impl InterfaceGenerator<'_> {
         pkg: Option<(String, String)>,
         name: &str,
         in_interface: bool,
+        resources: &[TypeId],
         funcs: &[&Function],
     ) {
         let path = if let Some((namespace, pkg_name)) = pkg {
@@ -469,11 +799,23 @@ impl InterfaceGenerator<'_> {
 
         uwriteln!(self.src, "impl {qualified_name} for Stub {{");
 
+        if resource.is_none() {
+            for ty in resources {
+                let camel = self.resolve.types[*ty]
+                    .name
+                    .as_deref()
+                    .unwrap()
+                    .to_upper_camel_case();
+                uwriteln!(self.src, "type {camel} = Stub;");
+            }
+        }
+
         for &func in funcs {
             if self.gen.skip.contains(&func.name) {
                 continue;
             }
             let mut sig = FnSig {
+                async_: self.gen.opts.async_exports.is_async(&func.name),
                 use_item_name: true,
                 private: true,
                 ..Default::default()
@@ -489,6 +831,299 @@ impl InterfaceGenerator<'_> {
         self.src.push_str("}\n");
     }
 
+    /// Records the `future` and `stream` types used by `func` so that the
+    /// intrinsics which operate on them can be generated once all interfaces
+    /// have been processed.
+    fn register_async_payloads(&mut self, func: &Function) {
+        let module = match self.wasm_import_module {
+            Some(module) => module.to_string(),
+            None => match self.identifier {
+                Identifier::Interface(_, key) => {
+                    format!("[export]{}", self.resolve.name_world_key(key))
+                }
+                Identifier::World(_) => "[export]$root".to_string(),
+            },
+        };
+        let mut live = LiveTypes::default();
+        live.add_func(self.resolve, func);
+        let mut futures = 0;
+        let mut streams = 0;
+        for ty in live.iter() {
+            let counter = match &self.resolve.types[ty].kind {
+                TypeDefKind::Future(_) => &mut futures,
+                TypeDefKind::Stream(_) => &mut streams,
+                _ => continue,
+            };
+            self.gen.async_payloads.push(AsyncPayload {
+                ty,
+                module: module.clone(),
+                func: func.name.clone(),
+                index: *counter,
+            });
+            *counter += 1;
+        }
+    }
+
+    /// Generates the `wit_future` and `wit_stream` modules along with an
+    /// implementation of their payload traits for each distinct payload type
+    /// in `payloads`.
+    ///
+    /// This is expected to be called on a generator for the root module.
+    pub(super) fn generate_async_payloads(&mut self, payloads: &[AsyncPayload]) {
+        let rt = self.gen.runtime_path().to_string();
+        self.gen.uses_executor = true;
+        let mut futures = BTreeSet::new();
+        let mut streams = BTreeSet::new();
+        for payload in payloads {
+            let (is_future, ty) = match &self.resolve.types[payload.ty].kind {
+                TypeDefKind::Future(ty) => (true, *ty),
+                TypeDefKind::Stream(stream) => (false, stream.element),
+                _ => unreachable!(),
+            };
+            // Aliases are looked through so that the same Rust type doesn't get
+            // multiple implementations of the payload traits.
+            let mut ty = ty;
+            while let Some(Type::Id(id)) = ty {
+                match &self.resolve.types[id].kind {
+                    TypeDefKind::Type(t) => ty = Some(*t),
+                    _ => break,
+                }
+            }
+            let name = match &ty {
+                Some(ty) => self.type_name_owned(ty),
+                None => "()".to_string(),
+            };
+            let seen = if is_future {
+                &mut futures
+            } else {
+                &mut streams
+            };
+            if !seen.insert(name.clone()) {
+                continue;
+            }
+
+            let AsyncPayload {
+                module,
+                func,
+                index,
+                ..
+            } = payload;
+            let (kind, vtable, payload_trait, params) = if is_future {
+                (
+                    "future",
+                    "FutureVtable",
+                    "wit_future::FuturePayload",
+                    &[("future", "u32"), ("ptr", "*mut u8")][..],
+                )
+            } else {
+                (
+                    "stream",
+                    "StreamVtable",
+                    "wit_stream::StreamPayload",
+                    &[("stream", "u32"), ("ptr", "*mut u8"), ("len", "usize")][..],
+                )
+            };
+            let handle = &params[..1];
+            let interface = module.strip_prefix("[export]").unwrap_or(module);
+            let item = WitItem::Function {
+                interface: (interface != "$root").then(|| interface.to_string()),
+                name: func.clone(),
+            };
+
+            uwrite!(
+                self.src,
+                "
+                    #[allow(clippy::all)]
+                    const _: () = {{
+                        #[allow(unused_imports)]
+                        use {rt}::{{alloc, vec::Vec, string::String}};
+                "
+            );
+            self.async_intrinsic(
+                module,
+                &format!("[{kind}-new-{index}]{func}"),
+                "new",
+                &[],
+                Some("u32"),
+                &item,
+            );
+            self.async_intrinsic(
+                module,
+                &format!("[{kind}-read-{index}]{func}"),
+                "start_read",
+                params,
+                Some("u32"),
+                &item,
+            );
+            self.async_intrinsic(
+                module,
+                &format!("[{kind}-write-{index}]{func}"),
+                "start_write",
+                params,
+                Some("u32"),
+                &item,
+            );
+            self.async_intrinsic(
+                module,
+                &format!("[{kind}-close-readable-{index}]{func}"),
+                "close_readable",
+                handle,
+                None,
+                &item,
+            );
+            self.async_intrinsic(
+                module,
+                &format!("[{kind}-close-writable-{index}]{func}"),
+                "close_writable",
+                handle,
+                None,
+                &item,
+            );
+
+            let (size, align, lift, lower) = match &ty {
+                Some(ty) => {
+                    let mut f = FunctionBindgen::new(self, Vec::new());
+                    let result = abi::lift_from_memory(f.gen.resolve, &mut f, "base".into(), ty);
+                    let lift = format!("let base = ptr as i32;\n{}{result}", String::from(f.src));
+
+                    let mut f = FunctionBindgen::new(self, Vec::new());
+                    abi::lower_to_memory(f.gen.resolve, &mut f, "base".into(), "value".into(), ty);
+                    let lower = format!("let base = ptr as i32;\n{}", String::from(f.src));
+
+                    (self.sizes.size(ty), self.sizes.align(ty), lift, lower)
+                }
+                None => (
+                    0,
+                    1,
+                    "let _ = ptr;".to_string(),
+                    "let _ = (value, ptr);".to_string(),
+                ),
+            };
+            uwrite!(
+                self.src,
+                "
+                        unsafe fn lift(ptr: *mut u8) -> {name} {{
+                            {lift}
+                        }}
+
+                        unsafe fn lower(value: {name}, ptr: *mut u8) {{
+                            {lower}
+                        }}
+
+                        impl {payload_trait} for {name} {{
+                            const VTABLE: &'static {rt}::async_support::{vtable}<Self> = &{rt}::async_support::{vtable} {{
+                                layout: unsafe {{
+                                    ::core::alloc::Layout::from_size_align_unchecked({size}, {align})
+                                }},
+                                new,
+                                start_read,
+                                start_write,
+                                lift,
+                                lower,
+                                close_readable,
+                                close_writable,
+                            }};
+                        }}
+                    }};
+                "
+            );
+        }
+
+        if !futures.is_empty() {
+            uwrite!(
+                self.src,
+                "
+                    /// Support for creating `future`s of the payload types used in this
+                    /// world.
+                    pub mod wit_future {{
+                        /// A type which may be sent through a `future` in this world.
+                        pub trait FuturePayload: Sized + 'static {{
+                            #[doc(hidden)]
+                            const VTABLE: &'static {rt}::async_support::FutureVtable<Self>;
+                        }}
+
+                        /// Creates a new `future`, returning its writable and readable ends.
+                        pub fn new<T: FuturePayload>() -> (
+                            {rt}::async_support::FutureWriter<T>,
+                            {rt}::async_support::FutureReader<T>,
+                        ) {{
+                            {rt}::async_support::future_new(T::VTABLE)
+                        }}
+                    }}
+                "
+            );
+        }
+        if !streams.is_empty() {
+            uwrite!(
+                self.src,
+                "
+                    /// Support for creating `stream`s of the element types used in this
+                    /// world.
+                    pub mod wit_stream {{
+                        /// A type which may be sent through a `stream` in this world.
+                        pub trait StreamPayload: Sized + 'static {{
+                            #[doc(hidden)]
+                            const VTABLE: &'static {rt}::async_support::StreamVtable<Self>;
+                        }}
+
+                        /// Creates a new `stream`, returning its writable and readable ends.
+                        pub fn new<T: StreamPayload>() -> (
+                            {rt}::async_support::StreamWriter<T>,
+                            {rt}::async_support::StreamReader<T>,
+                        ) {{
+                            {rt}::async_support::stream_new(T::VTABLE)
+                        }}
+                    }}
+                "
+            );
+        }
+    }
+
+    /// Defines a function named `rust_name` which invokes the intrinsic
+    /// `name` imported from `module`.
+    fn async_intrinsic(
+        &mut self,
+        module: &str,
+        name: &str,
+        rust_name: &str,
+        params: &[(&str, &str)],
+        result: Option<&str>,
+        item: &WitItem,
+    ) {
+        self.gen.async_imports.push(CoreImport {
+            module: module.to_string(),
+            name: name.to_string(),
+            item: item.clone(),
+        });
+        let mut sig_params = String::new();
+        let mut import_params = String::new();
+        let mut args = String::new();
+        for (param, ty) in params {
+            uwrite!(sig_params, "{param}: {ty}, ");
+            uwrite!(import_params, "_: {ty}, ");
+            uwrite!(args, "{param}, ");
+        }
+        let result = result.map(|ty| format!(" -> {ty}")).unwrap_or_default();
+        uwrite!(
+            self.src,
+            "
+                unsafe fn {rust_name}({sig_params}){result} {{
+                    #[cfg(target_arch = \"wasm32\")]
+                    #[link(wasm_import_module = \"{module}\")]
+                    extern \"C\" {{
+                        #[link_name = \"{name}\"]
+                        fn wit_import({import_params}){result};
+                    }}
+
+                    #[cfg(not(target_arch = \"wasm32\"))]
+                    unsafe fn wit_import({import_params}){result} {{ unreachable!() }}
+
+                    wit_import({args})
+                }}
+            "
+        );
+    }
+
     fn rustdoc(&mut self, docs: &Docs) {
         let docs = match &docs.contents {
             Some(docs) => docs,
@@ -573,7 +1208,9 @@ impl InterfaceGenerator<'_> {
             self.push_str("async ");
         }
         self.push_str("fn ");
-        let func_name = if sig.use_item_name {
+        let func_name = if let Some(name) = &sig.name {
+            name
+        } else if sig.use_item_name {
             if let FunctionKind::Constructor(_) = &func.kind {
                 "new"
             } else {
@@ -601,7 +1238,16 @@ impl InterfaceGenerator<'_> {
             self.push_str(&name);
             params.push(name);
             self.push_str(": ");
-            self.print_ty(param, param_mode);
+            match self.aliased_mapped_type(param) {
+                // Custom types are borrowed as a whole when parameters are.
+                Some(custom) => {
+                    if let TypeMode::AllBorrowed(_) | TypeMode::HandlesBorrowed(_) = param_mode {
+                        self.push_str("&");
+                    }
+                    self.push_str(&custom);
+                }
+                None => self.print_ty(param, param_mode),
+            }
             self.push_str(",");
         }
         self.push_str(")");
@@ -662,6 +1308,12 @@ impl InterfaceGenerator<'_> {
         }
     }
 
+    pub(super) fn type_name_owned(&mut self, ty: &Type) -> String {
+        let prev = mem::take(&mut self.src);
+        self.print_ty(ty, TypeMode::Owned);
+        String::from(mem::replace(&mut self.src, prev))
+    }
+
     pub fn type_path(&self, id: TypeId, owned: bool) -> String {
         self.type_path_with_name(
             id,
@@ -683,6 +1335,13 @@ impl InterfaceGenerator<'_> {
     }
 
     fn print_tyid(&mut self, id: TypeId, mode: TypeMode) {
+        match self.mapped_type(id) {
+            Some(custom) => self.push_str(&custom),
+            None => self.print_unmapped_tyid(id, mode),
+        }
+    }
+
+    fn print_unmapped_tyid(&mut self, id: TypeId, mode: TypeMode) {
         let info = self.info(id);
         let lt = self.lifetime_for(&info, mode);
         let ty = &self.resolve.types[id];
@@ -793,18 +1452,8 @@ impl InterfaceGenerator<'_> {
             TypeDefKind::Enum(_) => {
                 panic!("unsupported anonymous type reference: enum")
             }
-            TypeDefKind::Future(ty) => {
-                self.push_str("Future<");
-                self.print_optional_ty(ty.as_ref(), mode);
-                self.push_str(">");
-            }
-            TypeDefKind::Stream(stream) => {
-                self.push_str("Stream<");
-                self.print_optional_ty(stream.element.as_ref(), mode);
-                self.push_str(",");
-                self.print_optional_ty(stream.end.as_ref(), mode);
-                self.push_str(">");
-            }
+            TypeDefKind::Future(ty) => self.print_future(ty),
+            TypeDefKind::Stream(stream) => self.print_stream(stream),
 
             TypeDefKind::Handle(Handle::Own(ty)) => {
                 self.mark_resource_owned(*ty);
@@ -841,6 +1490,28 @@ impl InterfaceGenerator<'_> {
         }
     }
 
+    // The payloads of futures and streams are always owned since they're
+    // transferred through the future or stream after the call has returned.
+    fn print_future(&mut self, ty: &Option<Type>) {
+        self.push_str(&format!(
+            "{rt}::async_support::FutureReader<",
+            rt = self.gen.runtime_path()
+        ));
+        self.print_optional_ty(ty.as_ref(), TypeMode::Owned);
+        self.push_str(">");
+    }
+
+    fn print_stream(&mut self, stream: &Stream) {
+        // Streams with an end type are rejected in `preprocess`.
+        assert!(stream.end.is_none());
+        self.push_str(&format!(
+            "{rt}::async_support::StreamReader<",
+            rt = self.gen.runtime_path()
+        ));
+        self.print_optional_ty(stream.element.as_ref(), TypeMode::Owned);
+        self.push_str(">");
+    }
+
     fn print_list(&mut self, ty: &Type, mode: TypeMode) {
         let next_mode = if matches!(self.gen.opts.ownership, Ownership::Owning) {
             if let TypeMode::HandlesBorrowed(_) = mode {
@@ -947,6 +1618,8 @@ impl InterfaceGenerator<'_> {
             .iter()
             .cloned()
             .collect();
+        // Custom types of mapped types aren't necessarily `Copy`.
+        let is_copy = info.is_copy() && !record.fields.iter().any(|f| self.contains_mapped(&f.ty));
         for (name, mode) in self.modes_of(id) {
             let lt = self.lifetime_for(&info, mode);
             self.rustdoc(docs);
@@ -960,17 +1633,23 @@ impl InterfaceGenerator<'_> {
                 self.push_str("#[component(record)]\n");
             }
             let mut derives = additional_derives.clone();
-            if info.is_copy() {
+            if is_copy {
                 self.push_str("#[repr(C)]\n");
-                derives.extend(["Copy", "Clone"].into_iter().map(|s| s.to_string()));
+                add_derives(&mut derives, ["Copy", "Clone"]);
             } else if info.is_clone() {
-                derives.insert("Clone".to_string());
+                add_derives(&mut derives, ["Clone"]);
             }
+            if self.gen.opts.comparison_derives {
+                add_derives(&mut derives, comparison_derives(&info));
+            }
+            let serde = self.serde_derives(&info, lt);
+            derives.extend(serde.iter().cloned());
             if !derives.is_empty() {
                 self.push_str("#[derive(");
                 self.push_str(&derives.into_iter().collect::<Vec<_>>().join(", "));
                 self.push_str(")]\n")
             }
+            self.print_serde_attrs(&serde);
             self.push_str(&format!("pub struct {}", name));
             self.print_generics(lt);
             self.push_str(" {\n");
@@ -979,6 +1658,9 @@ impl InterfaceGenerator<'_> {
                 if derive_component {
                     self.push_str(&format!("#[component(name = \"{}\")]\n", field.name));
                 }
+                if !serde.is_empty() {
+                    self.push_str(&format!("#[serde(rename = \"{}\")]\n", field.name));
+                }
                 self.push_str("pub ");
                 self.push_str(&to_rust_ident(&field.name));
                 self.push_str(": ");
@@ -1031,6 +1713,93 @@ impl InterfaceGenerator<'_> {
         }
     }
 
+    /// Returns the custom type which the type `id` itself is mapped to
+    /// through `mapped_types`, rather than through an alias of it.
+    pub(super) fn mapped_type(&self, id: TypeId) -> Option<String> {
+        if self.gen.opts.mapped_types.is_empty() {
+            return None;
+        }
+        mapped_type_keys(self.resolve, id)
+            .iter()
+            .find_map(|key| self.gen.opts.mapped_types.get(key))
+            .cloned()
+    }
+
+    /// Returns the custom type `ty` is mapped to, either itself or through
+    /// the types it aliases.
+    fn aliased_mapped_type(&self, ty: &Type) -> Option<String> {
+        let Type::Id(id) = ty else {
+            return None;
+        };
+        self.mapped_type(*id)
+            .or_else(|| match &self.resolve.types[*id].kind {
+                TypeDefKind::Type(ty) => self.aliased_mapped_type(ty),
+                _ => None,
+            })
+    }
+
+    /// Returns whether `ty` is or contains a type which is mapped to a custom
+    /// type, which rules out lifting and lowering its lists as is and
+    /// deriving `Copy` for types containing it.
+    pub(super) fn contains_mapped(&self, ty: &Type) -> bool {
+        let Type::Id(id) = ty else {
+            return false;
+        };
+        let contains = |ty: &Option<Type>| ty.as_ref().is_some_and(|ty| self.contains_mapped(ty));
+        self.mapped_type(*id).is_some()
+            || match &self.resolve.types[*id].kind {
+                TypeDefKind::Type(ty) | TypeDefKind::List(ty) | TypeDefKind::Option(ty) => {
+                    self.contains_mapped(ty)
+                }
+                TypeDefKind::Record(record) => {
+                    record.fields.iter().any(|f| self.contains_mapped(&f.ty))
+                }
+                TypeDefKind::Tuple(tuple) => tuple.types.iter().any(|ty| self.contains_mapped(ty)),
+                TypeDefKind::Variant(variant) => variant.cases.iter().any(|c| contains(&c.ty)),
+                TypeDefKind::Result(result) => contains(&result.ok) || contains(&result.err),
+                _ => false,
+            }
+    }
+
+    /// Returns the type which the custom type of the mapped type `id` is
+    /// converted from and to, which is the type generated for `id`.
+    pub(super) fn mapped_wit_type(&mut self, id: TypeId) -> String {
+        if self.resolve.types[id].name.is_some() {
+            return self.type_path(id, true);
+        }
+        let prev = mem::take(&mut self.src);
+        self.print_unmapped_tyid(id, TypeMode::Owned);
+        String::from(mem::replace(&mut self.src, prev))
+    }
+
+    /// Returns the `serde` derives of a type with `info` which is generated
+    /// with the lifetime `lt`, if any.
+    ///
+    /// Types containing resources, futures or streams can't be serialized so
+    /// they don't get any, and borrowing types only implement `Serialize`.
+    fn serde_derives(&self, info: &TypeInfo, lt: Option<&str>) -> Vec<String> {
+        let serde = match self.gen.serde_path() {
+            Some(serde) if !info.has_resource => serde,
+            _ => return Vec::new(),
+        };
+        let mut derives = vec![format!("{serde}::Serialize")];
+        if lt.is_none() {
+            derives.push(format!("{serde}::Deserialize"));
+        }
+        derives
+    }
+
+    /// Prints the container attributes of a type deriving `serde`'s traits.
+    fn print_serde_attrs(&mut self, derives: &[String]) {
+        if derives.is_empty() {
+            return;
+        }
+        if let Some(path) = &self.gen.opts.serde_path {
+            let path = path.clone();
+            self.push_str(&format!("#[serde(crate = \"{path}\")]\n"));
+        }
+    }
+
     fn print_typedef_tuple(&mut self, id: TypeId, tuple: &Tuple, docs: &Docs) {
         let info = self.info(id);
         for (name, mode) in self.modes_of(id) {
@@ -1093,6 +1862,12 @@ impl InterfaceGenerator<'_> {
             .iter()
             .cloned()
             .collect();
+        // Custom types of mapped types aren't necessarily `Copy`.
+        let is_copy = info.is_copy()
+            && !cases
+                .clone()
+                .into_iter()
+                .any(|(_, _, _, ty)| ty.is_some_and(|ty| self.contains_mapped(ty)));
         for (name, mode) in self.modes_of(id) {
             self.rustdoc(docs);
             let lt = self.lifetime_for(&info, mode);
@@ -1105,26 +1880,37 @@ impl InterfaceGenerator<'_> {
                 self.push_str(&format!("#[component({})]\n", derive_component));
             }
             let mut derives = additional_derives.clone();
-            if info.is_copy() {
-                derives.extend(["Copy", "Clone"].into_iter().map(|s| s.to_string()));
+            if is_copy {
+                add_derives(&mut derives, ["Copy", "Clone"]);
             } else if info.is_clone() {
-                derives.insert("Clone".to_string());
+                add_derives(&mut derives, ["Clone"]);
+            }
+            if self.gen.opts.comparison_derives {
+                add_derives(&mut derives, comparison_derives(&info));
             }
+            let serde = self.serde_derives(&info, lt);
+            derives.extend(serde.iter().cloned());
             if !derives.is_empty() {
                 self.push_str("#[derive(");
                 self.push_str(&derives.into_iter().collect::<Vec<_>>().join(", "));
                 self.push_str(")]\n")
             }
+            self.print_serde_attrs(&serde);
             self.push_str(&format!("pub enum {name}"));
             self.print_generics(lt);
             self.push_str("{\n");
             for (case_name, component_name, docs, payload) in cases.clone() {
                 self.rustdoc(docs);
                 if derive_component.is_some() {
-                    if let Some(n) = component_name {
+                    if let Some(n) = &component_name {
                         self.push_str(&format!("#[component(name = \"{}\")] ", n));
                     }
                 }
+                if !serde.is_empty() {
+                    if let Some(n) = &component_name {
+                        self.push_str(&format!("#[serde(rename = \"{}\")] ", n));
+                    }
+                }
                 self.push_str(&case_name);
                 if let Some(ty) = payload {
                     self.push_str("(");
@@ -1273,18 +2059,23 @@ impl InterfaceGenerator<'_> {
             .iter()
             .cloned()
             .collect();
-        derives.extend(
-            ["Clone", "Copy", "PartialEq", "Eq"]
-                .into_iter()
-                .map(|s| s.to_string()),
-        );
+        add_derives(&mut derives, ["Clone", "Copy", "PartialEq", "Eq"]);
+        if self.gen.opts.comparison_derives {
+            add_derives(&mut derives, ["PartialOrd", "Ord", "Hash"]);
+        }
+        let serde = self.serde_derives(&info, None);
+        derives.extend(serde.iter().cloned());
         self.push_str("#[derive(");
         self.push_str(&derives.into_iter().collect::<Vec<_>>().join(", "));
         self.push_str(")]\n");
+        self.print_serde_attrs(&serde);
         self.push_str(&format!("pub enum {name} {{\n"));
         for case in enum_.cases.iter() {
             self.rustdoc(&case.docs);
             self.push_str(&case_attr(case));
+            if !serde.is_empty() {
+                self.push_str(&format!("#[serde(rename = \"{}\")]\n", case.name));
+            }
             self.push_str(&case.name.to_upper_camel_case());
             self.push_str(",\n");
         }
@@ -1411,6 +2202,24 @@ impl InterfaceGenerator<'_> {
         }
     }
 
+    fn print_typedef_future(&mut self, id: TypeId, ty: &Option<Type>, docs: &Docs) {
+        for (name, _mode) in self.modes_of(id) {
+            self.rustdoc(docs);
+            self.push_str(&format!("pub type {name} = "));
+            self.print_future(ty);
+            self.push_str(";\n");
+        }
+    }
+
+    fn print_typedef_stream(&mut self, id: TypeId, stream: &Stream, docs: &Docs) {
+        for (name, _mode) in self.modes_of(id) {
+            self.rustdoc(docs);
+            self.push_str(&format!("pub type {name} = "));
+            self.print_stream(stream);
+            self.push_str(";\n");
+        }
+    }
+
     fn print_type_list(&mut self, id: TypeId, ty: &Type, docs: &Docs) {
         let info = self.info(id);
         for (name, mode) in self.modes_of(id) {
@@ -1447,21 +2256,7 @@ impl InterfaceGenerator<'_> {
     }
 
     fn uses_two_names(&self, info: &TypeInfo) -> bool {
-        // Types are only duplicated if explicitly requested ...
-        matches!(
-            self.gen.opts.ownership,
-            Ownership::Borrowing {
-                duplicate_if_necessary: true
-            }
-        )
-            // ... and if they're both used in a borrowed/owned context
-            && info.borrowed
-            && info.owned
-            // ... and they have a list ...
-            && info.has_list
-            // ... and if there's NOT an `own` handle since those are always
-            // done by ownership.
-            && !info.has_own_handle
+        self.gen.uses_two_names(info)
     }
 
     fn lifetime_for(&self, info: &TypeInfo, mode: TypeMode) -> Option<&'static str> {
@@ -1646,6 +2441,21 @@ impl<'a> wit_bindgen_core::InterfaceGenerator<'a> for InterfaceGenerator<'a> {
                     }}
                 "#
             );
+            if self.gen.opts.mock_imports {
+                uwriteln!(
+                    self.src,
+                    r#"
+                        impl {camel} {{
+                            /// Wraps `handle`, which is only meaningful to the
+                            /// installed `Mock`.
+                            #[cfg(not(target_arch = "wasm32"))]
+                            pub fn from_mock_handle(handle: u32) -> Self {{
+                                unsafe {{ Self::from_handle(handle) }}
+                            }}
+                        }}
+                    "#
+                );
+            }
             self.wasm_import_module.unwrap().to_string()
         } else {
             // Exported resources are represented as `Resource<T>` as opposed
@@ -1661,17 +2471,55 @@ impl<'a> wit_bindgen_core::InterfaceGenerator<'a> for InterfaceGenerator<'a> {
                 Identifier::Interface(_, key) => self.resolve.name_world_key(key),
                 Identifier::World(_) => unimplemented!("resource exports from worlds"),
             };
-            // NB: errors are ignored here since they'll generate an error
-            // through the `generate_exports` method above.
-            let impl_name = self
-                .gen
-                .lookup_export(&self.export_key(Some(name)))
-                .unwrap_or_else(|_| "ERROR".to_string());
-            let path_to_root = self.path_to_root();
+            if self.gen.opts.export_macro {
+                // The implementation is only known once `export!` is invoked,
+                // so `{camel}` erases it and is downcast to the `Guest{camel}`
+                // type it's created from.
+                uwriteln!(
+                    self.src,
+                    r#"
+                        #[repr(transparent)]
+                        pub struct {camel}({rt}::boxed::Box<dyn ::core::any::Any>);
+
+                        impl {camel} {{
+                            /// Wraps `val`, the implementation of this resource.
+                            pub fn new<T: Guest{camel}>(val: T) -> Self {{
+                                Self({rt}::boxed::Box::new(val))
+                            }}
+
+                            /// Returns the implementation of this resource, which
+                            /// must be `T`.
+                            pub fn get<T: Guest{camel}>(&self) -> &T {{
+                                self.0.downcast_ref().expect("`{camel}` implemented by a different type")
+                            }}
+
+                            /// Returns the implementation of this resource, which
+                            /// must be `T`.
+                            pub fn get_mut<T: Guest{camel}>(&mut self) -> &mut T {{
+                                self.0.downcast_mut().expect("`{camel}` implemented by a different type")
+                            }}
+                        }}
+
+                        impl ::core::fmt::Debug for {camel} {{
+                            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {{
+                                f.debug_struct("{camel}").finish_non_exhaustive()
+                            }}
+                        }}
+                    "#
+                );
+            } else {
+                // NB: errors are ignored here since they'll generate an error
+                // through the `generate_exports` method above.
+                let impl_name = self
+                    .gen
+                    .lookup_export(&self.export_key(Some(name)), self.export_item(Some(name)))
+                    .unwrap_or_else(|_| "ERROR".to_string());
+                let path_to_root = self.path_to_root();
+                uwriteln!(self.src, "pub use {path_to_root}{impl_name} as {camel};");
+            }
             uwriteln!(
                 self.src,
                 r#"
-                    pub use {path_to_root}{impl_name} as {camel};
                     const _: () = {{
                         #[doc(hidden)]
                         #[export_name = "{export_prefix}{module}#[dtor]{name}"]
@@ -1717,6 +2565,17 @@ impl<'a> wit_bindgen_core::InterfaceGenerator<'a> for InterfaceGenerator<'a> {
             format!("[export]{module}")
         };
 
+        // Imported handles are only created by mocks natively, so they're
+        // dropped through the installed mock, if any.
+        let native_drop = if self.in_import && self.gen.opts.mock_imports {
+            format!(
+                "if let Some(mock) = __MOCK.with(|m| m.borrow().clone()) {{ mock.{}_drop(handle) }}",
+                name.to_snake_case()
+            )
+        } else {
+            "unreachable!();".to_string()
+        };
+
         uwriteln!(
             self.src,
             r#"
@@ -1724,7 +2583,7 @@ impl<'a> wit_bindgen_core::InterfaceGenerator<'a> for InterfaceGenerator<'a> {
                      #[inline]
                      unsafe fn drop(handle: u32) {{
                          #[cfg(not(target_arch = "wasm32"))]
-                         unreachable!();
+                         {native_drop}
 
                          #[cfg(target_arch = "wasm32")]
                          {{
@@ -1767,6 +2626,28 @@ impl<'a> wit_bindgen_core::InterfaceGenerator<'a> for InterfaceGenerator<'a> {
         }
         self.src.push_str("}\n");
         self.src.push_str("}\n");
+
+        // `bitflags` types are (de)serialized as their bits, which doesn't
+        // depend on `bitflags`' own `serde` feature.
+        if let Some(serde) = self.gen.serde_path() {
+            let name = name.to_upper_camel_case();
+            uwriteln!(
+                self.src,
+                "
+                    impl {serde}::Serialize for {name} {{
+                        fn serialize<S: {serde}::Serializer>(&self, serializer: S) -> ::core::result::Result<S::Ok, S::Error> {{
+                            {serde}::Serialize::serialize(&self.bits(), serializer)
+                        }}
+                    }}
+
+                    impl<'de> {serde}::Deserialize<'de> for {name} {{
+                        fn deserialize<D: {serde}::Deserializer<'de>>(deserializer: D) -> ::core::result::Result<Self, D::Error> {{
+                            <{repr} as {serde}::Deserialize<'de>>::deserialize(deserializer).map(Self::from_bits_retain)
+                        }}
+                    }}
+                "
+            );
+        }
     }
 
     fn type_variant(&mut self, id: TypeId, _name: &str, variant: &Variant, docs: &Docs) {
@@ -1818,6 +2699,14 @@ impl<'a> wit_bindgen_core::InterfaceGenerator<'a> for InterfaceGenerator<'a> {
         self.print_type_list(id, ty, docs);
     }
 
+    fn type_future(&mut self, id: TypeId, _name: &str, ty: &Option<Type>, docs: &Docs) {
+        self.print_typedef_future(id, ty, docs);
+    }
+
+    fn type_stream(&mut self, id: TypeId, _name: &str, stream: &Stream, docs: &Docs) {
+        self.print_typedef_stream(id, stream, docs);
+    }
+
     fn type_builtin(&mut self, _id: TypeId, name: &str, ty: &Type, docs: &Docs) {
         self.rustdoc(docs);
         self.src
@@ -1827,3 +2716,33 @@ impl<'a> wit_bindgen_core::InterfaceGenerator<'a> for InterfaceGenerator<'a> {
         self.src.push_str(";\n");
     }
 }
+
+/// Returns the comparison and hashing traits which can be derived for a type
+/// with `info`.
+fn comparison_derives(info: &TypeInfo) -> impl Iterator<Item = &'static str> {
+    let partial_eq: &[&str] = if info.is_partial_eq() {
+        &["PartialEq", "PartialOrd"]
+    } else {
+        &[]
+    };
+    let eq: &[&str] = if info.is_eq() {
+        &["Eq", "Ord", "Hash"]
+    } else {
+        &[]
+    };
+    partial_eq.iter().chain(eq).copied()
+}
+
+/// Adds each trait in `traits` to `derives` unless it's already derived,
+/// possibly through a path such as `std::hash::Hash` from
+/// `additional_derive_attributes`.
+fn add_derives<'a>(derives: &mut BTreeSet<String>, traits: impl IntoIterator<Item = &'a str>) {
+    for name in traits {
+        let derived = derives
+            .iter()
+            .any(|d| d.rsplit("::").next().map(str::trim) == Some(name));
+        if !derived {
+            derives.insert(name.to_string());
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use crate::interface::InterfaceGenerator;
-use anyhow::{bail, Result};
+use anyhow::Result;
 use heck::*;
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::{self, Write as _};
@@ -9,7 +9,8 @@ use std::process::{Command, Stdio};
 use std::str::FromStr;
 use wit_bindgen_core::abi::{Bitcast, WasmType};
 use wit_bindgen_core::{
-    uwriteln, wit_parser::*, Files, InterfaceGenerator as _, Source, Types, WorldGenerator,
+    uwrite, uwriteln, wit_parser::*, CoreImport, CoreSymbols, Diagnostic, Files,
+    InterfaceGenerator as _, Source, TypeInfo, Types, WitItem, WorldGenerator,
 };
 
 mod bindgen;
@@ -54,6 +55,42 @@ struct RustWasm {
     resources: HashMap<TypeId, ResourceInfo>,
     import_funcs_called: bool,
     with_name_counter: usize,
+    async_payloads: Vec<AsyncPayload>,
+    export_shims: Vec<ExportShim>,
+    /// The intrinsics imported for `future` and `stream` payloads.
+    async_imports: Vec<CoreImport>,
+    /// Whether the bindings use the async executor, which imports
+    /// `[task-wait]` and `[task-poll]`.
+    uses_executor: bool,
+}
+
+/// An exported function whose `#[export_name]` shim is emitted by the
+/// `export!` macro rather than alongside the bindings.
+///
+/// The shim forwards to the generic `{cabi}` function which performs the
+/// lift/lower operations for the type the macro was invoked with.
+struct ExportShim {
+    export_name: String,
+    name: String,
+    params: Vec<WasmType>,
+    result: Option<WasmType>,
+    /// Path to the generic function relative to the bindings root.
+    cabi: String,
+    /// The export name and path of the post-return function, if any.
+    post_return: Option<(String, String)>,
+}
+
+/// A `future` or `stream` type used by a function along with the location of
+/// the intrinsics which operate on it.
+///
+/// Intrinsics are imported per-function, for example
+/// `[future-read-0]my-func`, where the index is the position of the type
+/// amongst all `future`s (or `stream`s) used by that function.
+struct AsyncPayload {
+    ty: TypeId,
+    module: String,
+    func: String,
+    index: usize,
 }
 
 #[cfg(feature = "clap")]
@@ -184,6 +221,100 @@ pub struct Opts {
     /// Remapping of interface names to rust module names.
     #[cfg_attr(feature = "clap", arg(long, value_parser = parse_with, default_value = ""))]
     pub with: HashMap<String, String>,
+
+    /// Which exported functions are generated as `async fn`s.
+    ///
+    /// Valid values include `none`, `all`, or a comma-separated list of
+    /// function names. Generated bindings drive `async` exports to completion
+    /// with `wit_bindgen::rt::async_support::block_on`, which requires the
+    /// `async` feature of the `wit-bindgen` crate.
+    #[cfg_attr(feature = "clap", arg(long, default_value_t = AsyncExports::None))]
+    pub async_exports: AsyncExports,
+
+    /// If true, the `component-type` custom section describing the world
+    /// isn't emitted, and its type information must be supplied some other
+    /// way when creating a component.
+    ///
+    /// `wit-component` can't yet encode worlds which use `future` or `stream`
+    /// types, so generating bindings for them requires this option.
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub skip_component_type: bool,
+
+    /// If true, exported functions aren't bound to the types listed in
+    /// `exports` when bindings are generated. Instead an `export!` macro is
+    /// generated which the final component invokes with the type
+    /// implementing the `Guest` traits, e.g. `export!(MyImpl)`.
+    ///
+    /// Exported resources are implemented by the associated types of
+    /// `Guest`, for example `type Thing = MyThing;`.
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub export_macro: bool,
+
+    /// If true, the `export!` macro is also `#[macro_export]`-ed as
+    /// `__export_{world}_impl` so that crates other than the one containing
+    /// the bindings can invoke it.
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub pub_export_macro: bool,
+
+    /// The path to the generated bindings used by `export!(MyImpl)`.
+    ///
+    /// Without this the macro must be invoked as
+    /// `export!(MyImpl with_types_in path::to::bindings)`.
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub default_bindings_module: Option<String>,
+
+    /// If true, imported functions forward to a `Mock` trait when not
+    /// targeting wasm32 so that guests can be tested natively.
+    ///
+    /// Each module with imported functions gets a `Mock` trait with a method
+    /// per function, and a `set_mock` function installing an implementation
+    /// for the current thread. This requires `std` on the host.
+    ///
+    /// Resources are identified by `u32` handles chosen by the mock, which
+    /// methods of resources are passed and their constructors return.
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub mock_imports: bool,
+
+    /// If true, generated records, variants and enums derive `PartialEq`,
+    /// `PartialOrd`, `Eq`, `Ord` and `Hash` wherever their contents allow.
+    ///
+    /// Types containing floats only derive `PartialEq` and `PartialOrd`, and
+    /// types containing resources derive none of them. Traits already listed
+    /// in `additional_derive_attributes` aren't derived twice.
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub comparison_derives: bool,
+
+    /// If true, generated records, variants, enums and flags implement
+    /// `serde`'s `Serialize` and `Deserialize`, using the WIT names of
+    /// fields and cases.
+    ///
+    /// Types containing resources are skipped, and types borrowing their
+    /// contents only implement `Serialize`.
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub serde: bool,
+
+    /// The optional path to the `serde` crate to use.
+    ///
+    /// This defaults to `serde`.
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub serde_path: Option<String>,
+
+    /// Custom Rust types used in place of generated types wherever they
+    /// occur, including within other types.
+    ///
+    /// Keys are `ns:pkg/interface/type`, just the type name for types defined
+    /// in the world, or the WIT syntax of an anonymous type such as
+    /// `list<u8>` which refers to named types by their name. Values are paths to the Rust type which must
+    /// resolve from any module such as `crate::Point` or `bytes::Bytes`. A
+    /// `MappedType` trait is generated at the root of the bindings which each
+    /// custom type implements to convert from and to the generated type.
+    ///
+    /// Each key must name a type in the world, and types generated with
+    /// distinct borrowed and owned representations can't be mapped. Custom
+    /// types used within generated records and variants must implement the
+    /// `Clone` and `Debug` traits those derive.
+    #[cfg_attr(feature = "clap", arg(long, value_parser = parse_with, default_value = ""))]
+    pub mapped_types: HashMap<String, String>,
 }
 
 impl Opts {
@@ -253,6 +384,99 @@ impl RustWasm {
         }
     }
 
+    /// Emits the `export!` macro which defines the `#[export_name]` shims
+    /// of `shims` for the type it is invoked with.
+    fn emit_export_macro(&mut self, world: &str, shims: &[ExportShim]) {
+        let render = |root: &str| {
+            let mut src = String::new();
+            for shim in shims {
+                let params = shim
+                    .params
+                    .iter()
+                    .enumerate()
+                    .map(|(i, ty)| format!("arg{i}: {}", wasm_type(*ty)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let args = (0..shim.params.len())
+                    .map(|i| format!("arg{i}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let result = match shim.result {
+                    Some(ty) => format!(" -> {}", wasm_type(ty)),
+                    None => String::new(),
+                };
+                uwrite!(
+                    src,
+                    "
+                        const _: () = {{
+                            #[export_name = \"{export_name}\"]
+                            #[allow(non_snake_case)]
+                            unsafe extern \"C\" fn __export_{name}({params}){result} {{
+                                {root}::{cabi}::<$ty>({args})
+                            }}
+                    ",
+                    export_name = shim.export_name,
+                    name = shim.name,
+                    cabi = shim.cabi,
+                );
+                if let Some((post_export_name, post_return)) = &shim.post_return {
+                    let params = shim
+                        .result
+                        .iter()
+                        .map(|ty| format!("arg0: {}", wasm_type(*ty)))
+                        .collect::<String>();
+                    let args = if shim.result.is_some() { "arg0" } else { "" };
+                    uwrite!(
+                        src,
+                        "
+                            #[export_name = \"{post_export_name}\"]
+                            #[allow(non_snake_case)]
+                            unsafe extern \"C\" fn __post_return_{name}({params}) {{
+                                {root}::{post_return}({args})
+                            }}
+                        ",
+                        name = shim.name,
+                    );
+                }
+                src.push_str("};\n");
+            }
+            src
+        };
+
+        let world_snake = world.to_snake_case();
+        let macro_export = if self.opts.pub_export_macro {
+            "#[macro_export]"
+        } else {
+            ""
+        };
+        let default_arm = match &self.opts.default_bindings_module {
+            Some(module) => format!("($ty:ident) => ({});", render(module)),
+            None => String::new(),
+        };
+        let with_types_in = render("$($path_to_types_root)*");
+        uwriteln!(
+            self.src,
+            "
+                /// Exports `$ty` as the implementation of the `Guest` traits
+                /// of the `{world}` world.
+                ///
+                /// Invoked as `export!(MyImpl)` if the bindings were generated
+                /// with a `default_bindings_module`, or otherwise as
+                /// `export!(MyImpl with_types_in path::to::bindings)`.
+                #[allow(unused_macros)]
+                #[doc(hidden)]
+                {macro_export}
+                macro_rules! __export_{world_snake}_impl {{
+                    {default_arm}
+                    ($ty:ident with_types_in $($path_to_types_root:tt)*) => ({with_types_in});
+                }}
+                #[doc(inline)]
+                #[allow(unused_imports)]
+                pub(crate) use __export_{world_snake}_impl as export;
+            "
+        );
+    }
+
     fn runtime_path(&self) -> &str {
         self.opts
             .runtime_path
@@ -260,6 +484,76 @@ impl RustWasm {
             .unwrap_or("wit_bindgen::rt")
     }
 
+    fn uses_two_names(&self, info: &TypeInfo) -> bool {
+        // Types are only duplicated if explicitly requested ...
+        matches!(
+            self.opts.ownership,
+            Ownership::Borrowing {
+                duplicate_if_necessary: true
+            }
+        )
+            // ... and if they're both used in a borrowed/owned context
+            && info.borrowed
+            && info.owned
+            // ... and they have a list ...
+            && info.has_list
+            // ... and if there's NOT an `own` handle since those are always
+            // done by ownership.
+            && !info.has_own_handle
+    }
+
+    /// Checks that each key of `mapped_types` names a type in `world` which
+    /// has a single representation to convert from and to.
+    fn validate_mapped_types(&self, resolve: &Resolve, world: WorldId) -> Result<()> {
+        if self.opts.mapped_types.is_empty() {
+            return Ok(());
+        }
+        let mut live = LiveTypes::default();
+        live.add_world(resolve, world);
+        let mut types = HashMap::new();
+        for id in live.iter() {
+            for key in mapped_type_keys(resolve, id) {
+                types.insert(key, id);
+            }
+        }
+        let mut keys = self.opts.mapped_types.keys().collect::<Vec<_>>();
+        keys.sort();
+        for key in keys {
+            let Some(id) = types.get(key) else {
+                return Err(Diagnostic::new(format!(
+                    "`mapped_types` key `{key}` doesn't name a type in the world"
+                ))
+                .with_item(WitItem::world(resolve, world))
+                .with_help(
+                    "keys are `ns:pkg/interface/type`, just the type name for types \
+                     defined in the world, or the WIT syntax of an anonymous type such \
+                     as `list<u8>`",
+                )
+                .into());
+            };
+            if resolve.types[*id].name.is_some() && self.uses_two_names(&self.types.get(*id)) {
+                return Err(Diagnostic::new(format!(
+                    "mapped type `{key}` has distinct borrowed and owned representations \
+                     which isn't supported"
+                ))
+                .with_item(
+                    WitItem::ty(resolve, *id).unwrap_or_else(|| WitItem::world(resolve, world)),
+                )
+                .with_help("use `ownership: Owning` to generate a single type")
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the path to the `serde` crate if `serde` support is enabled.
+    fn serde_path(&self) -> Option<&str> {
+        if !self.opts.serde {
+            return None;
+        }
+        Some(self.opts.serde_path.as_deref().unwrap_or("serde"))
+    }
+
     fn bitflags_path(&self) -> &str {
         self.opts
             .bitflags_path
@@ -267,7 +561,7 @@ impl RustWasm {
             .unwrap_or("wit_bindgen::bitflags")
     }
 
-    fn lookup_export(&self, key: &ExportKey) -> Result<String> {
+    fn lookup_export(&self, key: &ExportKey, item: WitItem) -> Result<String> {
         if let Some(key) = self.opts.exports.get(key) {
             return Ok(key.clone());
         }
@@ -278,10 +572,18 @@ impl RustWasm {
             ExportKey::World => "world",
             ExportKey::Name(name) => name,
         };
-        if self.opts.exports.is_empty() {
-            bail!("no `exports` map provided in configuration but key is required for `{key}`")
-        }
-        bail!("expected `exports` map to contain key `{key}`")
+        let message = if self.opts.exports.is_empty() {
+            format!("no `exports` map provided in configuration but key is required for `{key}`")
+        } else {
+            format!("expected `exports` map to contain key `{key}`")
+        };
+        Err(Diagnostic::new(message)
+            .with_item(item)
+            .with_help(format!(
+                "add `{key}` to the `exports` map with the path of the type implementing it, \
+                 or enable `stubs` to generate stub implementations"
+            ))
+            .into())
     }
 
     fn name_interface(
@@ -362,9 +664,68 @@ fn name_package_module(resolve: &Resolve, id: PackageId) -> String {
 }
 
 impl WorldGenerator for RustWasm {
-    fn preprocess(&mut self, resolve: &Resolve, _world: WorldId) {
+    fn supports_async_types(&self) -> bool {
+        true
+    }
+
+    fn preprocess(&mut self, resolve: &Resolve, world: WorldId) -> Result<()> {
+        if let Some(ty) = wit_bindgen_core::find_async_type(resolve, world) {
+            if !self.opts.skip_component_type {
+                return Err(Diagnostic::new(
+                    "the `component-type` custom section can't yet be encoded for worlds \
+                     using `future` or `stream` types",
+                )
+                .with_item(
+                    WitItem::ty(resolve, ty).unwrap_or_else(|| WitItem::world(resolve, world)),
+                )
+                .with_help(
+                    "enable `skip_component_type` and supply the world's type information \
+                     when creating the component",
+                )
+                .into());
+            }
+        }
+        let mut live = LiveTypes::default();
+        live.add_world(resolve, world);
+        for ty in live.iter() {
+            if let TypeDefKind::Stream(Stream { end: Some(_), .. }) = resolve.types[ty].kind {
+                return Err(
+                    Diagnostic::new("streams with an end type are not supported")
+                        .with_item(
+                            WitItem::ty(resolve, ty)
+                                .unwrap_or_else(|| WitItem::world(resolve, world)),
+                        )
+                        .into(),
+                );
+            }
+        }
+
         wit_bindgen_core::generated_preamble(&mut self.src, env!("CARGO_PKG_VERSION"));
         self.types.analyze(resolve);
+        self.validate_mapped_types(resolve, world)
+    }
+
+    fn core_symbols(&self, resolve: &Resolve, world: WorldId) -> CoreSymbols {
+        let mut symbols = CoreSymbols::new(resolve, world);
+        if let Some(prefix) = &self.opts.export_prefix {
+            for export in symbols.exports.iter_mut() {
+                export.name = format!("{prefix}{}", export.name);
+            }
+        }
+        // Defined by the `wit-bindgen` crate rather than the bindings, so it
+        // isn't affected by `export_prefix`.
+        symbols.export_realloc(resolve, world);
+        symbols.imports.extend(self.async_imports.iter().cloned());
+        if self.uses_executor {
+            for name in ["[task-wait]", "[task-poll]"] {
+                symbols.imports.push(CoreImport {
+                    module: "$root".to_string(),
+                    name: name.to_string(),
+                    item: WitItem::world(resolve, world),
+                });
+            }
+        }
+        symbols
     }
 
     fn import_interface(
@@ -473,36 +834,61 @@ impl WorldGenerator for RustWasm {
         let exports = mem::take(&mut self.export_modules);
         self.emit_modules(exports);
 
-        self.src.push_str("\n#[cfg(target_arch = \"wasm32\")]\n");
-
-        // The custom section name here must start with "component-type" but
-        // otherwise is attempted to be unique here to ensure that this doesn't get
-        // concatenated to other custom sections by LLD by accident since LLD will
-        // concatenate custom sections of the same name.
-        self.src
-            .push_str(&format!("#[link_section = \"component-type:{}\"]\n", name,));
-
-        let mut producers = wasm_metadata::Producers::empty();
-        producers.add(
-            "processed-by",
-            env!("CARGO_PKG_NAME"),
-            env!("CARGO_PKG_VERSION"),
-        );
+        let payloads = mem::take(&mut self.async_payloads);
+        if !payloads.is_empty() {
+            let mut gen = self.interface(Identifier::World(world), None, resolve, true);
+            gen.generate_async_payloads(&payloads);
+            let src = gen.finish();
+            self.src.push_str(&src);
+        }
 
-        let component_type = wit_component::metadata::encode(
-            resolve,
-            world,
-            wit_component::StringEncoding::UTF8,
-            Some(&producers),
-        )
-        .unwrap();
+        if !self.opts.mapped_types.is_empty() {
+            self.src.push_str(
+                "
+                /// Conversions between a custom Rust type configured through
+                /// `mapped_types` and the type generated for it.
+                pub trait MappedType<Wit>: Sized {
+                    /// Converts the generated type to the custom type.
+                    fn from_wit(wit: Wit) -> Self;
+                    /// Converts the custom type to the generated type.
+                    fn to_wit(&self) -> Wit;
+                }
+            ",
+            );
+        }
 
-        self.src.push_str("#[doc(hidden)]\n");
-        self.src.push_str(&format!(
-            "pub static __WIT_BINDGEN_COMPONENT_TYPE: [u8; {}] = ",
-            component_type.len()
-        ));
-        self.src.push_str(&format!("{:?};\n", component_type));
+        if !self.opts.skip_component_type {
+            self.src.push_str("\n#[cfg(target_arch = \"wasm32\")]\n");
+
+            // The custom section name here must start with "component-type" but
+            // otherwise is attempted to be unique here to ensure that this doesn't get
+            // concatenated to other custom sections by LLD by accident since LLD will
+            // concatenate custom sections of the same name.
+            self.src
+                .push_str(&format!("#[link_section = \"component-type:{}\"]\n", name,));
+
+            let mut producers = wasm_metadata::Producers::empty();
+            producers.add(
+                "processed-by",
+                env!("CARGO_PKG_NAME"),
+                env!("CARGO_PKG_VERSION"),
+            );
+
+            let component_type = wit_component::metadata::encode(
+                resolve,
+                world,
+                wit_component::StringEncoding::UTF8,
+                Some(&producers),
+            )
+            .unwrap();
+
+            self.src.push_str("#[doc(hidden)]\n");
+            self.src.push_str(&format!(
+                "pub static __WIT_BINDGEN_COMPONENT_TYPE: [u8; {}] = ",
+                component_type.len()
+            ));
+            self.src.push_str(&format!("{:?};\n", component_type));
+        }
 
         self.src.push_str(
             "
@@ -513,6 +899,11 @@ impl WorldGenerator for RustWasm {
         ",
         );
 
+        let shims = mem::take(&mut self.export_shims);
+        if self.opts.export_macro {
+            self.emit_export_macro(name, &shims);
+        }
+
         if self.opts.stubs {
             self.src.push_str("\n#[derive(Debug)]\npub struct Stub;\n");
             let world_id = world;
@@ -534,9 +925,21 @@ impl WorldGenerator for RustWasm {
                         funcs.push(func);
                     }
                     WorldItem::Interface(id) => {
-                        for (resource, funcs) in
-                            group_by_resource(resolve.interfaces[*id].functions.values())
-                        {
+                        let mut groups =
+                            group_by_resource(resolve.interfaces[*id].functions.values());
+                        // With `export_macro` every resource is implemented
+                        // through an associated type of `Guest`.
+                        let mut resources = Vec::new();
+                        if self.opts.export_macro {
+                            for ty in resolve.interfaces[*id].types.values() {
+                                if let TypeDefKind::Resource = resolve.types[*ty].kind {
+                                    resources.push(*ty);
+                                    groups.entry(Some(*ty)).or_default();
+                                    groups.entry(None).or_default();
+                                }
+                            }
+                        }
+                        for (resource, funcs) in groups {
                             let mut gen =
                                 self.interface(Identifier::World(world_id), None, resolve, false);
                             let pkg = pkg.map(|pid| {
@@ -544,7 +947,7 @@ impl WorldGenerator for RustWasm {
                                 let package_module = name_package_module(resolve, pid);
                                 (namespace, package_module)
                             });
-                            gen.generate_stub(resource, pkg, name, true, &funcs);
+                            gen.generate_stub(resource, pkg, name, true, &resources, &funcs);
                             let stub = gen.finish();
                             self.src.push_str(&stub);
                         }
@@ -555,7 +958,7 @@ impl WorldGenerator for RustWasm {
 
             for (resource, funcs) in group_by_resource(funcs.into_iter()) {
                 let mut gen = self.interface(Identifier::World(world_id), None, resolve, false);
-                gen.generate_stub(resource, None, &world.name, false, &funcs);
+                gen.generate_stub(resource, None, &world.name, false, &[], &funcs);
                 let stub = gen.finish();
                 self.src.push_str(&stub);
             }
@@ -588,6 +991,7 @@ impl WorldGenerator for RustWasm {
 
         let module_name = name.to_snake_case();
         files.push(&format!("{module_name}.rs"), src.as_bytes());
+        files.set_source(&format!("{module_name}.rs"), WitItem::world(resolve, world));
     }
 }
 
@@ -692,12 +1096,59 @@ impl fmt::Display for Ownership {
     }
 }
 
+#[derive(Default, Debug, Clone)]
+pub enum AsyncExports {
+    /// No exported functions are `async`.
+    #[default]
+    None,
+
+    /// Only the exported functions with the listed names are `async`.
+    Some(Vec<String>),
+
+    /// All exported functions are `async`.
+    All,
+}
+
+impl AsyncExports {
+    fn is_async(&self, name: &str) -> bool {
+        match self {
+            AsyncExports::None => false,
+            AsyncExports::Some(names) => names.iter().any(|n| n == name),
+            AsyncExports::All => true,
+        }
+    }
+}
+
+impl FromStr for AsyncExports {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "all" => Ok(Self::All),
+            _ => Ok(Self::Some(s.split(',').map(|s| s.to_string()).collect())),
+        }
+    }
+}
+
+impl fmt::Display for AsyncExports {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AsyncExports::None => f.write_str("none"),
+            AsyncExports::Some(names) => f.write_str(&names.join(",")),
+            AsyncExports::All => f.write_str("all"),
+        }
+    }
+}
+
 #[derive(Default)]
 struct FnSig {
     async_: bool,
     unsafe_: bool,
     private: bool,
     use_item_name: bool,
+    /// Overrides the name of the function.
+    name: Option<String>,
     generics: Option<String>,
     self_arg: Option<String>,
     self_is_first_param: bool,
@@ -845,3 +1296,84 @@ fn dealias(resolve: &Resolve, mut id: TypeId) -> TypeId {
         }
     }
 }
+
+/// Returns the keys of `mapped_types` which map the type `id` itself, rather
+/// than through a `use` of it.
+///
+/// Anonymous types are keyed by their WIT syntax, such as `list<u8>`.
+fn mapped_type_keys(resolve: &Resolve, id: TypeId) -> Vec<String> {
+    let ty = &resolve.types[id];
+    let Some(name) = &ty.name else {
+        return anonymous_type_key(resolve, id).into_iter().collect();
+    };
+    let mut keys = match ty.owner {
+        TypeOwner::World(_) => vec![name.clone()],
+        TypeOwner::Interface(iface) => {
+            let interface = &resolve.interfaces[iface];
+            let (Some(package), Some(iface_name)) = (interface.package, &interface.name) else {
+                return Vec::new();
+            };
+            let package = &resolve.packages[package].name;
+            let mut keys = vec![format!(
+                "{}:{}/{iface_name}/{name}",
+                package.namespace, package.name
+            )];
+            // Versions may be omitted as for `exports`.
+            if let (Some(_), Some(id)) = (&package.version, resolve.id_of(iface)) {
+                keys.push(format!("{id}/{name}"));
+            }
+            keys
+        }
+        TypeOwner::None => Vec::new(),
+    };
+    // Named types such as `type bytes = list<u8>` are also occurrences of
+    // their anonymous WIT syntax.
+    keys.extend(anonymous_type_key(resolve, id));
+    keys
+}
+
+/// Returns the WIT syntax of the anonymous type `id`, referring to named
+/// types by their name, if it's a type which can be mapped.
+fn anonymous_type_key(resolve: &Resolve, id: TypeId) -> Option<String> {
+    let key = |ty: &Type| -> Option<String> {
+        Some(match ty {
+            Type::Bool => "bool".to_string(),
+            Type::U8 => "u8".to_string(),
+            Type::U16 => "u16".to_string(),
+            Type::U32 => "u32".to_string(),
+            Type::U64 => "u64".to_string(),
+            Type::S8 => "s8".to_string(),
+            Type::S16 => "s16".to_string(),
+            Type::S32 => "s32".to_string(),
+            Type::S64 => "s64".to_string(),
+            Type::Float32 => "float32".to_string(),
+            Type::Float64 => "float64".to_string(),
+            Type::Char => "char".to_string(),
+            Type::String => "string".to_string(),
+            Type::Id(id) => match &resolve.types[*id].name {
+                Some(name) => name.clone(),
+                None => anonymous_type_key(resolve, *id)?,
+            },
+        })
+    };
+    Some(match &resolve.types[id].kind {
+        TypeDefKind::List(ty) => format!("list<{}>", key(ty)?),
+        TypeDefKind::Option(ty) => format!("option<{}>", key(ty)?),
+        TypeDefKind::Tuple(tuple) => format!(
+            "tuple<{}>",
+            tuple
+                .types
+                .iter()
+                .map(key)
+                .collect::<Option<Vec<_>>>()?
+                .join(", ")
+        ),
+        TypeDefKind::Result(result) => match (&result.ok, &result.err) {
+            (None, None) => "result".to_string(),
+            (Some(ok), None) => format!("result<{}>", key(ok)?),
+            (None, Some(err)) => format!("result<_, {}>", key(err)?),
+            (Some(ok), Some(err)) => format!("result<{}, {}>", key(ok)?, key(err)?),
+        },
+        _ => return None,
+    })
+}
@@ -1,6 +1,24 @@
 #![allow(unused_macros)]
 
 mod codegen_tests {
+    macro_rules! export_macro_test {
+        ($id:ident $test:tt) => {
+            mod export_macro {
+                wit_bindgen::generate!({
+                    path: $test,
+                    stubs,
+                    export_macro,
+                    export_prefix: "[export-macro]",
+                });
+
+                export!(Stub with_types_in self);
+
+                #[test]
+                fn works() {}
+            }
+        };
+    }
+
     macro_rules! codegen_test {
         ($id:ident $name:tt $test:tt) => {
             mod $id {
@@ -31,6 +49,35 @@ mod codegen_tests {
                     #[test]
                     fn works() {}
                 }
+
+                export_macro_test!($id $test);
+
+                mod mock_imports {
+                    wit_bindgen::generate!({
+                        path: $test,
+                        stubs,
+                        mock_imports,
+                        export_prefix: "[mock-imports]",
+                    });
+
+                    #[test]
+                    fn works() {}
+                }
+
+                mod serde {
+                    wit_bindgen::generate!({
+                        path: $test,
+                        ownership: Borrowing {
+                            duplicate_if_necessary: true
+                        },
+                        stubs,
+                        serde,
+                        export_prefix: "[serde]",
+                    });
+
+                    #[test]
+                    fn works() {}
+                }
             }
 
         };
@@ -346,3 +393,1050 @@ mod custom_derives {
         }
     }
 }
+
+mod explicit_deps {
+    wit_bindgen::generate!({
+        inline: "
+            package my:inline;
+
+            world foo {
+                import my:dep/a@0.1.0;
+                import my:dep/a@0.2.0;
+            }
+        ",
+        deps: [
+            "../../tests/codegen/multiversion/deps/v1",
+            "../../tests/codegen/multiversion/deps/v2/root.wit",
+        ],
+    });
+
+    #[allow(dead_code)]
+    fn test() {
+        let _: u8 = my::dep0_2_0::a::x();
+    }
+}
+
+mod futures_and_streams {
+    wit_bindgen::generate!({
+        inline: "
+            package my:inline;
+
+            interface io {
+                record chunk {
+                    data: list<u8>,
+                    last: bool,
+                }
+
+                type body = stream<chunk>;
+
+                fetch: func(url: string) -> future<result<list<u8>, string>>;
+                open: func(path: string) -> body;
+                send: func(body: body, done: future);
+            }
+
+            interface handler {
+                use io.{chunk};
+
+                handle: func(request: string) -> string;
+                echo: func(input: stream<chunk>) -> future<u32>;
+            }
+
+            world service {
+                import io;
+                export handler;
+            }
+        ",
+        exports: {
+            "my:inline/handler": Component
+        },
+        async_exports: ["handle"],
+        skip_component_type,
+    });
+
+    use exports::my::inline::handler::Chunk;
+    use my::inline::io;
+    use wit_bindgen::rt::async_support::{spawn, yield_now, FutureReader, StreamReader};
+
+    struct Component;
+
+    impl exports::my::inline::handler::Guest for Component {
+        async fn handle(request: String) -> String {
+            // Imports which return a future or stream can be awaited.
+            let _response: Option<Result<Vec<u8>, String>> = io::fetch(&request).await;
+            let mut body: io::Body = io::open(&request);
+            while let Some(chunk) = body.next().await {
+                if chunk.last {
+                    break;
+                }
+            }
+
+            // Futures and streams can also be created and passed to imports,
+            // with their writable ends driven from a separately spawned task.
+            let (writer, reader) = wit_future::new::<()>();
+            let (mut body_writer, body) = wit_stream::new::<io::Chunk>();
+            io::send(body, reader);
+            let task = spawn(async move {
+                let written = body_writer.write(Vec::new()).await;
+                yield_now().await;
+                writer.write(()).await;
+                written
+            });
+            let _written: usize = task.await;
+
+            request
+        }
+
+        fn echo(input: StreamReader<Chunk>) -> FutureReader<u32> {
+            drop(input);
+            let (_writer, reader) = wit_future::new();
+            reader
+        }
+    }
+}
+
+mod futures_and_streams_glue {
+    wit_bindgen::generate!({
+        inline: "
+            package my:async-glue;
+
+            interface relay {
+                forward: func(input: stream<u8>) -> future<u32>;
+            }
+
+            world component {
+                export relay;
+            }
+        ",
+        export_macro,
+        skip_component_type,
+    });
+
+    use exports::my::async_glue::relay::{self, Guest};
+    use wit_bindgen::rt::async_support::{FutureReader, StreamReader};
+
+    struct Component;
+
+    impl Guest for Component {
+        fn forward(input: StreamReader<u8>) -> FutureReader<u32> {
+            let handle = input.into_handle();
+            unsafe {
+                FutureReader::from_handle(handle + 1, <u32 as wit_future::FuturePayload>::VTABLE)
+            }
+        }
+    }
+
+    #[test]
+    fn handles_are_lifted_and_lowered() {
+        // The stream's handle is lifted into a `StreamReader` and the
+        // returned `FutureReader` is lowered back to its handle.
+        let future = unsafe { relay::_export_forward_cabi::<Component>(7) };
+        assert_eq!(future, 8);
+    }
+}
+
+mod export_macro {
+    mod bindings {
+        wit_bindgen::generate!({
+            inline: "
+                package my:export-macro;
+
+                interface counter {
+                    record stats {
+                        count: u32,
+                        label: string,
+                    }
+                    get-stats: func(label: string) -> stats;
+                }
+
+                world component {
+                    export counter;
+                    export export-macro-greet: func(name: string) -> string;
+                }
+            ",
+            export_macro,
+            default_bindings_module: "crate::export_macro::bindings",
+        });
+    }
+
+    use bindings::exports::my::export_macro::counter::{self, Stats};
+
+    // The implementing type is only named where `export!` is invoked, not
+    // when the bindings are generated.
+    struct Component;
+
+    impl bindings::Guest for Component {
+        fn export_macro_greet(name: String) -> String {
+            format!("hello {name}")
+        }
+    }
+
+    impl counter::Guest for Component {
+        fn get_stats(label: String) -> Stats {
+            Stats { count: 0, label }
+        }
+    }
+
+    bindings::export!(Component);
+}
+
+mod export_macro_resources {
+    mod bindings {
+        wit_bindgen::generate!({
+            inline: "
+                package my:export-macro-resources;
+
+                interface counters {
+                    resource counter {
+                        constructor(start: u32);
+                        next: func() -> u32;
+                        add: static func(a: u32, b: u32) -> u32;
+                    }
+                }
+
+                world component {
+                    export counters;
+                }
+            ",
+            export_macro,
+            default_bindings_module: "crate::export_macro_resources::bindings",
+        });
+    }
+
+    use bindings::exports::my::export_macro_resources::counters::{self, Counter, GuestCounter};
+    use std::cell::Cell;
+
+    struct Component;
+
+    // Resources are implemented by associated types, so `export!` also
+    // provides their implementations.
+    impl counters::Guest for Component {
+        type Counter = MyCounter;
+    }
+
+    struct MyCounter {
+        next: Cell<u32>,
+    }
+
+    impl GuestCounter for MyCounter {
+        fn new(start: u32) -> Self {
+            MyCounter {
+                next: Cell::new(start),
+            }
+        }
+
+        fn next(&self) -> u32 {
+            let next = self.next.get();
+            self.next.set(next + 1);
+            next
+        }
+
+        fn add(a: u32, b: u32) -> u32 {
+            a + b
+        }
+    }
+
+    bindings::export!(Component);
+
+    #[test]
+    fn resource_functions_use_the_associated_type() {
+        let sum = unsafe { counters::_export_static_counter_add_cabi::<Component>(2, 3) };
+        assert_eq!(sum, 5);
+    }
+
+    #[test]
+    fn resources_wrap_their_implementation() {
+        let mut counter = Counter::new(MyCounter::new(7));
+        assert_eq!(counter.get::<MyCounter>().next(), 7);
+        counter.get_mut::<MyCounter>().next.set(10);
+        assert_eq!(counter.get::<MyCounter>().next(), 10);
+    }
+}
+
+mod mock_imports {
+    wit_bindgen::generate!({
+        inline: "
+            package my:mock-imports;
+
+            interface kv {
+                record entry {
+                    key: string,
+                    value: list<u8>,
+                }
+                get: func(key: string) -> option<list<u8>>;
+                set: func(entry: entry);
+            }
+
+            world component {
+                import kv;
+                import log: func(msg: string);
+            }
+        ",
+        mock_imports,
+    });
+
+    use my::mock_imports::kv;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::rc::Rc;
+
+    /// Business logic exercising the imports which can run natively.
+    fn bump(key: &str) -> u8 {
+        let count = kv::get(key).map(|v| v[0]).unwrap_or(0) + 1;
+        kv::set(&kv::Entry {
+            key: key.to_string(),
+            value: vec![count],
+        });
+        log(&format!("{key} = {count}"));
+        count
+    }
+
+    #[derive(Default)]
+    struct Store(RefCell<HashMap<String, Vec<u8>>>);
+
+    impl kv::Mock for Rc<Store> {
+        fn get(&self, key: &str) -> Option<Vec<u8>> {
+            self.0.borrow().get(key).cloned()
+        }
+
+        fn set(&self, entry: &kv::Entry) {
+            let entry = entry.clone();
+            self.0.borrow_mut().insert(entry.key, entry.value);
+        }
+    }
+
+    #[derive(Default)]
+    struct Log(RefCell<Vec<String>>);
+
+    impl Mock for Rc<Log> {
+        fn log(&self, msg: &str) {
+            self.0.borrow_mut().push(msg.to_string());
+        }
+    }
+
+    #[test]
+    fn imports_are_mocked() {
+        let store = Rc::new(Store::default());
+        let logs = Rc::new(Log::default());
+        kv::set_mock(store.clone());
+        set_mock(logs.clone());
+
+        assert_eq!(bump("a"), 1);
+        assert_eq!(bump("a"), 2);
+        assert_eq!(bump("b"), 1);
+        assert_eq!(store.0.borrow()["a"], [2]);
+        assert_eq!(*logs.0.borrow(), ["a = 1", "a = 2", "b = 1"]);
+
+        assert!(kv::clear_mock().is_some());
+        assert!(std::panic::catch_unwind(|| kv::get("a")).is_err());
+    }
+}
+
+mod mock_resources {
+    wit_bindgen::generate!({
+        inline: "
+            package my:mock-resources;
+
+            interface files {
+                resource file {
+                    constructor(path: string);
+                    read: func(len: u32) -> list<u8>;
+                    exists: static func(path: string) -> bool;
+                }
+                stdin: func() -> file;
+            }
+
+            world component {
+                import files;
+            }
+        ",
+        mock_imports,
+    });
+
+    use my::mock_resources::files::{self, File};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Business logic using an imported resource which can run natively.
+    fn head(path: &str) -> Option<Vec<u8>> {
+        if !File::exists(path) {
+            return None;
+        }
+        Some(File::new(path).read(2))
+    }
+
+    /// Files are identified by the index of their contents.
+    #[derive(Default)]
+    struct Fs {
+        contents: RefCell<Vec<Vec<u8>>>,
+        dropped: RefCell<Vec<u32>>,
+    }
+
+    impl files::Mock for Rc<Fs> {
+        fn file_new(&self, path: &str) -> u32 {
+            let mut contents = self.contents.borrow_mut();
+            contents.push(path.as_bytes().to_vec());
+            contents.len() as u32 - 1
+        }
+
+        fn file_read(&self, self_: u32, len: u32) -> Vec<u8> {
+            let contents = &self.contents.borrow()[self_ as usize];
+            contents[..contents.len().min(len as usize)].to_vec()
+        }
+
+        fn file_exists(&self, path: &str) -> bool {
+            !path.is_empty()
+        }
+
+        fn stdin(&self) -> File {
+            File::from_mock_handle(self.file_new("stdin"))
+        }
+
+        fn file_drop(&self, handle: u32) {
+            self.dropped.borrow_mut().push(handle);
+        }
+    }
+
+    #[test]
+    fn resource_functions_are_mocked() {
+        let fs = Rc::new(Fs::default());
+        files::set_mock(fs.clone());
+
+        assert_eq!(head("abc"), Some(b"ab".to_vec()));
+        assert_eq!(head(""), None);
+        assert_eq!(files::stdin().read(10), b"stdin");
+        assert_eq!(*fs.dropped.borrow(), [0, 1]);
+    }
+}
+
+mod serde {
+    wit_bindgen::generate!({
+        inline: "
+            package my:serde;
+
+            interface types {
+                record point {
+                    x-coord: s32,
+                    label: option<string>,
+                }
+                enum color { light-red, blue }
+                flags perms { read, write }
+                variant shape {
+                    circle(u32),
+                    polygon(list<point>),
+                    empty,
+                }
+                resource handle;
+                record holder {
+                    handle: handle,
+                }
+
+                draw: func(shape: shape, color: color, perms: perms) -> result<point, string>;
+                hold: func(holder: holder);
+            }
+
+            world component {
+                import types;
+            }
+        ",
+        serde,
+    });
+
+    use my::serde::types::{Color, Perms, Point, Shape};
+
+    #[test]
+    fn round_trips_through_json() {
+        let shape = Shape::Polygon(vec![Point {
+            x_coord: 1,
+            label: Some("a".to_string()),
+        }]);
+        let json = serde_json::to_string(&shape).unwrap();
+        assert_eq!(json, r#"{"polygon":[{"x-coord":1,"label":"a"}]}"#);
+        let back: Shape = serde_json::from_str(&json).unwrap();
+        assert!(matches!(&back, Shape::Polygon(p) if p[0].x_coord == 1));
+
+        assert_eq!(
+            serde_json::to_string(&Color::LightRed).unwrap(),
+            r#""light-red""#
+        );
+        assert_eq!(serde_json::to_string(&Shape::Empty).unwrap(), r#""empty""#);
+
+        let perms = Perms::READ | Perms::WRITE;
+        let json = serde_json::to_string(&perms).unwrap();
+        assert_eq!(serde_json::from_str::<Perms>(&json).unwrap(), perms);
+
+        let result: Result<Point, String> = serde_json::from_str(r#"{"Err":"no"}"#).unwrap();
+        assert_eq!(result.unwrap_err(), "no");
+    }
+}
+
+mod comparison_derives {
+    use std::collections::{BTreeSet, HashMap};
+
+    wit_bindgen::generate!({
+        inline: "
+            package my:comparison-derives;
+
+            interface keys {
+                record key {
+                    name: string,
+                    parts: list<tuple<u8, option<string>>>,
+                }
+                variant id {
+                    named(key),
+                    index(u64),
+                }
+                record measurement {
+                    key: key,
+                    value: float64,
+                }
+
+                lookup: func(id: id) -> option<measurement>;
+            }
+
+            world component {
+                import keys;
+            }
+        ",
+        comparison_derives,
+    });
+
+    use my::comparison_derives::keys::{Id, Key, Measurement};
+
+    #[test]
+    fn keys_are_hashable_and_ordered() {
+        let key = Key {
+            name: "a".to_string(),
+            parts: vec![(1, None)],
+        };
+        let mut map = HashMap::new();
+        map.insert(Id::Named(key.clone()), 1);
+        map.insert(Id::Index(2), 2);
+        assert_eq!(map[&Id::Named(key.clone())], 1);
+
+        let set = BTreeSet::from([Id::Index(3), Id::Index(1)]);
+        assert_eq!(set.first(), Some(&Id::Index(1)));
+
+        // Types with floats are only partially comparable.
+        let m = Measurement { key, value: 1.0 };
+        assert!(m == m.clone());
+        assert!(m
+            .partial_cmp(&Measurement {
+                value: 2.0,
+                ..m.clone()
+            })
+            .unwrap()
+            .is_lt());
+    }
+}
+
+mod mapped_types {
+    wit_bindgen::generate!({
+        inline: "
+            package my:mapped-types;
+
+            interface geometry {
+                record point {
+                    x: float32,
+                    y: float32,
+                }
+                record segment {
+                    start: point,
+                    end: point,
+                }
+                type bytes = list<u8>;
+
+                translate: func(p: point, by: point) -> point;
+                checksum: func(data: bytes) -> u8;
+                pair: func() -> tuple<point, point>;
+                centroid: func(points: list<point>) -> option<point>;
+                split: func(s: segment) -> (start: point, end: point);
+            }
+
+            world component {
+                use geometry.{point};
+                type label = string;
+
+                import geometry;
+                import describe: func(p: point) -> label;
+                export geometry;
+            }
+        ",
+        mapped_types: {
+            "my:mapped-types/geometry/point": crate::mapped_types::Vec2,
+            "list<u8>": crate::mapped_types::Bytes,
+            "label": crate::mapped_types::Text,
+        },
+        exports: {
+            "my:mapped-types/geometry": Component,
+        },
+        mock_imports,
+    });
+
+    use my::mapped_types::geometry;
+    use std::rc::Rc;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct Vec2(f32, f32);
+
+    impl MappedType<geometry::Point> for Vec2 {
+        fn from_wit(wit: geometry::Point) -> Self {
+            Vec2(wit.x, wit.y)
+        }
+
+        fn to_wit(&self) -> geometry::Point {
+            geometry::Point {
+                x: self.0,
+                y: self.1,
+            }
+        }
+    }
+
+    impl MappedType<exports::my::mapped_types::geometry::Point> for Vec2 {
+        fn from_wit(wit: exports::my::mapped_types::geometry::Point) -> Self {
+            Vec2(wit.x, wit.y)
+        }
+
+        fn to_wit(&self) -> exports::my::mapped_types::geometry::Point {
+            exports::my::mapped_types::geometry::Point {
+                x: self.0,
+                y: self.1,
+            }
+        }
+    }
+
+    pub struct Bytes(Rc<[u8]>);
+
+    impl MappedType<Vec<u8>> for Bytes {
+        fn from_wit(wit: Vec<u8>) -> Self {
+            Bytes(wit.into())
+        }
+
+        fn to_wit(&self) -> Vec<u8> {
+            self.0.to_vec()
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    pub struct Text(Box<str>);
+
+    impl MappedType<String> for Text {
+        fn from_wit(wit: String) -> Self {
+            Text(wit.into())
+        }
+
+        fn to_wit(&self) -> String {
+            self.0.to_string()
+        }
+    }
+
+    pub struct Component;
+
+    impl exports::my::mapped_types::geometry::Guest for Component {
+        fn translate(p: Vec2, by: Vec2) -> Vec2 {
+            Vec2(p.0 + by.0, p.1 + by.1)
+        }
+
+        fn checksum(data: Bytes) -> u8 {
+            data.0.iter().fold(0, |a, b| a.wrapping_add(*b))
+        }
+
+        fn pair() -> (Vec2, Vec2) {
+            (Vec2(0.0, 0.0), Vec2(1.0, 1.0))
+        }
+
+        fn centroid(points: Vec<Vec2>) -> Option<Vec2> {
+            let n = points.len() as f32;
+            let sum = points
+                .into_iter()
+                .reduce(|a, b| Vec2(a.0 + b.0, a.1 + b.1))?;
+            Some(Vec2(sum.0 / n, sum.1 / n))
+        }
+
+        fn split(s: exports::my::mapped_types::geometry::Segment) -> (Vec2, Vec2) {
+            (s.start, s.end)
+        }
+    }
+
+    struct Geometry;
+
+    impl geometry::Mock for Geometry {
+        fn translate(&self, p: &Vec2, by: &Vec2) -> Vec2 {
+            <Component as exports::my::mapped_types::geometry::Guest>::translate(*p, *by)
+        }
+
+        fn checksum(&self, data: &Bytes) -> u8 {
+            data.0.len() as u8
+        }
+
+        fn pair(&self) -> (Vec2, Vec2) {
+            <Component as exports::my::mapped_types::geometry::Guest>::pair()
+        }
+
+        fn centroid(&self, points: &[Vec2]) -> Option<Vec2> {
+            <Component as exports::my::mapped_types::geometry::Guest>::centroid(points.to_vec())
+        }
+
+        fn split(&self, s: geometry::Segment) -> (Vec2, Vec2) {
+            (s.start, s.end)
+        }
+    }
+
+    struct Describe;
+
+    impl Mock for Describe {
+        fn describe(&self, p: &Vec2) -> Text {
+            Text(format!("({}, {})", p.0, p.1).into())
+        }
+    }
+
+    #[test]
+    fn functions_use_mapped_types() {
+        geometry::set_mock(Geometry);
+        set_mock(Describe);
+
+        let p = geometry::translate(&Vec2(1.0, 2.0), &Vec2(0.5, 0.5));
+        assert_eq!(p, Vec2(1.5, 2.5));
+        assert_eq!(geometry::checksum(&Bytes(Rc::new([1, 2, 3]))), 3);
+        assert_eq!(describe(&p), Text("(1.5, 2.5)".into()));
+
+        // Nested uses of mapped types use the custom type too.
+        assert_eq!(geometry::pair(), (Vec2(0.0, 0.0), Vec2(1.0, 1.0)));
+        assert_eq!(
+            geometry::centroid(&[Vec2(0.0, 0.0), Vec2(2.0, 4.0)]),
+            Some(Vec2(1.0, 2.0))
+        );
+        assert_eq!(geometry::centroid(&[]), None);
+        let s = geometry::Segment {
+            start: Vec2(1.0, 1.0),
+            end: p,
+        };
+        assert_eq!(geometry::split(s), (Vec2(1.0, 1.0), p));
+
+        let wit = <Vec2 as MappedType<geometry::Point>>::to_wit(&p);
+        assert_eq!(<Vec2 as MappedType<_>>::from_wit(wit), p);
+    }
+}
+
+mod mapped_types_glue {
+    wit_bindgen::generate!({
+        inline: "
+            package my:mapped-types-glue;
+
+            interface units {
+                record meters {
+                    value: float32,
+                }
+                record point {
+                    x: float32,
+                    y: float32,
+                }
+                record segment {
+                    start: point,
+                    end: point,
+                }
+                type id = u32;
+
+                scale: func(m: meters, by: float32) -> meters;
+                length: func(p: point) -> meters;
+                next: func(id: id) -> id;
+                span: func(s: segment) -> meters;
+                norm: func(p: option<point>) -> float32;
+                wrap: func(id: id) -> tuple<id>;
+                dot: func(a: tuple<float32, float32>, b: tuple<float32, float32>) -> float32;
+            }
+
+            world component {
+                export units;
+            }
+        ",
+        mapped_types: {
+            "my:mapped-types-glue/units/meters": crate::mapped_types_glue::Meters,
+            "my:mapped-types-glue/units/point": crate::mapped_types_glue::Vec2,
+            "my:mapped-types-glue/units/id": crate::mapped_types_glue::Id,
+            "tuple<float32, float32>": crate::mapped_types_glue::Vec2,
+        },
+        export_macro,
+    });
+
+    use exports::my::mapped_types_glue::units::{self, Guest};
+    use std::num::NonZeroU32;
+
+    pub struct Meters(f64);
+
+    impl MappedType<units::Meters> for Meters {
+        fn from_wit(wit: units::Meters) -> Self {
+            Meters(wit.value.into())
+        }
+
+        fn to_wit(&self) -> units::Meters {
+            units::Meters {
+                value: self.0 as f32,
+            }
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    pub struct Vec2(f32, f32);
+
+    impl MappedType<units::Point> for Vec2 {
+        fn from_wit(wit: units::Point) -> Self {
+            Vec2(wit.x, wit.y)
+        }
+
+        fn to_wit(&self) -> units::Point {
+            units::Point {
+                x: self.0,
+                y: self.1,
+            }
+        }
+    }
+
+    impl MappedType<(f32, f32)> for Vec2 {
+        fn from_wit(wit: (f32, f32)) -> Self {
+            Vec2(wit.0, wit.1)
+        }
+
+        fn to_wit(&self) -> (f32, f32) {
+            (self.0, self.1)
+        }
+    }
+
+    pub struct Id(NonZeroU32);
+
+    impl MappedType<u32> for Id {
+        fn from_wit(wit: u32) -> Self {
+            Id(NonZeroU32::new(wit).expect("ids are non-zero"))
+        }
+
+        fn to_wit(&self) -> u32 {
+            self.0.get()
+        }
+    }
+
+    struct Component;
+
+    impl Guest for Component {
+        fn scale(m: Meters, by: f32) -> Meters {
+            Meters(m.0 * f64::from(by))
+        }
+
+        fn length(p: Vec2) -> Meters {
+            Meters(f64::from(p.0.hypot(p.1)))
+        }
+
+        fn next(id: Id) -> Id {
+            Id(id.0.checked_add(1).unwrap())
+        }
+
+        fn span(s: units::Segment) -> Meters {
+            Self::length(Vec2(s.end.0 - s.start.0, s.end.1 - s.start.1))
+        }
+
+        fn norm(p: Option<Vec2>) -> f32 {
+            p.map_or(0.0, |p| p.0.hypot(p.1))
+        }
+
+        fn wrap(id: Id) -> (Id,) {
+            (Self::next(id),)
+        }
+
+        fn dot(a: Vec2, b: Vec2) -> f32 {
+            a.0 * b.0 + a.1 * b.1
+        }
+    }
+
+    #[test]
+    fn exports_convert_through_mapped_types() {
+        // Arguments are lifted into the generated types and converted with
+        // `from_wit`, and results are converted back with `to_wit`.
+        unsafe {
+            assert_eq!(units::_export_scale_cabi::<Component>(1.5, 2.0), 3.0);
+            assert_eq!(units::_export_length_cabi::<Component>(3.0, 4.0), 5.0);
+            assert_eq!(units::_export_next_cabi::<Component>(41), 42);
+        }
+    }
+
+    #[test]
+    fn nested_mapped_types_are_converted() {
+        unsafe {
+            assert_eq!(
+                units::_export_span_cabi::<Component>(1.0, 1.0, 4.0, 5.0),
+                5.0
+            );
+            assert_eq!(units::_export_norm_cabi::<Component>(1, 3.0, 4.0), 5.0);
+            assert_eq!(units::_export_norm_cabi::<Component>(0, 0.0, 0.0), 0.0);
+            assert_eq!(units::_export_wrap_cabi::<Component>(41), 42);
+            assert_eq!(
+                units::_export_dot_cabi::<Component>(1.0, 2.0, 3.0, 4.0),
+                11.0
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "ids are non-zero")]
+    fn exports_use_custom_conversions() {
+        unsafe {
+            units::_export_next_cabi::<Component>(0);
+        }
+    }
+}
+
+#[test]
+fn unsupported_async_types_are_diagnosed() {
+    use wit_bindgen_core::wit_parser::{Resolve, UnresolvedPackage};
+
+    let generate = |wit: &str, skip_component_type: bool| {
+        let mut resolve = Resolve::default();
+        let pkg = resolve
+            .push(UnresolvedPackage::parse("async.wit".as_ref(), wit).unwrap())
+            .unwrap();
+        let world = resolve.select_world(pkg, None).unwrap();
+        let opts = wit_bindgen_rust::Opts {
+            skip_component_type,
+            ..Default::default()
+        };
+        opts.build()
+            .generate(&resolve, world, &mut Default::default())
+            .unwrap_err()
+            .to_string()
+    };
+
+    let wit = "
+        package my:async;
+
+        interface channels {
+            type body = future<string>;
+            open: func() -> body;
+        }
+
+        world component {
+            import channels;
+        }
+    ";
+    assert_eq!(
+        generate(wit, false),
+        "the `component-type` custom section can't yet be encoded for worlds using `future` \
+         or `stream` types\n  --> in type `body` of interface `my:async/channels`\n  \
+         = help: enable `skip_component_type` and supply the world's type information when \
+         creating the component"
+    );
+
+    let wit = "
+        package my:async;
+
+        world component {
+            import open: func() -> stream<u8, string>;
+        }
+    ";
+    assert_eq!(
+        generate(wit, true),
+        "streams with an end type are not supported\n  --> in world `component`"
+    );
+}
+
+#[test]
+fn invalid_mapped_types_are_diagnosed() {
+    use wit_bindgen_core::wit_parser::{Resolve, UnresolvedPackage};
+    use wit_bindgen_rust::Ownership;
+
+    let wit = "
+        package my:blobs;
+
+        interface store {
+            record blob {
+                data: list<u8>,
+            }
+            put: func(b: blob);
+            get: func() -> blob;
+        }
+
+        world component {
+            import store;
+        }
+    ";
+    let mut resolve = Resolve::default();
+    let pkg = resolve
+        .push(UnresolvedPackage::parse("blobs.wit".as_ref(), wit).unwrap())
+        .unwrap();
+    let world = resolve.select_world(pkg, None).unwrap();
+    let generate = |key: &str, ownership: Ownership| {
+        let opts = wit_bindgen_rust::Opts {
+            mapped_types: [(key.to_string(), "crate::Blob".to_string())].into(),
+            ownership,
+            ..Default::default()
+        };
+        opts.build()
+            .generate(&resolve, world, &mut Default::default())
+            .map_err(|e| e.to_string())
+    };
+
+    assert!(generate("my:blobs/store/blob", Ownership::Owning).is_ok());
+    assert_eq!(
+        generate("my:blobs/store/blobs", Ownership::Owning).unwrap_err(),
+        "`mapped_types` key `my:blobs/store/blobs` doesn't name a type in the world\n  \
+         --> in world `component`\n  \
+         = help: keys are `ns:pkg/interface/type`, just the type name for types defined in \
+         the world, or the WIT syntax of an anonymous type such as `list<u8>`"
+    );
+    assert_eq!(
+        generate(
+            "my:blobs/store/blob",
+            Ownership::Borrowing {
+                duplicate_if_necessary: true
+            }
+        )
+        .unwrap_err(),
+        "mapped type `my:blobs/store/blob` has distinct borrowed and owned representations \
+         which isn't supported\n  \
+         --> in type `blob` of interface `my:blobs/store`\n  \
+         = help: use `ownership: Owning` to generate a single type"
+    );
+}
+
+mod comparison_derives_with_additional_derives {
+    use std::collections::HashSet;
+
+    wit_bindgen::generate!({
+        inline: "
+            package my:comparison-derives-with-additional-derives;
+
+            interface keys {
+                record key {
+                    name: string,
+                }
+                enum kind {
+                    a,
+                    b,
+                }
+
+                lookup: func(key: key, kind: kind);
+            }
+
+            world component {
+                import keys;
+            }
+        ",
+        comparison_derives,
+        additional_derives: [std::hash::Hash, core::cmp::PartialEq, Eq],
+    });
+
+    use my::comparison_derives_with_additional_derives::keys::{Key, Kind};
+
+    #[test]
+    fn additional_derives_are_not_repeated() {
+        let set = HashSet::from([(
+            Key {
+                name: "a".to_string(),
+            },
+            Kind::A,
+        )]);
+        assert!(set.contains(&(
+            Key {
+                name: "a".to_string()
+            },
+            Kind::A
+        )));
+        assert!(Kind::A < Kind::B);
+    }
+}
@@ -1,7 +1,7 @@
 use anyhow::Result;
 use heck::{ToLowerCamelCase, ToShoutySnakeCase, ToUpperCamelCase};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     fmt::Write,
     iter, mem,
     ops::Deref,
@@ -10,16 +10,17 @@ use wit_bindgen_core::{
     abi::{self, AbiVariant, Bindgen, Bitcast, Instruction, LiftLower, WasmType},
     uwrite, uwriteln,
     wit_parser::{
-        Docs, Enum, Flags, FlagsRepr, Function, FunctionKind, Int, InterfaceId, Record, Resolve,
-        Result_, SizeAlign, Tuple, Type, TypeDef, TypeDefKind, TypeId, TypeOwner, Variant, WorldId,
-        WorldKey,
+        Docs, Enum, Flags, FlagsRepr, Function, FunctionKind, Handle, Int, InterfaceId, Record,
+        Resolve, Result_, Results, SizeAlign, Stream, Tuple, Type, TypeDef, TypeDefKind, TypeId,
+        TypeOwner, Variant, WorldId, WorldItem, WorldKey,
     },
-    Files, InterfaceGenerator as _, Ns, Source, WorldGenerator,
+    Files, InterfaceGenerator as _, Ns, Source, WitItem, WorldGenerator,
 };
 
 const IMPORTS: &str = "\
 import java.nio.charset.StandardCharsets;
 import java.util.ArrayList;
+import java.util.HashMap;
 
 import org.teavm.interop.Memory;
 import org.teavm.interop.Address;
@@ -33,6 +34,56 @@ pub struct Opts {
     /// Whether or not to generate a stub class for exported functions
     #[cfg_attr(feature = "clap", arg(long))]
     pub generate_stub: bool,
+
+    /// Whether or not to generate Java `record`s for WIT records
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub records: bool,
+
+    /// Whether or not to generate sealed interfaces, with a nested `record`
+    /// per case, for WIT variants
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub sealed_variants: bool,
+
+    /// Whether or not to represent WIT options as `java.util.Optional`s rather
+    /// than nullable values
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub optional: bool,
+
+    /// Whether or not to return the `ok` value of functions returning a
+    /// `result`, throwing a checked exception generated for the type of the
+    /// `err` value instead of returning a `Result`
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub exceptions: bool,
+
+    /// Root package of the generated classes, replacing the default `wit`;
+    /// e.g. `com.example` places the world class in `com.example.worlds`
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub package: Option<String>,
+
+    /// Remapping of interface names to the Java packages their classes are
+    /// generated in, e.g. `wasi:io/streams=com.example.io`
+    #[cfg_attr(feature = "clap", arg(long, value_parser = parse_packages, default_value = ""))]
+    pub packages: HashMap<String, String>,
+
+    /// Suffix of the stub classes generated by `--generate-stub`, defaulting
+    /// to `Impl`; this is also the class exported functions are dispatched to
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub stub_suffix: Option<String>,
+}
+
+#[cfg(feature = "clap")]
+fn parse_packages(s: &str) -> Result<HashMap<String, String>, String> {
+    if s.is_empty() {
+        return Ok(HashMap::default());
+    }
+    s.split(',')
+        .map(|entry| {
+            let (key, value) = entry.split_once('=').ok_or_else(|| {
+                format!("expected string of form `<key>=<value>[,<key>=<value>...]`; got `{s}`")
+            })?;
+            Ok((key.to_owned(), value.to_owned()))
+        })
+        .collect()
 }
 
 impl Opts {
@@ -44,6 +95,7 @@ impl Opts {
     }
 }
 
+#[derive(Clone, Copy, PartialEq)]
 enum Direction {
     Import,
     Export,
@@ -63,10 +115,20 @@ pub struct TeaVmJava {
     tuple_counts: HashSet<usize>,
     needs_cleanup: bool,
     needs_result: bool,
+    needs_wit_exception: bool,
+    /// Exception classes thrown for the `err` types of `result`s, by the
+    /// class they're nested in, mapped to the type of their payload.
+    exceptions: BTreeMap<String, BTreeMap<String, String>>,
     interface_fragments: HashMap<String, Vec<InterfaceFragment>>,
     world_fragments: Vec<InterfaceFragment>,
     sizes: SizeAlign,
     interface_names: HashMap<InterfaceId, String>,
+    /// The WIT interface each entry in `interface_fragments` was generated
+    /// for.
+    interface_sources: HashMap<String, WitItem>,
+    /// Resources defined by exported interfaces, which are backed by a rep
+    /// table rather than wrapping an imported handle.
+    exported_resources: HashSet<TypeId>,
 }
 
 impl TeaVmJava {
@@ -74,21 +136,117 @@ impl TeaVmJava {
         format!("{}.", self.name)
     }
 
-    fn interface<'a>(&'a mut self, resolve: &'a Resolve, name: &'a str) -> InterfaceGenerator<'a> {
+    /// Returns the source of the exception class `name` thrown with an `err`
+    /// value of type `payload`.
+    fn exception_class(&self, name: &str, payload: &str) -> String {
+        format!(
+            "
+            public static final class {name} extends {}WitException {{
+                public final {payload} value;
+
+                public {name}({payload} value) {{
+                    super(value);
+                    this.value = value;
+                }}
+            }}
+            ",
+            self.qualifier()
+        )
+    }
+
+    /// Returns the root package of the generated classes.
+    fn package(&self) -> &str {
+        self.opts.package.as_deref().unwrap_or("wit")
+    }
+
+    /// Returns the name of the stub class implementing the exports of the
+    /// class `name`.
+    fn stub_name(&self, name: &str) -> String {
+        format!(
+            "{name}{}",
+            self.opts.stub_suffix.as_deref().unwrap_or("Impl")
+        )
+    }
+
+    fn world_name(&self, resolve: &Resolve, world: WorldId) -> String {
+        format!(
+            "{}.worlds.{}",
+            self.package(),
+            resolve.worlds[world].name.to_upper_camel_case()
+        )
+    }
+
+    fn interface_name(&self, resolve: &Resolve, key: &WorldKey, direction: Direction) -> String {
+        let pkg = match key {
+            WorldKey::Name(_) => None,
+            WorldKey::Interface(id) => {
+                let pkg = resolve.interfaces[*id].package.unwrap();
+                Some(resolve.packages[pkg].name.clone())
+            }
+        };
+
+        let name = match key {
+            WorldKey::Name(name) => name,
+            WorldKey::Interface(id) => resolve.interfaces[*id].name.as_ref().unwrap(),
+        }
+        .to_upper_camel_case();
+
+        if let Some(package) = self.opts.packages.get(&resolve.name_world_key(key)) {
+            return format!("{package}.{name}");
+        }
+
+        format!(
+            "{}.{}.{}{name}",
+            self.package(),
+            match direction {
+                Direction::Import => "imports",
+                Direction::Export => "exports",
+            },
+            if let Some(name) = &pkg {
+                format!(
+                    "{}.{}.",
+                    name.namespace.to_java_ident(),
+                    name.name.to_java_ident()
+                )
+            } else {
+                String::new()
+            }
+        )
+    }
+
+    /// Returns the imports of each generated file.
+    fn imports(&self) -> String {
+        let mut imports = IMPORTS.to_string();
+        if self.opts.optional {
+            imports.push_str("\nimport java.util.Optional;");
+        }
+        imports
+    }
+
+    fn interface<'a>(
+        &'a mut self,
+        resolve: &'a Resolve,
+        name: &'a str,
+        key: Option<&'a WorldKey>,
+        direction: Direction,
+    ) -> InterfaceGenerator<'a> {
         InterfaceGenerator {
             src: String::new(),
             stub: String::new(),
             gen: self,
             resolve,
             name,
+            key,
+            direction,
         }
     }
 }
 
 impl WorldGenerator for TeaVmJava {
-    fn preprocess(&mut self, resolve: &Resolve, world: WorldId) {
-        self.name = world_name(resolve, world);
+    fn preprocess(&mut self, resolve: &Resolve, world: WorldId) -> Result<()> {
+        self.name = self.world_name(resolve, world);
         self.sizes.fill(resolve);
+        Ok(())
     }
 
     fn import_interface(
@@ -98,13 +256,17 @@ impl WorldGenerator for TeaVmJava {
         id: InterfaceId,
         _files: &mut Files,
     ) {
-        let name = interface_name(resolve, key, Direction::Import);
+        let name = self.interface_name(resolve, key, Direction::Import);
         self.interface_names.insert(id, name.clone());
-        let mut gen = self.interface(resolve, &name);
+        self.interface_sources
+            .insert(name.clone(), WitItem::interface(resolve, key));
+        let mut gen = self.interface(resolve, &name, Some(key), Direction::Import);
         gen.types(id);
 
         for (_, func) in resolve.interfaces[id].functions.iter() {
-            gen.import(&resolve.name_world_key(key), func);
+            if func.kind == FunctionKind::Freestanding {
+                gen.import(&resolve.name_world_key(key), func);
+            }
         }
 
         gen.add_interface_fragment();
@@ -117,11 +279,13 @@ impl WorldGenerator for TeaVmJava {
         funcs: &[(&str, &Function)],
         _files: &mut Files,
     ) {
-        let name = world_name(resolve, world);
-        let mut gen = self.interface(resolve, &name);
+        let name = self.world_name(resolve, world);
+        let mut gen = self.interface(resolve, &name, None, Direction::Import);
 
         for (_, func) in funcs {
-            gen.import("$root", func);
+            if func.kind == FunctionKind::Freestanding {
+                gen.import("$root", func);
+            }
         }
 
         gen.add_world_fragment();
@@ -134,13 +298,17 @@ impl WorldGenerator for TeaVmJava {
         id: InterfaceId,
         _files: &mut Files,
     ) -> Result<()> {
-        let name = interface_name(resolve, key, Direction::Export);
+        let name = self.interface_name(resolve, key, Direction::Export);
         self.interface_names.insert(id, name.clone());
-        let mut gen = self.interface(resolve, &name);
+        self.interface_sources
+            .insert(name.clone(), WitItem::interface(resolve, key));
+        let mut gen = self.interface(resolve, &name, Some(key), Direction::Export);
         gen.types(id);
 
         for (_, func) in resolve.interfaces[id].functions.iter() {
-            gen.export(Some(&resolve.name_world_key(key)), func);
+            if func.kind == FunctionKind::Freestanding {
+                gen.export(Some(&resolve.name_world_key(key)), func);
+            }
         }
 
         gen.add_interface_fragment();
@@ -154,8 +322,8 @@ impl WorldGenerator for TeaVmJava {
         funcs: &[(&str, &Function)],
         _files: &mut Files,
     ) -> Result<()> {
-        let name = world_name(resolve, world);
-        let mut gen = self.interface(resolve, &name);
+        let name = self.world_name(resolve, world);
+        let mut gen = self.interface(resolve, &name, None, Direction::Export);
 
         for (_, func) in funcs {
             gen.export(None, func);
@@ -172,8 +340,8 @@ impl WorldGenerator for TeaVmJava {
         types: &[(&str, TypeId)],
         _files: &mut Files,
     ) {
-        let name = world_name(resolve, world);
-        let mut gen = self.interface(resolve, &name);
+        let name = self.world_name(resolve, world);
+        let mut gen = self.interface(resolve, &name, None, Direction::Import);
 
         for (ty_name, ty) in types {
             gen.define_type(ty_name, *ty);
@@ -183,18 +351,19 @@ impl WorldGenerator for TeaVmJava {
     }
 
     fn finish(&mut self, resolve: &Resolve, id: WorldId, files: &mut Files) {
-        let name = world_name(resolve, id);
+        let name = self.world_name(resolve, id);
         let (package, name) = split_qualified_name(&name);
 
         let mut src = Source::default();
         let version = env!("CARGO_PKG_VERSION");
         wit_bindgen_core::generated_preamble(&mut src, version);
+        let imports = self.imports();
 
         uwrite!(
             src,
             "package {package};
 
-             {IMPORTS}
+             {imports}
              import org.teavm.interop.CustomSection;
 
              public final class {name} {{
@@ -330,6 +499,40 @@ impl WorldGenerator for TeaVmJava {
             )
         }
 
+        if self.needs_wit_exception {
+            src.push_str(
+                "
+                public static class WitException extends Exception {
+                    public WitException() {}
+
+                    protected WitException(Object value) {
+                        super(String.valueOf(value));
+                    }
+                }
+                ",
+            );
+        }
+
+        let mut exceptions = mem::take(&mut self.exceptions);
+        for (exception, payload) in exceptions.remove(&self.name).unwrap_or_default() {
+            src.push_str(&self.exception_class(&exception, &payload));
+        }
+        // Exceptions for types defined in an interface are nested alongside
+        // them.
+        for (class, exceptions) in exceptions {
+            let src = exceptions
+                .iter()
+                .map(|(exception, payload)| self.exception_class(exception, payload))
+                .collect();
+            self.interface_fragments
+                .entry(class)
+                .or_default()
+                .push(InterfaceFragment {
+                    src,
+                    stub: String::new(),
+                });
+        }
+
         if self.needs_cleanup {
             src.push_str(
                 "
@@ -361,7 +564,9 @@ impl WorldGenerator for TeaVmJava {
         src.push_str("}\n");
 
         let directory = package.replace('.', "/");
+        let world_source = WitItem::world(resolve, id);
         files.push(&format!("{directory}/{name}.java"), indent(&src).as_bytes());
+        files.set_source(&format!("{directory}/{name}.java"), world_source.clone());
 
         let generate_stub =
             |package: &str, name, fragments: &[InterfaceFragment], files: &mut Files| {
@@ -377,7 +582,7 @@ impl WorldGenerator for TeaVmJava {
                     &mut body,
                     "package {package};
 
-                 {IMPORTS}
+                 {imports}
 
                  public class {name} {{
                      {b}
@@ -393,16 +598,13 @@ impl WorldGenerator for TeaVmJava {
             };
 
         if self.opts.generate_stub {
-            generate_stub(
-                &package,
-                format!("{name}Impl"),
-                &self.world_fragments,
-                files,
-            );
+            let stub = self.stub_name(name);
+            generate_stub(&package, stub.clone(), &self.world_fragments, files);
+            files.set_source(&format!("{directory}/{stub}.java"), world_source);
         }
 
-        for (name, fragments) in &self.interface_fragments {
-            let (package, name) = split_qualified_name(name);
+        for (qualified_name, fragments) in &self.interface_fragments {
+            let (package, name) = split_qualified_name(qualified_name);
 
             let b = fragments
                 .iter()
@@ -416,7 +618,7 @@ impl WorldGenerator for TeaVmJava {
                 &mut body,
                 "package {package};
 
-                 {IMPORTS}
+                 {imports}
 
                  public final class {name} {{
                      private {name}() {{}}
@@ -427,13 +629,15 @@ impl WorldGenerator for TeaVmJava {
             );
 
             let directory = package.replace('.', "/");
-            files.push(
-                &format!("{directory}/{name}.java"),
-                indent(&body).as_bytes(),
-            );
+            let path = format!("{directory}/{name}.java");
+            files.push(&path, indent(&body).as_bytes());
+            let source = &self.interface_sources[qualified_name];
+            files.set_source(&path, source.clone());
 
             if self.opts.generate_stub {
-                generate_stub(&package, format!("{name}Impl"), fragments, files);
+                let stub = self.stub_name(name);
+                generate_stub(&package, stub.clone(), fragments, files);
+                files.set_source(&format!("{directory}/{stub}.java"), source.clone());
             }
         }
     }
@@ -445,6 +649,9 @@ struct InterfaceGenerator<'a> {
     gen: &'a mut TeaVmJava,
     resolve: &'a Resolve,
     name: &'a str,
+    /// The interface being generated, or `None` for world-level items.
+    key: Option<&'a WorldKey>,
+    direction: Direction,
 }
 
 impl InterfaceGenerator<'_> {
@@ -483,17 +690,18 @@ impl InterfaceGenerator<'_> {
     }
 
     fn import(&mut self, module: &str, func: &Function) {
-        if func.kind != FunctionKind::Freestanding {
-            todo!("resources");
-        }
-
         let mut bindgen = FunctionBindgen::new(
             self,
             &func.name,
             func.params
                 .iter()
-                .map(|(name, _)| name.to_java_ident())
+                .enumerate()
+                .map(|(i, (name, _))| match func.kind {
+                    FunctionKind::Method(_) if i == 0 => "this".into(),
+                    _ => name.to_java_ident(),
+                })
                 .collect(),
+            true,
         );
 
         abi::call(
@@ -527,7 +735,7 @@ impl InterfaceGenerator<'_> {
             _ => unreachable!(),
         };
 
-        let camel_name = func.name.to_upper_camel_case();
+        let camel_name = wasm_func_name(&func.name);
 
         let params = sig
             .params
@@ -563,6 +771,7 @@ impl InterfaceGenerator<'_> {
             self,
             &func.name,
             (0..sig.params.len()).map(|i| format!("p{i}")).collect(),
+            false,
         );
 
         abi::call(
@@ -583,7 +792,7 @@ impl InterfaceGenerator<'_> {
             _ => unreachable!(),
         };
 
-        let camel_name = func.name.to_upper_camel_case();
+        let camel_name = wasm_func_name(&func.name);
 
         let params = sig
             .params
@@ -622,6 +831,7 @@ impl InterfaceGenerator<'_> {
                 self,
                 "INVALID",
                 (0..sig.results.len()).map(|i| format!("p{i}")).collect(),
+                false,
             );
 
             abi::post_return(bindgen.gen.resolve, func, &mut bindgen);
@@ -641,6 +851,10 @@ impl InterfaceGenerator<'_> {
 
         if self.gen.opts.generate_stub {
             let sig = self.sig_string(func, true);
+            let sig = match func.kind {
+                FunctionKind::Method(_) => format!("@Override\n{sig}"),
+                _ => sig,
+            };
 
             uwrite!(
                 self.stub,
@@ -698,7 +912,14 @@ impl InterfaceGenerator<'_> {
 
                         format!("{}Tuple{count}{params}", self.gen.qualifier())
                     }
-                    TypeDefKind::Option(ty) => self.type_name_boxed(ty, qualifier),
+                    TypeDefKind::Option(ty) => {
+                        let ty = self.type_name_boxed(ty, qualifier);
+                        if self.gen.opts.optional {
+                            format!("Optional<{ty}>")
+                        } else {
+                            ty
+                        }
+                    }
                     TypeDefKind::Result(result) => {
                         self.gen.needs_result = true;
                         let mut name = |ty: &Option<Type>| {
@@ -715,6 +936,9 @@ impl InterfaceGenerator<'_> {
 
                         format!("{}Result<{ok}, {err}>", self.gen.qualifier())
                     }
+                    TypeDefKind::Handle(Handle::Own(ty) | Handle::Borrow(ty)) => {
+                        self.type_name_with_qualifier(&Type::Id(*ty), qualifier)
+                    }
                     _ => {
                         if let Some(name) = &ty.name {
                             format!(
@@ -789,10 +1013,32 @@ impl InterfaceGenerator<'_> {
     }
 
     fn sig_string(&mut self, func: &Function, qualifier: bool) -> String {
-        let name = func.name.to_java_ident();
+        let modifiers = match func.kind {
+            FunctionKind::Method(_) => "public",
+            _ => "public static",
+        };
+        format!("{modifiers} {}", self.sig_body(func, qualifier))
+    }
 
+    /// Renders the return type, name and parameters of `func`.
+    ///
+    /// Resource methods omit their `self` parameter since they're generated
+    /// as instance methods, and resource constructors become a static `create`
+    /// factory.
+    fn sig_body(&mut self, func: &Function, qualifier: bool) -> String {
+        let name = match func.kind {
+            FunctionKind::Freestanding => func.name.to_java_ident(),
+            FunctionKind::Method(_) | FunctionKind::Static(_) => func.item_name().to_java_ident(),
+            FunctionKind::Constructor(_) => "create".into(),
+        };
+
+        let exception_result = self.exception_result(func);
         let result_type = match func.results.len() {
             0 => "void".into(),
+            1 if exception_result.is_some() => match &exception_result.as_ref().unwrap().ok {
+                Some(ty) => self.type_name_with_qualifier(ty, qualifier),
+                None => "void".into(),
+            },
             1 => {
                 self.type_name_with_qualifier(func.results.iter_types().next().unwrap(), qualifier)
             }
@@ -810,9 +1056,15 @@ impl InterfaceGenerator<'_> {
             }
         };
 
+        let skip = match func.kind {
+            FunctionKind::Method(_) => 1,
+            _ => 0,
+        };
+
         let params = func
             .params
             .iter()
+            .skip(skip)
             .map(|(name, ty)| {
                 let ty = self.type_name_with_qualifier(ty, qualifier);
                 let name = name.to_java_ident();
@@ -821,7 +1073,126 @@ impl InterfaceGenerator<'_> {
             .collect::<Vec<_>>()
             .join(", ");
 
-        format!("public static {result_type} {name}({params})")
+        let throws = match &exception_result {
+            Some(result) => format!(" throws {}", self.exception_name(result.err.as_ref())),
+            None => String::new(),
+        };
+
+        format!("{result_type} {name}({params}){throws}")
+    }
+
+    /// Returns the exception class thrown in place of returning `err`, and
+    /// registers it to be generated.
+    ///
+    /// Each `err` type gets an exception class of its own carrying the value,
+    /// while results without an `err` type throw a plain `WitException`.
+    /// Exceptions for named types are nested in the class defining the type,
+    /// and all others in the world's class.
+    fn exception_name(&mut self, err: Option<&Type>) -> String {
+        self.gen.needs_wit_exception = true;
+        let world = self.gen.name.clone();
+        let Some(mut ty) = err.copied() else {
+            return format!("{world}.WitException");
+        };
+        while let Type::Id(id) = ty {
+            match &self.resolve.types[id].kind {
+                TypeDefKind::Type(t) => ty = *t,
+                _ => break,
+            }
+        }
+        let payload = self.type_name_boxed(&ty, true);
+        // Only these are generated as classes of their own.
+        let named = match ty {
+            Type::Id(id) => matches!(
+                self.resolve.types[id].kind,
+                TypeDefKind::Record(_)
+                    | TypeDefKind::Flags(_)
+                    | TypeDefKind::Variant(_)
+                    | TypeDefKind::Enum(_)
+                    | TypeDefKind::Resource
+                    | TypeDefKind::Handle(_)
+            ),
+            _ => false,
+        };
+        let (class, stem) = match payload.rsplit_once('.') {
+            Some((class, name)) if named => (class.to_string(), name.to_string()),
+            _ => (world, self.exception_stem(&ty)),
+        };
+        let exceptions = self.gen.exceptions.entry(class.clone()).or_default();
+        let mut name = format!("{stem}Exception");
+        let mut count = 1;
+        // Distinct types nested in anonymous ones may share a name.
+        while exceptions.get(&name).is_some_and(|other| *other != payload) {
+            count += 1;
+            name = format!("{stem}{count}Exception");
+        }
+        exceptions.insert(name.clone(), payload);
+        format!("{class}.{name}")
+    }
+
+    /// Returns the name of the exception class for the `err` type `ty`
+    /// without its `Exception` suffix.
+    fn exception_stem(&self, ty: &Type) -> String {
+        match ty {
+            Type::Bool => "Bool".into(),
+            Type::U8 => "U8".into(),
+            Type::U16 => "U16".into(),
+            Type::U32 => "U32".into(),
+            Type::U64 => "U64".into(),
+            Type::S8 => "S8".into(),
+            Type::S16 => "S16".into(),
+            Type::S32 => "S32".into(),
+            Type::S64 => "S64".into(),
+            Type::Float32 => "Float32".into(),
+            Type::Float64 => "Float64".into(),
+            Type::Char => "Char".into(),
+            Type::String => "String".into(),
+            Type::Id(id) => {
+                let ty = &self.resolve.types[*id];
+                if let Some(name) = &ty.name {
+                    return name.to_upper_camel_case();
+                }
+                let stem = |ty: &Option<Type>| {
+                    ty.as_ref()
+                        .map(|ty| self.exception_stem(ty))
+                        .unwrap_or_default()
+                };
+                match &ty.kind {
+                    TypeDefKind::Type(ty) => self.exception_stem(ty),
+                    TypeDefKind::List(ty) => format!("{}List", self.exception_stem(ty)),
+                    TypeDefKind::Option(ty) => format!("Optional{}", self.exception_stem(ty)),
+                    TypeDefKind::Tuple(tuple) => {
+                        tuple.types.iter().fold("Tuple".to_string(), |name, ty| {
+                            name + &self.exception_stem(ty)
+                        })
+                    }
+                    TypeDefKind::Result(result) => {
+                        format!("Result{}{}", stem(&result.ok), stem(&result.err))
+                    }
+                    TypeDefKind::Handle(Handle::Own(ty) | Handle::Borrow(ty)) => {
+                        self.exception_stem(&Type::Id(*ty))
+                    }
+                    _ => "Wit".into(),
+                }
+            }
+        }
+    }
+
+    /// Returns the `result` returned by `func` if its `err` value is thrown
+    /// as an exception rather than returned.
+    fn exception_result(&self, func: &Function) -> Option<Result_> {
+        if !self.gen.opts.exceptions {
+            return None;
+        }
+        match &func.results {
+            Results::Anon(Type::Id(id)) => {
+                match &self.resolve.types[dealias(self.resolve, *id)].kind {
+                    TypeDefKind::Result(result) => Some(result.clone()),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
     }
 }
 
@@ -858,6 +1229,24 @@ impl<'a> wit_bindgen_core::InterfaceGenerator<'a> for InterfaceGenerator<'a> {
             .collect::<Vec<_>>()
             .join("\n");
 
+        if self.gen.opts.records {
+            let instance = if record.fields.is_empty() {
+                format!("public static final {name} INSTANCE = new {name}();")
+            } else {
+                String::new()
+            };
+
+            uwrite!(
+                self.src,
+                "
+                public static record {name}({parameters}) {{
+                    {instance}
+                }}
+                "
+            );
+            return;
+        }
+
         let fields = if record.fields.is_empty() {
             format!("public static final {name} INSTANCE = new {name}();")
         } else {
@@ -890,8 +1279,128 @@ impl<'a> wit_bindgen_core::InterfaceGenerator<'a> for InterfaceGenerator<'a> {
     }
 
     fn type_resource(&mut self, id: TypeId, name: &str, docs: &Docs) {
-        _ = (id, name, docs);
-        todo!()
+        self.print_docs(docs);
+
+        let camel = name.to_upper_camel_case();
+        let module = self
+            .key
+            .map(|key| self.resolve.name_world_key(key))
+            .unwrap_or_else(|| "$root".into());
+
+        match self.direction {
+            Direction::Import => {
+                uwrite!(
+                    self.src,
+                    r#"
+                    public static final class {camel} implements AutoCloseable {{
+                        public final int handle;
+
+                        public {camel}(int handle) {{
+                            this.handle = handle;
+                        }}
+
+                        @Import(name = "[resource-drop]{name}", module = "{module}")
+                        private static native void wasmImportResourceDrop(int p0);
+
+                        @Override
+                        public void close() {{
+                            wasmImportResourceDrop(this.handle);
+                        }}
+                    "#
+                );
+
+                for func in resource_functions(self.resolve, id) {
+                    self.import(&module, func);
+                }
+
+                self.src.push_str("}\n");
+            }
+            Direction::Export => {
+                self.gen.exported_resources.insert(id);
+
+                let methods = resource_functions(self.resolve, id)
+                    .into_iter()
+                    .filter(|func| matches!(func.kind, FunctionKind::Method(_)))
+                    .map(|func| format!("public abstract {};", self.sig_body(func, false)))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+
+                uwrite!(
+                    self.src,
+                    r#"
+                    public static abstract class {camel} {{
+                        private static final HashMap<Integer, {camel}> wasmReps = new HashMap<>();
+                        private static int wasmNextRep = 1;
+                        private int wasmRep = 0;
+                        private int wasmHandles = 0;
+
+                        {methods}
+
+                        @Import(name = "[resource-new]{name}", module = "[export]{module}")
+                        private static native int wasmImportResourceNew(int p0);
+
+                        @Import(name = "[resource-rep]{name}", module = "[export]{module}")
+                        private static native int wasmImportResourceRep(int p0);
+
+                        @Import(name = "[resource-drop]{name}", module = "[export]{module}")
+                        private static native void wasmImportResourceDrop(int p0);
+
+                        public static int wasmLowerOwn({camel} value) {{
+                            int rep = wasmLowerBorrow(value);
+                            value.wasmHandles += 1;
+                            return wasmImportResourceNew(rep);
+                        }}
+
+                        public static int wasmLowerBorrow({camel} value) {{
+                            if (value.wasmRep == 0) {{
+                                value.wasmRep = wasmNextRep++;
+                                wasmReps.put(value.wasmRep, value);
+                            }}
+                            return value.wasmRep;
+                        }}
+
+                        public static {camel} wasmLiftOwn(int handle) {{
+                            {camel} value = wasmReps.get(wasmImportResourceRep(handle));
+                            wasmImportResourceDrop(handle);
+                            return value;
+                        }}
+
+                        public static {camel} wasmLiftBorrow(int rep) {{
+                            return wasmReps.get(rep);
+                        }}
+
+                        @Export(name = "{module}#[dtor]{name}")
+                        private static void wasmExportResourceDtor(int rep) {{
+                            {camel} value = wasmReps.get(rep);
+                            value.wasmHandles -= 1;
+                            if (value.wasmHandles == 0) {{
+                                wasmReps.remove(rep);
+                                value.wasmRep = 0;
+                            }}
+                        }}
+                    "#
+                );
+
+                if self.gen.opts.generate_stub {
+                    uwrite!(
+                        self.stub,
+                        "
+                        public static class {camel} extends {}.{camel} {{
+                        ",
+                        self.name
+                    );
+                }
+
+                for func in resource_functions(self.resolve, id) {
+                    self.export(Some(&module), func);
+                }
+
+                self.src.push_str("}\n");
+                if self.gen.opts.generate_stub {
+                    self.stub.push_str("}\n");
+                }
+            }
+        }
     }
 
     fn type_flags(&mut self, _id: TypeId, name: &str, flags: &Flags, docs: &Docs) {
@@ -945,10 +1454,43 @@ impl<'a> wit_bindgen_core::InterfaceGenerator<'a> for InterfaceGenerator<'a> {
         self.type_name(&Type::Id(id));
     }
 
-    fn type_variant(&mut self, _id: TypeId, name: &str, variant: &Variant, docs: &Docs) {
+    fn type_variant(&mut self, id: TypeId, name: &str, variant: &Variant, docs: &Docs) {
         self.print_docs(docs);
 
         let name = name.to_upper_camel_case();
+
+        if self.gen.opts.sealed_variants {
+            let qualified = self.type_name_with_qualifier(&Type::Id(id), true);
+            let cases = variant
+                .cases
+                .iter()
+                .map(|case| {
+                    let case_name = sealed_case_name(&qualified, &case.name);
+                    // The cases shadow the types they're named after, so the
+                    // type of their value has to be fully qualified.
+                    let value = match self.non_empty_type(case.ty.as_ref()) {
+                        Some(ty) => {
+                            let ty = self.type_name_with_qualifier(ty, true);
+                            format!("{} value", qualify_java_lang(&ty))
+                        }
+                        None => String::new(),
+                    };
+                    format!("record {case_name}({value}) implements {name} {{}}")
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            uwrite!(
+                self.src,
+                "
+                public static sealed interface {name} {{
+                    {cases}
+                }}
+                "
+            );
+            return;
+        }
+
         let tag_type = int_type(variant.tag());
 
         let constructors = variant
@@ -1069,6 +1611,14 @@ impl<'a> wit_bindgen_core::InterfaceGenerator<'a> for InterfaceGenerator<'a> {
         self.type_name(&Type::Id(id));
     }
 
+    fn type_future(&mut self, _id: TypeId, _name: &str, _ty: &Option<Type>, _docs: &Docs) {
+        unreachable!("`future` types are rejected by `supports_async_types`")
+    }
+
+    fn type_stream(&mut self, _id: TypeId, _name: &str, _stream: &Stream, _docs: &Docs) {
+        unreachable!("`stream` types are rejected by `supports_async_types`")
+    }
+
     fn type_builtin(&mut self, _id: TypeId, _name: &str, _ty: &Type, _docs: &Docs) {
         unimplemented!();
     }
@@ -1105,6 +1655,9 @@ struct FunctionBindgen<'a, 'b> {
     payloads: Vec<String>,
     cleanup: Vec<Cleanup>,
     needs_cleanup_list: bool,
+    /// Whether this is the body of an imported function, which returns the
+    /// lifted results to its caller.
+    in_import: bool,
 }
 
 impl<'a, 'b> FunctionBindgen<'a, 'b> {
@@ -1112,6 +1665,7 @@ impl<'a, 'b> FunctionBindgen<'a, 'b> {
         gen: &'b mut InterfaceGenerator<'a>,
         func_name: &'b str,
         params: Box<[String]>,
+        in_import: bool,
     ) -> FunctionBindgen<'a, 'b> {
         Self {
             gen,
@@ -1124,14 +1678,20 @@ impl<'a, 'b> FunctionBindgen<'a, 'b> {
             payloads: Vec::new(),
             cleanup: Vec::new(),
             needs_cleanup_list: false,
+            in_import,
         }
     }
 
+    /// Lowers a variant, or a `result` if `sealed` is `None`.
+    ///
+    /// `sealed` is the name of the sealed interface the variant is
+    /// represented by, if any.
     fn lower_variant(
         &mut self,
         cases: &[(&str, Option<Type>)],
         lowered_types: &[WasmType],
         op: &str,
+        sealed: Option<String>,
         results: &mut Vec<String>,
     ) {
         let blocks = self
@@ -1165,11 +1725,19 @@ impl<'a, 'b> FunctionBindgen<'a, 'b> {
             .enumerate()
             .map(
                 |(i, (((name, ty), Block { body, results, .. }), payload))| {
+                    let name = match &sealed {
+                        Some(sealed) => sealed_case_name(sealed, name),
+                        None => name.to_upper_camel_case(),
+                    };
                     let payload = if let Some(ty) = self.gen.non_empty_type(ty.as_ref()) {
                         let ty = self.gen.type_name(ty);
-                        let name = name.to_upper_camel_case();
 
-                        format!("{ty} {payload} = ({op}).get{name}();")
+                        match &sealed {
+                            Some(sealed) => {
+                                format!("{ty} {payload} = (({sealed}.{name}) ({op})).value();")
+                            }
+                            None => format!("{ty} {payload} = ({op}).get{name}();"),
+                        }
                     } else {
                         String::new()
                     };
@@ -1181,19 +1749,42 @@ impl<'a, 'b> FunctionBindgen<'a, 'b> {
                         .collect::<Vec<_>>()
                         .concat();
 
-                    format!(
-                        "case {i}: {{
-                         {payload}
-                         {body}
-                         {assignments}
-                         break;
-                     }}"
-                    )
+                    match &sealed {
+                        Some(sealed) => format!(
+                            "if (({op}) instanceof {sealed}.{name}) {{
+                                 {payload}
+                                 {body}
+                                 {assignments}
+                             }} else "
+                        ),
+                        None => format!(
+                            "case {i}: {{
+                                 {payload}
+                                 {body}
+                                 {assignments}
+                                 break;
+                             }}"
+                        ),
+                    }
                 },
             )
             .collect::<Vec<_>>()
             .join("\n");
 
+        if sealed.is_some() {
+            uwrite!(
+                self.src,
+                r#"
+                {declarations}
+
+                {cases} {{
+                    throw new AssertionError("invalid variant: " + ({op}));
+                }}
+                "#
+            );
+            return;
+        }
+
         uwrite!(
             self.src,
             r#"
@@ -1208,11 +1799,14 @@ impl<'a, 'b> FunctionBindgen<'a, 'b> {
         );
     }
 
+    /// Lifts a variant, or a `result`, constructing the records of its cases
+    /// if it's represented by a sealed interface.
     fn lift_variant(
         &mut self,
         ty: &Type,
         cases: &[(&str, Option<Type>)],
         op: &str,
+        sealed: bool,
         results: &mut Vec<String>,
     ) {
         let blocks = self
@@ -1220,6 +1814,7 @@ impl<'a, 'b> FunctionBindgen<'a, 'b> {
             .drain(self.blocks.len() - cases.len()..)
             .collect::<Vec<_>>();
 
+        let sealed = sealed.then(|| self.gen.type_name_with_qualifier(ty, true));
         let ty = self.gen.type_name(ty);
         let generics_position = ty.find('<');
         let lifted = self.locals.tmp("lifted");
@@ -1243,7 +1838,9 @@ impl<'a, 'b> FunctionBindgen<'a, 'b> {
 
                 let method = case_name.to_java_ident();
 
-                let call = if let Some(position) = generics_position {
+                let call = if let Some(sealed) = &sealed {
+                    format!("new {sealed}.{}", sealed_case_name(sealed, case_name))
+                } else if let Some(position) = generics_position {
                     let (ty, generics) = ty.split_at(position);
                     format!("{ty}.{generics}{method}")
                 } else {
@@ -1379,12 +1976,51 @@ impl Bindgen for FunctionBindgen<'_, '_> {
                 }
             },
 
-            Instruction::HandleLower { .. } | Instruction::HandleLift { .. } => todo!(),
+            Instruction::HandleLower { handle, .. } => {
+                let (Handle::Own(ty) | Handle::Borrow(ty)) = handle;
+                let ty = dealias(self.gen.resolve, *ty);
+                let op = &operands[0];
+                if self.gen.gen.exported_resources.contains(&ty) {
+                    let name = self.gen.type_name(&Type::Id(ty));
+                    // Every `own` handle shares the value's rep, which is kept
+                    // until the last of them is dropped, while borrows pass the
+                    // rep itself.
+                    match handle {
+                        Handle::Own(_) => results.push(format!("{name}.wasmLowerOwn({op})")),
+                        Handle::Borrow(_) => results.push(format!("{name}.wasmLowerBorrow({op})")),
+                    }
+                } else {
+                    results.push(format!("({op}).handle"));
+                }
+            }
+
+            Instruction::HandleLift { handle, .. } => {
+                let (Handle::Own(ty) | Handle::Borrow(ty)) = handle;
+                let ty = dealias(self.gen.resolve, *ty);
+                let name = self.gen.type_name(&Type::Id(ty));
+                let op = &operands[0];
+                if self.gen.gen.exported_resources.contains(&ty) {
+                    match handle {
+                        Handle::Own(_) => results.push(format!("{name}.wasmLiftOwn({op})")),
+                        Handle::Borrow(_) => results.push(format!("{name}.wasmLiftBorrow({op})")),
+                    }
+                } else {
+                    results.push(format!("new {name}({op})"));
+                }
+            }
+
+            Instruction::FutureLower { .. }
+            | Instruction::FutureLift { .. }
+            | Instruction::StreamLower { .. }
+            | Instruction::StreamLift { .. } => {
+                unreachable!("`future` and `stream` types are rejected by `supports_async_types`")
+            }
 
             Instruction::RecordLower { record, .. } => {
                 let op = &operands[0];
+                let accessor = if self.gen.gen.opts.records { "()" } else { "" };
                 for field in record.fields.iter() {
-                    results.push(format!("({op}).{}", field.name.to_java_ident()));
+                    results.push(format!("({op}).{}{accessor}", field.name.to_java_ident()));
                 }
             }
             Instruction::RecordLift { ty, .. } | Instruction::TupleLift { ty, .. } => {
@@ -1412,18 +2048,28 @@ impl Bindgen for FunctionBindgen<'_, '_> {
 
             Instruction::VariantLower {
                 variant,
+                ty,
                 results: lowered_types,
                 ..
-            } => self.lower_variant(
-                &variant
-                    .cases
-                    .iter()
-                    .map(|case| (case.name.deref(), case.ty))
-                    .collect::<Vec<_>>(),
-                lowered_types,
-                &operands[0],
-                results,
-            ),
+            } => {
+                let sealed = self
+                    .gen
+                    .gen
+                    .opts
+                    .sealed_variants
+                    .then(|| self.gen.type_name_with_qualifier(&Type::Id(*ty), true));
+                self.lower_variant(
+                    &variant
+                        .cases
+                        .iter()
+                        .map(|case| (case.name.deref(), case.ty))
+                        .collect::<Vec<_>>(),
+                    lowered_types,
+                    &operands[0],
+                    sealed,
+                    results,
+                )
+            }
 
             Instruction::VariantLift { variant, ty, .. } => self.lift_variant(
                 &Type::Id(*ty),
@@ -1433,6 +2079,7 @@ impl Bindgen for FunctionBindgen<'_, '_> {
                     .map(|case| (case.name.deref(), case.ty))
                     .collect::<Vec<_>>(),
                 &operands[0],
+                self.gen.gen.opts.sealed_variants,
                 results,
             ),
 
@@ -1461,12 +2108,17 @@ impl Bindgen for FunctionBindgen<'_, '_> {
                     .join("\n");
 
                 let op = &operands[0];
+                let optional = self.gen.gen.opts.optional;
 
                 let mut block = |ty: Option<&Type>, Block { body, results, .. }, payload| {
                     let payload = if let Some(ty) = self.gen.non_empty_type(ty) {
                         let ty = self.gen.type_name(ty);
 
-                        format!("{ty} {payload} = ({ty}) ({op});")
+                        if optional {
+                            format!("{ty} {payload} = ({op}).get();")
+                        } else {
+                            format!("{ty} {payload} = ({ty}) ({op});")
+                        }
                     } else {
                         String::new()
                     };
@@ -1487,13 +2139,18 @@ impl Bindgen for FunctionBindgen<'_, '_> {
 
                 let none = block(None, none, none_payload);
                 let some = block(Some(payload), some, some_payload);
+                let is_none = if optional {
+                    format!("!({op}).isPresent()")
+                } else {
+                    format!("({op}) == null")
+                };
 
                 uwrite!(
                     self.src,
                     r#"
                     {declarations}
 
-                    if (({op}) == null) {{
+                    if ({is_none}) {{
                         {none}
                     }} else {{
                         {some}
@@ -1512,9 +2169,17 @@ impl Bindgen for FunctionBindgen<'_, '_> {
 
                 let payload = if self.gen.non_empty_type(Some(*payload)).is_some() {
                     some.results.into_iter().next().unwrap()
+                } else if self.gen.gen.opts.optional {
+                    // `Optional`s can't hold `null`.
+                    format!("{}.INSTANCE", self.gen.type_name(payload))
                 } else {
                     "null".into()
                 };
+                let (none, payload) = if self.gen.gen.opts.optional {
+                    ("Optional.empty()", format!("Optional.of({payload})"))
+                } else {
+                    ("null", payload)
+                };
 
                 let some = some.body;
 
@@ -1525,7 +2190,7 @@ impl Bindgen for FunctionBindgen<'_, '_> {
 
                     switch ({op}) {{
                         case 0: {{
-                            {lifted} = null;
+                            {lifted} = {none};
                             break;
                         }}
 
@@ -1551,6 +2216,7 @@ impl Bindgen for FunctionBindgen<'_, '_> {
                 &[("ok", result.ok), ("err", result.err)],
                 lowered_types,
                 &operands[0],
+                None,
                 results,
             ),
 
@@ -1558,6 +2224,7 @@ impl Bindgen for FunctionBindgen<'_, '_> {
                 &Type::Id(*ty),
                 &[("ok", result.ok), ("err", result.err)],
                 &operands[0],
+                false,
                 results,
             ),
 
@@ -1761,7 +2428,7 @@ impl Bindgen for FunctionBindgen<'_, '_> {
                     _ => unreachable!(),
                 };
 
-                let func_name = self.func_name.to_upper_camel_case();
+                let func_name = wasm_func_name(self.func_name);
 
                 let operands = operands.join(", ");
 
@@ -1813,21 +2480,82 @@ impl Bindgen for FunctionBindgen<'_, '_> {
                     }
                 };
 
-                let module = self.gen.name;
-                let name = func.name.to_java_ident();
+                let module = self.gen.gen.stub_name(self.gen.name);
 
-                let args = operands.join(", ");
+                let call = match func.kind {
+                    FunctionKind::Freestanding => format!(
+                        "{module}.{}({})",
+                        func.name.to_java_ident(),
+                        operands.join(", ")
+                    ),
+                    FunctionKind::Method(_) => format!(
+                        "({}).{}({})",
+                        operands[0],
+                        func.item_name().to_java_ident(),
+                        operands[1..].join(", ")
+                    ),
+                    FunctionKind::Static(ty) | FunctionKind::Constructor(ty) => {
+                        let resource = self.gen.resolve.types[ty]
+                            .name
+                            .as_deref()
+                            .unwrap()
+                            .to_upper_camel_case();
+                        let name = match func.kind {
+                            FunctionKind::Constructor(_) => "create".into(),
+                            _ => func.item_name().to_java_ident(),
+                        };
+                        format!("{module}.{resource}.{name}({})", operands.join(", "))
+                    }
+                };
 
-                uwrite!(
-                    self.src,
-                    "
-                    {assignment}{module}Impl.{name}({args});
-                    {destructure}
-                    "
-                );
+                match self.gen.exception_result(func) {
+                    Some(result) => {
+                        let qualifier = self.gen.gen.qualifier();
+                        let lifted = &results[0];
+                        let ok = if result.ok.is_some() {
+                            format!("{lifted} = {qualifier}Result.ok({call});")
+                        } else {
+                            self.gen.gen.tuple_counts.insert(0);
+                            format!(
+                                "{call};
+                                 {lifted} = {qualifier}Result.ok({qualifier}Tuple0.INSTANCE);"
+                            )
+                        };
+                        let exception = self.locals.tmp("exception");
+                        let err = match &result.err {
+                            Some(_) => format!("{exception}.value"),
+                            None => {
+                                self.gen.gen.tuple_counts.insert(0);
+                                format!("{qualifier}Tuple0.INSTANCE")
+                            }
+                        };
+                        let class = self.gen.exception_name(result.err.as_ref());
+                        let ty = self
+                            .gen
+                            .type_name(func.results.iter_types().next().unwrap());
+                        uwrite!(
+                            self.src,
+                            "
+                            {ty} {lifted};
+                            try {{
+                                {ok}
+                            }} catch ({class} {exception}) {{
+                                {lifted} = {qualifier}Result.err({err});
+                            }}
+                            "
+                        );
+                    }
+                    None => uwrite!(
+                        self.src,
+                        "
+                        {assignment}{call};
+                        {destructure}
+                        "
+                    ),
+                }
             }
 
-            Instruction::Return { amt, .. } => {
+            Instruction::Return { amt, func } => {
                 for Cleanup {
                     address,
                     size,
@@ -1852,8 +2580,30 @@ impl Bindgen for FunctionBindgen<'_, '_> {
                     );
                 }
 
+                let exception_result = self.gen.exception_result(func).filter(|_| self.in_import);
                 match *amt {
                     0 => (),
+                    1 if exception_result.is_some() => {
+                        let result = exception_result.unwrap();
+                        let op = &operands[0];
+                        let qualifier = self.gen.gen.qualifier();
+                        let class = self.gen.exception_name(result.err.as_ref());
+                        let err = match result.err {
+                            Some(_) => format!("({op}).getErr()"),
+                            None => String::new(),
+                        };
+                        uwrite!(
+                            self.src,
+                            "
+                            if (({op}).tag == {qualifier}Result.ERR) {{
+                                throw new {class}({err});
+                            }}
+                            "
+                        );
+                        if result.ok.is_some() {
+                            uwriteln!(self.src, "return ({op}).getOk();");
+                        }
+                    }
                     1 => uwriteln!(self.src, "return {};", operands[0]),
                     count => {
                         let results = operands.join(", ");
@@ -2175,44 +2925,83 @@ fn is_primitive(ty: &Type) -> bool {
     )
 }
 
-fn world_name(resolve: &Resolve, world: WorldId) -> String {
-    format!(
-        "wit.worlds.{}",
-        resolve.worlds[world].name.to_upper_camel_case()
-    )
-}
-
-fn interface_name(resolve: &Resolve, name: &WorldKey, direction: Direction) -> String {
-    let pkg = match name {
-        WorldKey::Name(_) => None,
-        WorldKey::Interface(id) => {
-            let pkg = resolve.interfaces[*id].package.unwrap();
-            Some(resolve.packages[pkg].name.clone())
+/// Returns the methods, static functions and constructors of the resource `id`.
+fn resource_functions(resolve: &Resolve, id: TypeId) -> Vec<&Function> {
+    let belongs = |func: &Function| match func.kind {
+        FunctionKind::Freestanding => false,
+        FunctionKind::Method(ty) | FunctionKind::Static(ty) | FunctionKind::Constructor(ty) => {
+            ty == id
         }
     };
 
-    let name = match name {
-        WorldKey::Name(name) => name,
-        WorldKey::Interface(id) => resolve.interfaces[*id].name.as_ref().unwrap(),
+    match resolve.types[id].owner {
+        TypeOwner::Interface(interface) => resolve.interfaces[interface]
+            .functions
+            .values()
+            .filter(|func| belongs(func))
+            .collect(),
+        TypeOwner::World(world) => resolve.worlds[world]
+            .imports
+            .values()
+            .filter_map(|item| match item {
+                WorldItem::Function(func) if belongs(func) => Some(func),
+                _ => None,
+            })
+            .collect(),
+        TypeOwner::None => Vec::new(),
     }
-    .to_upper_camel_case();
+}
 
-    format!(
-        "wit.{}.{}{name}",
-        match direction {
-            Direction::Import => "imports",
-            Direction::Export => "exports",
-        },
-        if let Some(name) = &pkg {
-            format!(
-                "{}.{}.",
-                name.namespace.to_java_ident(),
-                name.name.to_java_ident()
-            )
-        } else {
-            String::new()
+/// Converts a function's WIT name into the suffix of its `wasmImport` and
+/// `wasmExport` helpers, e.g. `[method]blob.write` to `MethodBlobWrite`.
+fn wasm_func_name(name: &str) -> String {
+    name.replace('.', "-").to_upper_camel_case()
+}
+
+/// Returns the name of the record of the case `case` of the variant
+/// represented by the sealed interface `sealed`, a qualified name.
+fn sealed_case_name(sealed: &str, case: &str) -> String {
+    let name = case.to_upper_camel_case();
+    // Nested classes can't be named after a class enclosing them.
+    if sealed.split('.').any(|enclosing| enclosing == name) {
+        format!("{name}_")
+    } else {
+        name
+    }
+}
+
+/// Fully qualifies the names of the `java.lang` and `java.util` classes used
+/// by the type `ty`.
+fn qualify_java_lang(ty: &str) -> String {
+    let mut qualified = String::new();
+    let mut rest = ty;
+    while !rest.is_empty() {
+        let end = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'))
+            .unwrap_or(rest.len());
+        let (name, tail) = rest.split_at(end);
+        match name {
+            "String" | "Boolean" | "Byte" | "Short" | "Integer" | "Long" | "Float" | "Double" => {
+                qualified.push_str("java.lang.")
+            }
+            "ArrayList" | "Optional" => qualified.push_str("java.util."),
+            _ => {}
         }
-    )
+        qualified.push_str(name);
+        let separator = tail.chars().next().map_or(0, char::len_utf8);
+        qualified.push_str(&tail[..separator]);
+        rest = &tail[separator..];
+    }
+    qualified
+}
+
+fn dealias(resolve: &Resolve, mut id: TypeId) -> TypeId {
+    loop {
+        match &resolve.types[id].kind {
+            TypeDefKind::Type(Type::Id(that_id)) => id = *that_id,
+            _ => break id,
+        }
+    }
 }
 
 fn split_qualified_name(name: &str) -> (String, &str) {
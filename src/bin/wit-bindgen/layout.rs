@@ -0,0 +1,393 @@
+//! Implementation of the `layout` subcommand, which describes how the
+//! canonical ABI lays out a world's functions and types in core wasm.
+
+use std::fmt::Write;
+use wit_bindgen_core::abi::{AbiVariant, WasmType, MAX_FLAT_PARAMS, MAX_FLAT_RESULTS};
+use wit_bindgen_core::wit_parser::*;
+
+/// Renders a description of the canonical ABI layout of everything in `world`.
+pub fn render(resolve: &Resolve, world: WorldId) -> String {
+    let mut sizes = SizeAlign::default();
+    sizes.fill(resolve);
+    let mut layout = Layout {
+        resolve,
+        sizes,
+        out: String::new(),
+    };
+
+    let world = &resolve.worlds[world];
+    match world.package {
+        Some(pkg) => writeln!(
+            layout.out,
+            "world {} ({})",
+            world.name, resolve.packages[pkg].name
+        ),
+        None => writeln!(layout.out, "world {}", world.name),
+    }
+    .unwrap();
+
+    for (direction, items, variant) in [
+        ("import", &world.imports, AbiVariant::GuestImport),
+        ("export", &world.exports, AbiVariant::GuestExport),
+    ] {
+        let mut funcs = Vec::new();
+        let mut types = Vec::new();
+        for (key, item) in items.iter() {
+            match item {
+                WorldItem::Interface(id) => {
+                    let iface = &resolve.interfaces[*id];
+                    writeln!(
+                        layout.out,
+                        "\n{direction} interface {}",
+                        resolve.name_world_key(key)
+                    )
+                    .unwrap();
+                    for (_, ty) in iface.types.iter() {
+                        layout.type_(*ty);
+                    }
+                    for (_, func) in iface.functions.iter() {
+                        layout.func(func, variant);
+                    }
+                }
+                WorldItem::Function(func) => funcs.push(func),
+                WorldItem::Type(ty) => types.push(*ty),
+            }
+        }
+        if funcs.is_empty() && types.is_empty() {
+            continue;
+        }
+        writeln!(layout.out, "\n{direction} world items").unwrap();
+        for ty in types {
+            layout.type_(ty);
+        }
+        for func in funcs {
+            layout.func(func, variant);
+        }
+    }
+
+    layout.out
+}
+
+struct Layout<'a> {
+    resolve: &'a Resolve,
+    sizes: SizeAlign,
+    out: String,
+}
+
+impl Layout<'_> {
+    fn func(&mut self, func: &Function, variant: AbiVariant) {
+        let sig = self.resolve.wasm_signature(variant, func);
+        writeln!(
+            self.out,
+            "  func {}({}){}",
+            func.name,
+            func.params
+                .iter()
+                .map(|(name, ty)| format!("{name}: {}", self.type_name(ty)))
+                .collect::<Vec<_>>()
+                .join(", "),
+            match &func.results {
+                Results::Named(params) if params.is_empty() => String::new(),
+                Results::Named(params) => format!(
+                    " -> ({})",
+                    params
+                        .iter()
+                        .map(|(name, ty)| format!("{name}: {}", self.type_name(ty)))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                Results::Anon(ty) => format!(" -> {}", self.type_name(ty)),
+            }
+        )
+        .unwrap();
+        writeln!(
+            self.out,
+            "    core signature: ({}) -> ({})",
+            wasm_types(&sig.params),
+            wasm_types(&sig.results)
+        )
+        .unwrap();
+
+        let flat_params = self.flat_len(func.params.iter().map(|(_, ty)| ty));
+        if sig.indirect_params {
+            let (size, align) = self.sizes.params(func.params.iter().map(|(_, ty)| ty));
+            writeln!(
+                self.out,
+                "    params: {} exceed the limit of {}, passed in \
+                 memory ({size} bytes, align {align})",
+                flat_values(flat_params),
+                MAX_FLAT_PARAMS,
+            )
+            .unwrap();
+        } else {
+            writeln!(self.out, "    params: {}", flat_values(flat_params)).unwrap();
+        }
+
+        let flat_results = self.flat_len(func.results.iter_types());
+        if sig.retptr {
+            let (size, align) = self.sizes.params(func.results.iter_types());
+            let via = match variant {
+                AbiVariant::GuestImport => "caller-allocated return area",
+                AbiVariant::GuestExport => "returned pointer",
+            };
+            writeln!(
+                self.out,
+                "    results: {} exceed the limit of {}, passed in \
+                 memory via {via} ({size} bytes, align {align})",
+                flat_values(flat_results),
+                MAX_FLAT_RESULTS,
+            )
+            .unwrap();
+        } else {
+            writeln!(self.out, "    results: {}", flat_values(flat_results)).unwrap();
+        }
+    }
+
+    /// Number of core wasm values `types` flatten to, ignoring any limits.
+    fn flat_len<'a>(&self, types: impl IntoIterator<Item = &'a Type>) -> usize {
+        let mut flat = Vec::new();
+        for ty in types {
+            self.resolve.push_flat(ty, &mut flat);
+        }
+        flat.len()
+    }
+
+    fn type_(&mut self, id: TypeId) {
+        let ty = &self.resolve.types[id];
+        let name = ty.name.as_deref().unwrap_or("<anonymous>");
+        if let TypeDefKind::Resource = ty.kind {
+            writeln!(self.out, "  resource {name}").unwrap();
+            return;
+        }
+        let t = Type::Id(id);
+        writeln!(
+            self.out,
+            "  type {name}: {} (size {}, align {})",
+            self.kind_name(&ty.kind),
+            self.sizes.size(&t),
+            self.sizes.align(&t)
+        )
+        .unwrap();
+
+        match &ty.kind {
+            TypeDefKind::Record(r) => {
+                let offsets = self.sizes.field_offsets(r.fields.iter().map(|f| &f.ty));
+                for (field, (offset, ty)) in r.fields.iter().zip(offsets) {
+                    self.field(offset, &field.name, ty);
+                }
+            }
+            TypeDefKind::Tuple(t) => {
+                let offsets = self.sizes.field_offsets(t.types.iter());
+                for (i, (offset, ty)) in offsets.into_iter().enumerate() {
+                    self.field(offset, &i.to_string(), ty);
+                }
+            }
+            TypeDefKind::Variant(v) => {
+                self.payload(v.tag(), v.cases.iter().map(|c| c.ty.as_ref()));
+            }
+            TypeDefKind::Option(t) => self.payload(Int::U8, [None, Some(t)]),
+            TypeDefKind::Result(r) => self.payload(Int::U8, [r.ok.as_ref(), r.err.as_ref()]),
+            _ => {}
+        }
+    }
+
+    fn field(&mut self, offset: usize, name: &str, ty: &Type) {
+        writeln!(
+            self.out,
+            "    offset {offset}: {name}: {}",
+            self.type_name(ty)
+        )
+        .unwrap();
+    }
+
+    fn payload<'a>(&mut self, tag: Int, cases: impl IntoIterator<Item = Option<&'a Type>>) {
+        let tag_name = match tag {
+            Int::U8 => "u8",
+            Int::U16 => "u16",
+            Int::U32 => "u32",
+            Int::U64 => "u64",
+        };
+        let offset = self.sizes.payload_offset(tag, cases);
+        writeln!(
+            self.out,
+            "    offset 0: discriminant: {tag_name}\n    offset {offset}: payload"
+        )
+        .unwrap();
+    }
+
+    fn kind_name(&self, kind: &TypeDefKind) -> String {
+        match kind {
+            TypeDefKind::Record(_) => "record".to_string(),
+            TypeDefKind::Flags(_) => "flags".to_string(),
+            TypeDefKind::Tuple(_) => "tuple".to_string(),
+            TypeDefKind::Variant(_) => "variant".to_string(),
+            TypeDefKind::Enum(_) => "enum".to_string(),
+            TypeDefKind::Resource => "resource".to_string(),
+            _ => self.anonymous_name(kind),
+        }
+    }
+
+    /// Renders `ty` the way it would be written in a WIT document.
+    fn type_name(&self, ty: &Type) -> String {
+        match ty {
+            Type::Bool => "bool".to_string(),
+            Type::U8 => "u8".to_string(),
+            Type::U16 => "u16".to_string(),
+            Type::U32 => "u32".to_string(),
+            Type::U64 => "u64".to_string(),
+            Type::S8 => "s8".to_string(),
+            Type::S16 => "s16".to_string(),
+            Type::S32 => "s32".to_string(),
+            Type::S64 => "s64".to_string(),
+            Type::Float32 => "float32".to_string(),
+            Type::Float64 => "float64".to_string(),
+            Type::Char => "char".to_string(),
+            Type::String => "string".to_string(),
+            Type::Id(id) => {
+                let ty = &self.resolve.types[*id];
+                match &ty.name {
+                    Some(name) => name.clone(),
+                    None => self.anonymous_name(&ty.kind),
+                }
+            }
+        }
+    }
+
+    fn anonymous_name(&self, kind: &TypeDefKind) -> String {
+        let opt = |ty: Option<&Type>| match ty {
+            Some(ty) => self.type_name(ty),
+            None => "_".to_string(),
+        };
+        match kind {
+            TypeDefKind::Type(t) => self.type_name(t),
+            TypeDefKind::List(t) => format!("list<{}>", self.type_name(t)),
+            TypeDefKind::Option(t) => format!("option<{}>", self.type_name(t)),
+            TypeDefKind::Result(r) => {
+                format!("result<{}, {}>", opt(r.ok.as_ref()), opt(r.err.as_ref()))
+            }
+            TypeDefKind::Tuple(t) => format!(
+                "tuple<{}>",
+                t.types
+                    .iter()
+                    .map(|t| self.type_name(t))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            TypeDefKind::Handle(Handle::Own(id)) => {
+                format!("own<{}>", self.type_name(&Type::Id(*id)))
+            }
+            TypeDefKind::Handle(Handle::Borrow(id)) => {
+                format!("borrow<{}>", self.type_name(&Type::Id(*id)))
+            }
+            TypeDefKind::Future(t) => format!("future<{}>", opt(t.as_ref())),
+            TypeDefKind::Stream(s) => format!(
+                "stream<{}, {}>",
+                opt(s.element.as_ref()),
+                opt(s.end.as_ref())
+            ),
+            TypeDefKind::Record(_)
+            | TypeDefKind::Flags(_)
+            | TypeDefKind::Variant(_)
+            | TypeDefKind::Enum(_)
+            | TypeDefKind::Resource
+            | TypeDefKind::Unknown => "<anonymous>".to_string(),
+        }
+    }
+}
+
+fn flat_values(n: usize) -> String {
+    match n {
+        1 => "1 flat value".to_string(),
+        n => format!("{n} flat values"),
+    }
+}
+
+fn wasm_types(types: &[WasmType]) -> String {
+    types
+        .iter()
+        .map(|ty| match ty {
+            WasmType::I32 => "i32",
+            WasmType::I64 => "i64",
+            WasmType::F32 => "f32",
+            WasmType::F64 => "f64",
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot() {
+        let params = (b'a'..=b'q')
+            .map(|c| format!("{}: u64", c as char))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let wit = format!(
+            "
+                package my:layout
+
+                interface shapes {{
+                    record point {{
+                        tag: u8,
+                        x: float64,
+                        label: string,
+                        y: u16,
+                    }}
+
+                    variant shape {{
+                        dot(point),
+                        empty,
+                    }}
+
+                    area: func(s: shape) -> float64
+                    bounds: func(points: list<point>) -> tuple<point, point>
+                }}
+
+                world geometry {{
+                    import shapes
+
+                    export spill: func({params}) -> option<string>
+                }}
+            "
+        );
+        let mut resolve = Resolve::default();
+        let pkg = resolve
+            .push(UnresolvedPackage::parse("world.wit".as_ref(), &wit).unwrap())
+            .unwrap();
+        let world = resolve.select_world(pkg, None).unwrap();
+
+        let expected = format!(
+            "\
+world geometry (my:layout)
+
+import interface my:layout/shapes
+  type point: record (size 32, align 8)
+    offset 0: tag: u8
+    offset 8: x: float64
+    offset 16: label: string
+    offset 24: y: u16
+  type shape: variant (size 40, align 8)
+    offset 0: discriminant: u8
+    offset 8: payload
+  func area(s: shape) -> float64
+    core signature: (i32, i32, f64, i32, i32, i32) -> (f64)
+    params: 6 flat values
+    results: 1 flat value
+  func bounds(points: list<point>) -> tuple<point, point>
+    core signature: (i32, i32, i32) -> ()
+    params: 2 flat values
+    results: 10 flat values exceed the limit of 1, passed in memory via caller-allocated return area (64 bytes, align 8)
+
+export world items
+  func spill({params}) -> option<string>
+    core signature: (i32) -> (i32)
+    params: 17 flat values exceed the limit of 16, passed in memory (136 bytes, align 8)
+    results: 3 flat values exceed the limit of 1, passed in memory via returned pointer (12 bytes, align 4)
+"
+        );
+        assert_eq!(render(&resolve, world), expected);
+    }
+}
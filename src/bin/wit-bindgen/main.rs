@@ -0,0 +1,926 @@
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use serde::Deserialize;
+use serde_json::json;
+use similar::TextDiff;
+use std::ffi::{OsStr, OsString};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str;
+use wit_bindgen_core::{
+    push_deps, push_path, wit_parser, CoreSymbols, Files, WitItem, WorldGenerator,
+};
+use wit_parser::{Resolve, WorldId};
+
+mod layout;
+
+/// Helper for passing VERSION to opt.
+/// If CARGO_VERSION_INFO is set, use it, otherwise use CARGO_PKG_VERSION.
+fn version() -> &'static str {
+    option_env!("CARGO_VERSION_INFO").unwrap_or(env!("CARGO_PKG_VERSION"))
+}
+
+#[derive(Debug, Parser)]
+#[command(version = version())]
+enum Opt {
+    /// This generator outputs a Markdown file describing an interface.
+    #[cfg(feature = "markdown")]
+    Markdown {
+        #[clap(flatten)]
+        opts: wit_bindgen_markdown::Opts,
+        #[clap(flatten)]
+        args: Common,
+    },
+    /// Generates bindings for Rust guest modules.
+    #[cfg(feature = "rust")]
+    Rust {
+        #[clap(flatten)]
+        opts: wit_bindgen_rust::Opts,
+        #[clap(flatten)]
+        args: Common,
+    },
+    /// Generates bindings for C/CPP guest modules.
+    #[cfg(feature = "c")]
+    C {
+        #[clap(flatten)]
+        opts: wit_bindgen_c::Opts,
+        #[clap(flatten)]
+        args: Common,
+    },
+
+    /// Generates bindings for C++ guest modules.
+    #[cfg(feature = "cpp")]
+    Cpp {
+        #[clap(flatten)]
+        opts: wit_bindgen_cpp::Opts,
+        #[clap(flatten)]
+        args: Common,
+    },
+
+    /// Generates bindings for TeaVM-based Java guest modules.
+    #[cfg(feature = "teavm-java")]
+    TeavmJava {
+        #[clap(flatten)]
+        opts: wit_bindgen_teavm_java::Opts,
+        #[clap(flatten)]
+        args: Common,
+    },
+    /// Generates bindings for TinyGo-based Go guest modules.
+    #[cfg(feature = "go")]
+    TinyGo {
+        #[clap(flatten)]
+        opts: wit_bindgen_go::Opts,
+        #[clap(flatten)]
+        args: Common,
+    },
+
+    /// Generates all bindings described by a `wit-bindgen.toml` file.
+    ///
+    /// Each `[[target]]` in the file names a generator with `language`, the
+    /// `wit` input, and optionally a `world`, `out-dir`, and `deps`.
+    /// Generator-specific options go in a `[target.options]` table keyed by
+    /// their command line flag names.
+    Generate {
+        /// Path to the configuration file.
+        #[clap(long, default_value = "wit-bindgen.toml")]
+        config: PathBuf,
+
+        /// Checks that all generated files are up-to-date instead of writing
+        /// them.
+        #[clap(long)]
+        check: bool,
+    },
+
+    /// Prints the canonical ABI layout of the functions and types in a world.
+    ///
+    /// For each function this shows the flattened core wasm signature used
+    /// when it's imported or exported, and whether its parameters or results
+    /// are passed through linear memory instead. For each type this shows its
+    /// size and alignment along with the offsets of any fields or payloads.
+    Layout {
+        #[clap(flatten)]
+        input: Input,
+    },
+}
+
+#[derive(Debug, Parser)]
+struct Common {
+    /// Where to place output files
+    #[clap(long = "out-dir")]
+    out_dir: Option<PathBuf>,
+
+    #[clap(flatten)]
+    input: Input,
+
+    /// Also emit a JSON manifest with this name describing the generated files
+    /// and the core wasm symbols they import and export.
+    ///
+    /// Like other generated files this is placed in `--out-dir`.
+    #[clap(long, value_name = "NAME")]
+    manifest: Option<String>,
+
+    /// Indicates that no files are written and instead files are checked if
+    /// they're up-to-date with the source files.
+    ///
+    /// A unified diff is printed for each file which is out of date, and the
+    /// command fails if any are found.
+    #[clap(long)]
+    check: bool,
+}
+
+/// Arguments selecting the world to operate on.
+#[derive(Debug, Parser)]
+struct Input {
+    /// WIT document to generate bindings for.
+    ///
+    /// This may be a single `*.wit` file, a directory of WIT files, or a
+    /// binary `*.wasm` file containing either a component or a WIT package.
+    #[clap(value_name = "DOCUMENT", index = 1)]
+    wit: PathBuf,
+
+    /// World within the WIT document specified to generate bindings for.
+    ///
+    /// This can either be `foo` which is the default world in document `foo` or
+    /// it's `foo.bar` which is the world named `bar` within document `foo`.
+    #[clap(short, long)]
+    world: Option<String>,
+
+    /// Additional WIT packages to make available when resolving `DOCUMENT`.
+    ///
+    /// Each path may be a directory, a `*.wit` file, or a binary WIT package,
+    /// and is added in the order given before `DOCUMENT` itself. This option
+    /// may be specified multiple times, and a package must be listed after any
+    /// packages it depends on.
+    #[clap(long = "deps", value_name = "PATH")]
+    deps: Vec<PathBuf>,
+}
+
+fn main() -> Result<()> {
+    let summary = match Opt::parse() {
+        Opt::Generate { config, check } => generate_from_config(&config, check)?,
+        Opt::Layout { input } => {
+            let (resolve, world) = resolve_world(&input)?;
+            print!("{}", layout::render(&resolve, world));
+            return Ok(());
+        }
+        opt => run(opt)?,
+    };
+
+    if summary.stale > 0 {
+        bail!(
+            "{} of {} generated file(s) are not up to date",
+            summary.stale,
+            summary.files
+        );
+    }
+
+    Ok(())
+}
+
+/// Counts of files processed by [`run`].
+#[derive(Default)]
+struct Summary {
+    /// Number of files generated.
+    files: usize,
+    /// Number of files which were found to be out of date with `--check`.
+    stale: usize,
+}
+
+/// Runs the generator selected by `opt`, writing or checking its output.
+fn run(opt: Opt) -> Result<Summary> {
+    let mut files = Files::default();
+    let (generator, opt) = match opt {
+        #[cfg(feature = "markdown")]
+        Opt::Markdown { opts, args } => (opts.build(), args),
+        #[cfg(feature = "c")]
+        Opt::C { opts, args } => (opts.build(), args),
+        #[cfg(feature = "cpp")]
+        Opt::Cpp { opts, args } => (opts.build(), args),
+        #[cfg(feature = "rust")]
+        Opt::Rust { opts, args } => (opts.build(), args),
+        #[cfg(feature = "teavm-java")]
+        Opt::TeavmJava { opts, args } => (opts.build(), args),
+        #[cfg(feature = "go")]
+        Opt::TinyGo { opts, args } => (opts.build(), args),
+        Opt::Generate { .. } | Opt::Layout { .. } => unreachable!(),
+    };
+
+    gen_world(generator, &opt, &mut files)?;
+
+    let mut summary = Summary::default();
+    for (name, contents) in files.iter() {
+        let dst = match &opt.out_dir {
+            Some(path) => path.join(name),
+            None => name.into(),
+        };
+        summary.files += 1;
+
+        if opt.check {
+            if let Some(report) = check_up_to_date(&dst, contents)? {
+                print!("{report}");
+                summary.stale += 1;
+            }
+            continue;
+        }
+
+        println!("Generating {:?}", dst);
+
+        if let Some(parent) = dst.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create {:?}", parent))?;
+        }
+        std::fs::write(&dst, contents).with_context(|| format!("failed to write {:?}", dst))?;
+    }
+
+    Ok(summary)
+}
+
+/// The contents of a `wit-bindgen.toml` configuration file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    /// Each set of bindings to generate.
+    #[serde(rename = "target", default)]
+    targets: Vec<Target>,
+}
+
+/// A single `[[target]]` entry in a `wit-bindgen.toml` file.
+///
+/// Paths are relative to the directory containing the configuration file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct Target {
+    /// Name of the generator's subcommand, such as `rust` or `c`.
+    language: String,
+    wit: PathBuf,
+    world: Option<String>,
+    out_dir: Option<PathBuf>,
+    #[serde(default)]
+    deps: Vec<PathBuf>,
+    /// Generator-specific options, named after their command line flags.
+    #[serde(default)]
+    options: toml::Table,
+}
+
+/// Runs each target listed in the configuration file at `path`.
+fn generate_from_config(path: &Path, check: bool) -> Result<Summary> {
+    let contents =
+        std::fs::read_to_string(path).with_context(|| format!("failed to read {:?}", path))?;
+    let config: Config =
+        toml::from_str(&contents).with_context(|| format!("failed to parse {:?}", path))?;
+    let root = path.parent().unwrap_or(Path::new(""));
+
+    let mut summary = Summary::default();
+    for (i, target) in config.targets.iter().enumerate() {
+        let args = target_args(target, root, check)?;
+        let opt = Opt::try_parse_from(&args).with_context(|| {
+            format!(
+                "invalid options for target {} (`{}`) in {:?}",
+                i + 1,
+                target.language,
+                path
+            )
+        })?;
+        if let Opt::Generate { .. } | Opt::Layout { .. } = opt {
+            bail!("target {} in {:?} has an invalid language", i + 1, path);
+        }
+        let target_summary = run(opt)?;
+        summary.files += target_summary.files;
+        summary.stale += target_summary.stale;
+    }
+    Ok(summary)
+}
+
+/// Translates `target` into the equivalent command line arguments.
+fn target_args(target: &Target, root: &Path, check: bool) -> Result<Vec<OsString>> {
+    let mut args: Vec<OsString> = vec!["wit-bindgen".into(), target.language.clone().into()];
+    let flag = |name: &str, value: &dyn AsRef<OsStr>| {
+        let mut arg = OsString::from(format!("--{name}="));
+        arg.push(value);
+        arg
+    };
+    if let Some(world) = &target.world {
+        args.push(flag("world", world));
+    }
+    if let Some(out_dir) = &target.out_dir {
+        args.push(flag("out-dir", &root.join(out_dir)));
+    }
+    for dep in target.deps.iter() {
+        args.push(flag("deps", &root.join(dep)));
+    }
+    for (name, value) in target.options.iter() {
+        match value {
+            toml::Value::Boolean(true) => args.push(format!("--{name}").into()),
+            toml::Value::Boolean(false) => {}
+            toml::Value::Array(values) => {
+                for value in values {
+                    args.push(flag(name, &option_value(name, value)?));
+                }
+            }
+            toml::Value::Table(table) => {
+                // Maps such as Rust's `exports` are written as `key=value`
+                // pairs separated by commas.
+                let pairs = table
+                    .iter()
+                    .map(|(key, value)| Ok(format!("{key}={}", option_value(name, value)?)))
+                    .collect::<Result<Vec<_>>>()?;
+                args.push(flag(name, &pairs.join(",")));
+            }
+            value => args.push(flag(name, &option_value(name, value)?)),
+        }
+    }
+    if check {
+        args.push("--check".into());
+    }
+    args.push("--".into());
+    args.push(root.join(&target.wit).into());
+    Ok(args)
+}
+
+/// Renders a scalar option `value` as a command line argument.
+fn option_value(name: &str, value: &toml::Value) -> Result<String> {
+    Ok(match value {
+        toml::Value::String(s) => s.clone(),
+        toml::Value::Integer(i) => i.to_string(),
+        toml::Value::Float(f) => f.to_string(),
+        toml::Value::Boolean(b) => b.to_string(),
+        _ => bail!("unsupported value for option `{name}`: {value}"),
+    })
+}
+
+/// Checks whether `dst` already contains `contents`, returning a description
+/// of the difference if it doesn't.
+fn check_up_to_date(dst: &Path, contents: &[u8]) -> Result<Option<String>> {
+    let prev = match std::fs::read(dst) {
+        Ok(prev) => prev,
+        // A missing file is reported as a diff against empty contents.
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e).with_context(|| format!("failed to read {:?}", dst)),
+    };
+    if prev == contents {
+        return Ok(None);
+    }
+
+    let mut report = format!("not up to date: {}\n", dst.display());
+
+    // The contents differ. If it looks like textual contents, show a unified
+    // diff so that we can tell users what the problem is directly.
+    match (str::from_utf8(&prev), str::from_utf8(contents)) {
+        (Ok(utf8_prev), Ok(utf8_contents)) => {
+            if !utf8_prev
+                .chars()
+                .any(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t'))
+                && utf8_prev.lines().eq(utf8_contents.lines())
+            {
+                report.push_str(&format!("{} differs only in line endings (CRLF vs. LF). If this is a text file, configure git to mark the file as `text eol=lf`.\n", dst.display()));
+            } else {
+                let path = dst.display().to_string();
+                report.push_str(
+                    &TextDiff::from_lines(utf8_prev, utf8_contents)
+                        .unified_diff()
+                        .header(&path, &path)
+                        .to_string(),
+                );
+            }
+        }
+        // The contents are binary; just issue a generic note.
+        _ => report.push_str(&format!("binary contents of {} differ\n", dst.display())),
+    }
+    Ok(Some(report))
+}
+
+fn gen_world(
+    mut generator: Box<dyn WorldGenerator>,
+    opts: &Common,
+    files: &mut Files,
+) -> Result<()> {
+    let (resolve, world) = resolve_world(&opts.input)?;
+    generator
+        .generate(&resolve, world, files)
+        .with_context(|| {
+            format!(
+                "failed to generate bindings for world `{}`",
+                resolve.worlds[world].name
+            )
+        })?;
+
+    if let Some(name) = &opts.manifest {
+        let symbols = generator.core_symbols(&resolve, world);
+        let manifest = manifest(&resolve, world, &symbols, files);
+        files.push(name, serde_json::to_string_pretty(&manifest)?.as_bytes());
+    }
+
+    Ok(())
+}
+
+/// Parses the inputs described by `input` and selects the world they name.
+fn resolve_world(input: &Input) -> Result<(Resolve, WorldId)> {
+    let mut resolve = Resolve::default();
+    push_deps(&mut resolve, &input.deps)?;
+    let (parsed, _) = push_path(&mut resolve, &input.wit)?;
+    let world = parsed.select_world(&resolve, input.world.as_deref())?;
+    Ok((resolve, world))
+}
+
+/// Describes the generated `files` for `world`, and the core wasm `symbols`
+/// they use, as JSON.
+fn manifest(
+    resolve: &Resolve,
+    world: WorldId,
+    symbols: &CoreSymbols,
+    files: &Files,
+) -> serde_json::Value {
+    let world_item = WitItem::world(resolve, world);
+    let package = resolve.worlds[world]
+        .package
+        .map(|pkg| resolve.packages[pkg].name.to_string());
+    json!({
+        "package": package,
+        "world": resolve.worlds[world].name,
+        "files": files
+            .iter()
+            .map(|(name, _)| {
+                json!({
+                    "name": name,
+                    "source": item_json(files.source(name).unwrap_or(&world_item)),
+                })
+            })
+            .collect::<Vec<_>>(),
+        "imports": symbols
+            .imports
+            .iter()
+            .map(|import| {
+                json!({
+                    "module": import.module,
+                    "name": import.name,
+                    "source": item_json(&import.item),
+                })
+            })
+            .collect::<Vec<_>>(),
+        "exports": symbols
+            .exports
+            .iter()
+            .map(|export| {
+                json!({
+                    "name": export.name,
+                    "source": item_json(&export.item),
+                })
+            })
+            .collect::<Vec<_>>(),
+    })
+}
+
+fn item_json(item: &WitItem) -> serde_json::Value {
+    match item {
+        WitItem::World(name) => json!({ "world": name }),
+        WitItem::Interface(name) => json!({ "interface": name }),
+        WitItem::Function { interface, name } => json!({
+            "interface": interface,
+            "function": name,
+        }),
+        WitItem::Type { interface, name } => json!({
+            "interface": interface,
+            "type": name,
+        }),
+    }
+}
+
+#[test]
+fn verify_cli() {
+    use clap::CommandFactory;
+    Opt::command().debug_assert()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wit_parser::UnresolvedPackage;
+
+    /// Creates an empty scratch directory unique to the calling test.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join("wit-bindgen-cli-tests")
+            .join(format!("{name}-{}", std::process::id()));
+        drop(std::fs::remove_dir_all(&dir));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn markdown(dir: &Path, check: bool) -> Result<Summary> {
+        let wit = dir.join("world.wit");
+        let out = dir.join("out");
+        let mut args = vec![
+            OsString::from("wit-bindgen"),
+            "markdown".into(),
+            "--out-dir".into(),
+            out.into(),
+        ];
+        if check {
+            args.push("--check".into());
+        }
+        args.push(wit.into());
+        run(Opt::try_parse_from(args)?)
+    }
+
+    #[test]
+    fn check_reports_unified_diff() {
+        let dir = scratch_dir("unified-diff");
+        let dst = dir.join("file.txt");
+        std::fs::write(&dst, "a\nb\nc\n").unwrap();
+
+        assert_eq!(check_up_to_date(&dst, b"a\nb\nc\n").unwrap(), None);
+
+        let report = check_up_to_date(&dst, b"a\nB\nc\n").unwrap().unwrap();
+        let path = dst.display();
+        assert_eq!(
+            report,
+            format!(
+                "not up to date: {path}\n\
+                 --- {path}\n\
+                 +++ {path}\n\
+                 @@ -1,3 +1,3 @@\n \
+                 a\n\
+                 -b\n\
+                 +B\n \
+                 c\n"
+            )
+        );
+    }
+
+    #[test]
+    fn check_reports_line_endings_and_binary_contents() {
+        let dir = scratch_dir("line-endings");
+        let dst = dir.join("file.txt");
+        std::fs::write(&dst, "a\r\nb\r\n").unwrap();
+        let report = check_up_to_date(&dst, b"a\nb\n").unwrap().unwrap();
+        assert!(report.contains("differs only in line endings"), "{report}");
+
+        std::fs::write(&dst, [0xff, 0x00]).unwrap();
+        let report = check_up_to_date(&dst, &[0xff, 0x01]).unwrap().unwrap();
+        assert!(report.ends_with("differ\n"), "{report}");
+        assert!(report.contains("binary contents of"), "{report}");
+    }
+
+    #[test]
+    fn check_treats_missing_file_as_empty() {
+        let dir = scratch_dir("missing-file");
+        let dst = dir.join("missing.txt");
+
+        assert_eq!(check_up_to_date(&dst, b"").unwrap(), None);
+
+        let report = check_up_to_date(&dst, b"new\n").unwrap().unwrap();
+        assert!(report.contains("@@ -0,0 +1 @@\n+new\n"), "{report}");
+        assert!(!dst.exists());
+    }
+
+    #[test]
+    fn check_counts_stale_files() {
+        let dir = scratch_dir("stale-count");
+        std::fs::write(
+            dir.join("world.wit"),
+            "package my:test\n\nworld docs {\n  import f: func()\n}\n",
+        )
+        .unwrap();
+
+        // Nothing has been generated yet, so every file is stale and nothing
+        // gets written.
+        let summary = markdown(&dir, true).unwrap();
+        assert_eq!((summary.files, summary.stale), (2, 2));
+        assert!(!dir.join("out").exists());
+
+        let summary = markdown(&dir, false).unwrap();
+        assert_eq!((summary.files, summary.stale), (2, 0));
+
+        let summary = markdown(&dir, true).unwrap();
+        assert_eq!((summary.files, summary.stale), (2, 0));
+
+        std::fs::write(dir.join("out/docs.md"), "stale").unwrap();
+        let summary = markdown(&dir, true).unwrap();
+        assert_eq!((summary.files, summary.stale), (2, 1));
+        assert_eq!(
+            std::fs::read_to_string(dir.join("out/docs.md")).unwrap(),
+            "stale"
+        );
+    }
+
+    #[test]
+    fn binary_wit_package_input() {
+        let dir = scratch_dir("binary-input");
+        let mut resolve = Resolve::default();
+        let pkg = resolve
+            .push(
+                UnresolvedPackage::parse(
+                    "world.wit".as_ref(),
+                    "package my:test\n\nworld docs {\n  import f: func()\n}\n",
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        let wasm = wit_component::encode(Some(true), &resolve, pkg).unwrap();
+        std::fs::write(dir.join("world.wasm"), wasm).unwrap();
+
+        let input = Input {
+            wit: dir.join("world.wasm"),
+            world: Some("docs".to_string()),
+            deps: Vec::new(),
+        };
+        let (resolve, world) = resolve_world(&input).unwrap();
+        assert_eq!(resolve.worlds[world].name, "docs");
+        assert_eq!(resolve.worlds[world].imports.len(), 1);
+
+        std::fs::write(dir.join("world.wasm"), b"\0asm\x01\0\0\0\xff").unwrap();
+        let Err(err) = resolve_world(&input) else {
+            panic!("invalid wasm should fail to decode");
+        };
+        assert!(
+            err.to_string().starts_with("failed to decode wasm file"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn deps_are_pushed_in_order() {
+        let dir = scratch_dir("deps");
+        std::fs::write(
+            dir.join("a.wit"),
+            "package my:a\n\ninterface types {\n  type id = u32\n}\n",
+        )
+        .unwrap();
+        std::fs::create_dir(dir.join("b")).unwrap();
+        std::fs::write(
+            dir.join("b/b.wit"),
+            "package my:b\n\ninterface api {\n  use my:a/types.{id}\n  get: func() -> id\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("world.wit"),
+            "package my:test\n\nworld docs {\n  import my:b/api\n}\n",
+        )
+        .unwrap();
+
+        let markdown = |deps: &[&str]| {
+            let mut args = vec![
+                OsString::from("wit-bindgen"),
+                "markdown".into(),
+                "--out-dir".into(),
+                dir.join("out").into(),
+            ];
+            for dep in deps {
+                args.push("--deps".into());
+                args.push(dir.join(dep).into());
+            }
+            args.push(dir.join("world.wit").into());
+            run(Opt::try_parse_from(args)?)
+        };
+
+        let summary = markdown(&["a.wit", "b"]).unwrap();
+        assert_eq!((summary.files, summary.stale), (2, 0));
+        assert!(dir.join("out/docs.md").exists());
+
+        // `my:b` can't be resolved before the package it depends on.
+        let Err(err) = markdown(&["b", "a.wit"]) else {
+            panic!("out of order dependencies should fail");
+        };
+        assert_eq!(
+            err.to_string(),
+            format!("failed to push dependency {}", dir.join("b").display())
+        );
+
+        // Without `--deps` the imported package isn't known.
+        assert!(markdown(&[]).is_err());
+    }
+
+    fn parse_config(contents: &str) -> Result<Config> {
+        Ok(toml::from_str(contents)?)
+    }
+
+    #[test]
+    fn config_target_args() {
+        let config = parse_config(
+            r#"
+                [[target]]
+                language = "rust"
+                wit = "wit"
+                world = "guest"
+                out-dir = "src/bindings"
+                deps = ["deps/a", "deps/b.wit"]
+
+                [target.options]
+                std-feature = true
+                raw-strings = false
+                skip = ["foo", "bar"]
+                exports = { world = "MyWorld", "my:pkg/iface" = "MyIface" }
+                ownership = "owning"
+
+                [[target]]
+                language = "c"
+                wit = "c.wit"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.targets.len(), 2);
+
+        let root = Path::new("root");
+        let args = target_args(&config.targets[0], root, false).unwrap();
+        let expected = [
+            "wit-bindgen".to_string(),
+            "rust".to_string(),
+            "--world=guest".to_string(),
+            format!("--out-dir={}", root.join("src/bindings").display()),
+            format!("--deps={}", root.join("deps/a").display()),
+            format!("--deps={}", root.join("deps/b.wit").display()),
+            "--exports=my:pkg/iface=MyIface,world=MyWorld".to_string(),
+            "--ownership=owning".to_string(),
+            "--skip=foo".to_string(),
+            "--skip=bar".to_string(),
+            "--std-feature".to_string(),
+            "--".to_string(),
+            root.join("wit").display().to_string(),
+        ];
+        assert_eq!(args, expected.map(OsString::from));
+        let Opt::Rust { opts, args } = Opt::try_parse_from(&args).unwrap() else {
+            panic!("expected the rust generator");
+        };
+        assert!(opts.std_feature && !opts.raw_strings);
+        assert_eq!(opts.skip, ["foo", "bar"]);
+        assert_eq!(opts.exports.len(), 2);
+        assert_eq!(args.input.deps.len(), 2);
+        assert!(!args.check);
+
+        let args = target_args(&config.targets[1], root, true).unwrap();
+        let expected = [
+            "wit-bindgen".to_string(),
+            "c".to_string(),
+            "--check".to_string(),
+            "--".to_string(),
+            root.join("c.wit").display().to_string(),
+        ];
+        assert_eq!(args, expected.map(OsString::from));
+        let Opt::C { args, .. } = Opt::try_parse_from(&args).unwrap() else {
+            panic!("expected the c generator");
+        };
+        assert!(args.check);
+    }
+
+    #[test]
+    fn config_rejects_unknown_fields() {
+        let err = parse_config("[[target]]\nlanguage = \"c\"\nwit = \"a.wit\"\nout_dir = \"x\"\n")
+            .unwrap_err();
+        assert!(err.to_string().contains("unknown field `out_dir`"), "{err}");
+
+        let err = parse_config("[targets]\n").unwrap_err();
+        assert!(err.to_string().contains("unknown field `targets`"), "{err}");
+
+        let err = parse_config("[[target]]\nlanguage = \"c\"\n").unwrap_err();
+        assert!(err.to_string().contains("missing field `wit`"), "{err}");
+    }
+
+    #[test]
+    fn config_rejects_nested_option_values() {
+        let config = parse_config(
+            "[[target]]\nlanguage = \"c\"\nwit = \"a.wit\"\n[target.options]\nskip = [[\"a\"]]\n",
+        )
+        .unwrap();
+        let err = target_args(&config.targets[0], Path::new(""), false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unsupported value for option `skip`: [\"a\"]"
+        );
+    }
+
+    #[test]
+    fn config_generates_each_target() {
+        let dir = scratch_dir("config");
+        std::fs::write(
+            dir.join("world.wit"),
+            "package my:test\n\nworld docs {\n  import f: func()\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("wit-bindgen.toml"),
+            "[[target]]\nlanguage = \"markdown\"\nwit = \"world.wit\"\nout-dir = \"out\"\n",
+        )
+        .unwrap();
+        let config = dir.join("wit-bindgen.toml");
+
+        let summary = generate_from_config(&config, true).unwrap();
+        assert_eq!((summary.files, summary.stale), (2, 2));
+        let summary = generate_from_config(&config, false).unwrap();
+        assert_eq!((summary.files, summary.stale), (2, 0));
+        assert!(dir.join("out/docs.md").exists());
+        let summary = generate_from_config(&config, true).unwrap();
+        assert_eq!((summary.files, summary.stale), (2, 0));
+
+        std::fs::write(
+            &config,
+            "[[target]]\nlanguage = \"generate\"\nwit = \"world.wit\"\n",
+        )
+        .unwrap();
+        let Err(err) = generate_from_config(&config, false) else {
+            panic!("`generate` isn't a generator");
+        };
+        assert!(err.to_string().contains("invalid"), "{err}");
+    }
+
+    /// Runs `args` with a manifest named `manifest.json`, returning it.
+    fn run_manifest(dir: &Path, args: &[&str]) -> serde_json::Value {
+        let mut argv = vec![OsString::from("wit-bindgen")];
+        argv.extend(args.iter().map(OsString::from));
+        argv.extend([
+            "--manifest".into(),
+            "manifest.json".into(),
+            "--out-dir".into(),
+            dir.join("out").into(),
+            dir.join("world.wit").into(),
+        ]);
+        run(Opt::try_parse_from(argv).unwrap()).unwrap();
+        let manifest = std::fs::read_to_string(dir.join("out/manifest.json")).unwrap();
+        serde_json::from_str(&manifest).unwrap()
+    }
+
+    fn symbol_names(manifest: &serde_json::Value, kind: &str) -> Vec<String> {
+        manifest[kind]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|symbol| match symbol["module"].as_str() {
+                Some(module) => format!("{module}/{}", symbol["name"].as_str().unwrap()),
+                None => symbol["name"].as_str().unwrap().to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn manifest_reports_rust_symbols() {
+        let dir = scratch_dir("rust-symbols");
+        std::fs::write(
+            dir.join("world.wit"),
+            "package my:test\n\n\
+             world guest {\n  \
+               import next: func() -> future<u32>\n  \
+               export greet: func(name: string) -> string\n\
+             }\n",
+        )
+        .unwrap();
+
+        let manifest = run_manifest(
+            &dir,
+            &[
+                "rust",
+                "--exports=world=Guest",
+                "--export-prefix=pre_",
+                "--skip-component-type",
+            ],
+        );
+        assert_eq!(
+            symbol_names(&manifest, "exports"),
+            ["pre_greet", "pre_cabi_post_greet", "cabi_realloc"]
+        );
+        assert_eq!(
+            symbol_names(&manifest, "imports"),
+            [
+                "$root/next",
+                "$root/[future-new-0]next",
+                "$root/[future-read-0]next",
+                "$root/[future-write-0]next",
+                "$root/[future-close-readable-0]next",
+                "$root/[future-close-writable-0]next",
+                "$root/[task-wait]",
+                "$root/[task-poll]",
+            ]
+        );
+        assert_eq!(
+            manifest["imports"][1]["source"],
+            json!({ "interface": null, "function": "next" })
+        );
+        assert_eq!(
+            manifest["files"],
+            json!([
+                { "name": "guest.rs", "source": { "world": "guest" } },
+            ])
+        );
+    }
+
+    #[test]
+    fn manifest_reports_go_file_per_interface_sources() {
+        let dir = scratch_dir("go-sources");
+        std::fs::write(
+            dir.join("world.wit"),
+            "package my:test\n\n\
+             interface api {\n  get: func() -> list<u8>\n}\n\n\
+             world guest {\n  import api\n}\n",
+        )
+        .unwrap();
+
+        let manifest = run_manifest(&dir, &["tiny-go", "--file-per-interface", "--wasmimport"]);
+        assert_eq!(
+            manifest["files"],
+            json!([
+                { "name": "guest-my-test-api.go", "source": { "interface": "my:test/api" } },
+                { "name": "guest.go", "source": { "world": "guest" } },
+            ])
+        );
+        assert_eq!(symbol_names(&manifest, "exports"), ["cabi_realloc"]);
+        assert_eq!(symbol_names(&manifest, "imports"), ["my:test/api/get"]);
+    }
+}
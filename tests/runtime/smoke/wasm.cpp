@@ -0,0 +1,8 @@
+#include <smoke.hpp>
+#include <cstdio>
+
+void exports::smoke::thunk() {
+  test::smoke::imports::thunk();
+
+  printf("howdy\n");
+}
@@ -0,0 +1,16 @@
+#include <cassert>
+#include <strings.hpp>
+
+void exports::strings::test_imports() {
+  test::strings::imports::take_basic("latin utf16");
+  assert(test::strings::imports::return_unicode() == "🚀🚀🚀 𠈄𓀀");
+}
+
+std::string exports::strings::return_empty() {
+  return ""; // Exercise cabi_realloc new_size = 0
+}
+
+std::string exports::strings::roundtrip(std::string s) {
+  assert(!s.empty());
+  return s;
+}